@@ -1,11 +1,12 @@
 use crate::pairing::{Engine};
 use crate::pairing::ff::Field;
 
-use std::ops::{Add, Sub};
+use std::ops::{Add, Sub, Mul, Neg};
 use std::fmt;
 use std::error::Error;
 use std::io;
 use std::marker::PhantomData;
+use std::mem;
 
 /// Computations are expressed in terms of arithmetic circuits, in particular
 /// rank-1 quadratic constraint systems. The `Circuit` trait represents a
@@ -20,7 +21,7 @@ pub trait Circuit<E: Engine> {
 }
 
 /// Represents a variable in our constraint system.
-#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Variable(pub(crate) Index);
 
 impl Variable {
@@ -38,8 +39,9 @@ impl Variable {
 }
 
 /// Represents the index of either an input variable or
-/// auxillary variable.
-#[derive(Copy, Clone, PartialEq, Debug, Hash, Eq)]
+/// auxillary variable. Inputs are ordered before auxillary
+/// variables.
+#[derive(Copy, Clone, PartialEq, Debug, Hash, Eq, PartialOrd, Ord)]
 pub enum Index {
     Input(usize),
     Aux(usize)
@@ -47,6 +49,10 @@ pub enum Index {
 
 /// This represents a linear combination of some variables, with coefficients
 /// in the scalar field of a pairing-friendly elliptic curve group.
+///
+/// The combination is always kept canonical: terms are sorted by the index
+/// of their variable, every variable appears at most once and no coefficient
+/// is zero.
 #[derive(Clone)]
 pub struct LinearCombination<E: Engine>(pub(crate) Vec<(Variable, E::Fr)>);
 
@@ -60,13 +66,84 @@ impl<E: Engine> LinearCombination<E> {
     pub fn zero() -> LinearCombination<E> {
         LinearCombination(vec![])
     }
+
+    /// Returns the number of distinct variables with a non-zero coefficient.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns whether the combination has no terms.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Adds `coeff * var`, merging it into the existing term for `var`.
+    fn add_term(&mut self, var: Variable, coeff: E::Fr) {
+        // Variables are usually added in order of allocation,
+        // so the common case is an append.
+        let pos = match self.0.last() {
+            Some(&(last, _)) if last < var => Err(self.0.len()),
+            None => Err(0),
+            _ => self.0.binary_search_by(|&(v, _)| v.cmp(&var))
+        };
+
+        match pos {
+            Ok(pos) => {
+                self.0[pos].1.add_assign(&coeff);
+                if self.0[pos].1.is_zero() {
+                    self.0.remove(pos);
+                }
+            },
+            Err(pos) => {
+                if !coeff.is_zero() {
+                    self.0.insert(pos, (var, coeff));
+                }
+            }
+        }
+    }
+
+    /// Returns `self + coeff * other` in a single pass over both sorted
+    /// term lists.
+    fn add_scaled(self, other: &LinearCombination<E>, coeff: E::Fr) -> LinearCombination<E> {
+        let scale = coeff != E::Fr::one();
+        let mut ours = self.0.into_iter().peekable();
+        let mut theirs = other.0.iter().map(|&(var, mut c)| {
+            if scale {
+                c.mul_assign(&coeff);
+            }
+
+            (var, c)
+        }).peekable();
+
+        let mut result = Vec::with_capacity(ours.len() + theirs.len());
+        loop {
+            let term = match (ours.peek(), theirs.peek()) {
+                (Some(&(a, _)), Some(&(b, _))) if a == b => {
+                    let (var, mut c) = ours.next().unwrap();
+                    c.add_assign(&theirs.next().unwrap().1);
+
+                    (var, c)
+                },
+                (Some(&(a, _)), Some(&(b, _))) if b < a => theirs.next().unwrap(),
+                (Some(_), _) => ours.next().unwrap(),
+                (None, Some(_)) => theirs.next().unwrap(),
+                (None, None) => break
+            };
+
+            if !term.1.is_zero() {
+                result.push(term);
+            }
+        }
+
+        LinearCombination(result)
+    }
 }
 
 impl<E: Engine> Add<(E::Fr, Variable)> for LinearCombination<E> {
     type Output = LinearCombination<E>;
 
     fn add(mut self, (coeff, var): (E::Fr, Variable)) -> LinearCombination<E> {
-        self.0.push((var, coeff));
+        self.add_term(var, coeff);
 
         self
     }
@@ -101,57 +178,180 @@ impl<E: Engine> Sub<Variable> for LinearCombination<E> {
 impl<'a, E: Engine> Add<&'a LinearCombination<E>> for LinearCombination<E> {
     type Output = LinearCombination<E>;
 
-    fn add(mut self, other: &'a LinearCombination<E>) -> LinearCombination<E> {
-        for s in &other.0 {
-            self = self + (s.1, s.0);
-        }
-
-        self
+    fn add(self, other: &'a LinearCombination<E>) -> LinearCombination<E> {
+        self.add_scaled(other, E::Fr::one())
     }
 }
 
 impl<'a, E: Engine> Sub<&'a LinearCombination<E>> for LinearCombination<E> {
     type Output = LinearCombination<E>;
 
-    fn sub(mut self, other: &'a LinearCombination<E>) -> LinearCombination<E> {
-        for s in &other.0 {
-            self = self - (s.1, s.0);
-        }
+    fn sub(self, other: &'a LinearCombination<E>) -> LinearCombination<E> {
+        let mut minus_one = E::Fr::one();
+        minus_one.negate();
 
-        self
+        self.add_scaled(other, minus_one)
     }
 }
 
 impl<'a, E: Engine> Add<(E::Fr, &'a LinearCombination<E>)> for LinearCombination<E> {
     type Output = LinearCombination<E>;
 
-    fn add(mut self, (coeff, other): (E::Fr, &'a LinearCombination<E>)) -> LinearCombination<E> {
-        for s in &other.0 {
-            let mut tmp = s.1;
-            tmp.mul_assign(&coeff);
-            self = self + (tmp, s.0);
+    fn add(self, (coeff, other): (E::Fr, &'a LinearCombination<E>)) -> LinearCombination<E> {
+        self.add_scaled(other, coeff)
+    }
+}
+
+impl<'a, E: Engine> Sub<(E::Fr, &'a LinearCombination<E>)> for LinearCombination<E> {
+    type Output = LinearCombination<E>;
+
+    fn sub(self, (mut coeff, other): (E::Fr, &'a LinearCombination<E>)) -> LinearCombination<E> {
+        coeff.negate();
+
+        self.add_scaled(other, coeff)
+    }
+}
+
+impl<E: Engine> Mul<E::Fr> for LinearCombination<E> {
+    type Output = LinearCombination<E>;
+
+    fn mul(mut self, scalar: E::Fr) -> LinearCombination<E> {
+        if scalar.is_zero() {
+            self.0.clear();
+        } else {
+            for s in self.0.iter_mut() {
+                s.1.mul_assign(&scalar);
+            }
         }
 
         self
     }
 }
 
-impl<'a, E: Engine> Sub<(E::Fr, &'a LinearCombination<E>)> for LinearCombination<E> {
+impl<E: Engine> Neg for LinearCombination<E> {
     type Output = LinearCombination<E>;
 
-    fn sub(mut self, (coeff, other): (E::Fr, &'a LinearCombination<E>)) -> LinearCombination<E> {
-        for s in &other.0 {
-            let mut tmp = s.1;
-            tmp.mul_assign(&coeff);
-            self = self - (tmp, s.0);
+    fn neg(mut self) -> LinearCombination<E> {
+        for s in self.0.iter_mut() {
+            s.1.negate();
         }
 
         self
     }
 }
 
+/// Points at the part of a constraint system an error originated from.
+/// Not every context knows every detail, so all of them are optional.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ErrorLocation {
+    /// Full namespace path of the offending variable or constraint
+    pub path: Option<String>,
+    /// The offending variable
+    pub variable: Option<Index>,
+    /// Number of the offending constraint, in order of enforcement
+    pub constraint: Option<usize>
+}
+
+impl ErrorLocation {
+    pub fn path<P: Into<String>>(path: P) -> Self {
+        ErrorLocation {
+            path: Some(path.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn variable(variable: Index) -> Self {
+        ErrorLocation {
+            variable: Some(variable),
+            ..Default::default()
+        }
+    }
+
+    pub fn constraint(constraint: usize) -> Self {
+        ErrorLocation {
+            constraint: Some(constraint),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_path<P: Into<String>>(mut self, path: P) -> Self {
+        self.path = Some(path.into());
+
+        self
+    }
+
+    pub fn with_variable(mut self, variable: Index) -> Self {
+        self.variable = Some(variable);
+
+        self
+    }
+
+    pub fn with_constraint(mut self, constraint: usize) -> Self {
+        self.constraint = Some(constraint);
+
+        self
+    }
+
+    /// Location of a variable allocated under the namespace, with
+    /// its full path.
+    pub(crate) fn in_namespace(variable: Index, namespace: &[String], name: String) -> Self {
+        ErrorLocation::variable(variable).with_path(namespace_path(namespace, name))
+    }
+
+    // Fills in whatever we don't know yet from a less specific location
+    fn merge(&mut self, other: ErrorLocation) {
+        if self.path.is_none() {
+            self.path = other.path;
+        }
+        if self.variable.is_none() {
+            self.variable = other.variable;
+        }
+        if self.constraint.is_none() {
+            self.constraint = other.constraint;
+        }
+    }
+}
+
+/// Joins the namespace and the name into a full path.
+pub(crate) fn namespace_path(namespace: &[String], name: String) -> String {
+    let mut path = namespace.join("/");
+    if path.is_empty() {
+        return name;
+    }
+    path.push('/');
+    path.push_str(&name);
+
+    path
+}
+
+impl fmt::Display for ErrorLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let mut separator = "";
+        if let Some(ref path) = self.path {
+            write!(f, "`{}`", path)?;
+            separator = ", ";
+        }
+        if let Some(variable) = self.variable {
+            match variable {
+                Index::Input(i) => write!(f, "{}input variable {}", separator, i)?,
+                Index::Aux(i) => write!(f, "{}auxillary variable {}", separator, i)?
+            }
+            separator = ", ";
+        }
+        if let Some(constraint) = self.constraint {
+            write!(f, "{}constraint {}", separator, constraint)?;
+        }
+
+        Ok(())
+    }
+}
+
 /// This is an error that could occur during circuit synthesis contexts,
 /// such as CRS generation, proving or verification.
+///
+/// Errors raised while synthesizing may come back `Located`. Match on
+/// `kind()` rather than on the error itself, so that the location is seen
+/// through. Comparisons with `==` ignore the location as well.
 #[derive(Debug)]
 pub enum SynthesisError {
     /// During synthesis, we lacked knowledge of a variable assignment.
@@ -169,7 +369,21 @@ pub enum SynthesisError {
     /// During verification, our verifying key was malformed.
     MalformedVerifyingKey,
     /// During CRS generation, we observed an unconstrained auxillary variable
-    UnconstrainedVariable
+    UnconstrainedVariable,
+    /// During synthesis, a namespace was pushed or popped on a constraint
+    /// system that is not the root.
+    NamespaceMisuse,
+    /// A worker thread panicked or was dropped before producing its result
+    WorkerFailure,
+    /// A transcript exhausted its nonces without producing a challenge
+    ChallengeFailure,
+    /// During proof generation, the stored witness was created for a
+    /// different circuit
+    WitnessMismatch,
+    /// Verification failed before the proof itself could be checked
+    Verification(VerificationError),
+    /// Any of the above, together with where in the constraint system it happened
+    Located(Box<SynthesisError>, ErrorLocation)
 }
 
 impl From<io::Error> for SynthesisError {
@@ -178,6 +392,12 @@ impl From<io::Error> for SynthesisError {
     }
 }
 
+impl From<VerificationError> for SynthesisError {
+    fn from(e: VerificationError) -> SynthesisError {
+        SynthesisError::Verification(e)
+    }
+}
+
 impl SynthesisError {
     pub fn self_description(&self) -> &str {
         match *self {
@@ -188,7 +408,51 @@ impl SynthesisError {
             SynthesisError::UnexpectedIdentity => "encountered an identity element in the CRS",
             SynthesisError::IoError(_) => "encountered an I/O error",
             SynthesisError::MalformedVerifyingKey => "malformed verifying key",
-            SynthesisError::UnconstrainedVariable => "auxillary variable was unconstrained"
+            SynthesisError::UnconstrainedVariable => "auxillary variable was unconstrained",
+            SynthesisError::NamespaceMisuse => "namespaces can only be pushed and popped on the root constraint system",
+            SynthesisError::WorkerFailure => "a worker thread failed to produce its result",
+            SynthesisError::ChallengeFailure => "can not make challenge scalar",
+            SynthesisError::WitnessMismatch => "witness was created for a different circuit",
+            SynthesisError::Verification(ref e) => e.self_description(),
+            SynthesisError::Located(ref e, _) => e.self_description()
+        }
+    }
+
+    /// Attaches the location the error originated from. If the error
+    /// already has a location, only the missing details are filled in.
+    pub fn at(self, location: ErrorLocation) -> SynthesisError {
+        match self {
+            SynthesisError::Located(e, mut existing) => {
+                existing.merge(location);
+
+                SynthesisError::Located(e, existing)
+            },
+            e => SynthesisError::Located(Box::new(e), location)
+        }
+    }
+
+    /// The error itself, with any location stripped.
+    pub fn kind(&self) -> &SynthesisError {
+        match *self {
+            SynthesisError::Located(ref e, _) => e.kind(),
+            ref e => e
+        }
+    }
+
+    pub fn location(&self) -> Option<&ErrorLocation> {
+        match *self {
+            SynthesisError::Located(_, ref location) => Some(location),
+            _ => None
+        }
+    }
+}
+
+impl PartialEq for SynthesisError {
+    fn eq(&self, other: &SynthesisError) -> bool {
+        match (self.kind(), other.kind()) {
+            (SynthesisError::IoError(a), SynthesisError::IoError(b)) => a.kind() == b.kind(),
+            (SynthesisError::Verification(a), SynthesisError::Verification(b)) => a == b,
+            (a, b) => mem::discriminant(a) == mem::discriminant(b)
         }
     }
 }
@@ -201,11 +465,69 @@ impl Error for SynthesisError {
 
 impl fmt::Display for SynthesisError {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        if let &SynthesisError::IoError(ref e) = self {
-            write!(f, "I/O error: ")?;
-            e.fmt(f)
-        } else {
-            write!(f, "{}", self.self_description())
+        match *self {
+            SynthesisError::IoError(ref e) => {
+                write!(f, "I/O error: ")?;
+                e.fmt(f)
+            },
+            SynthesisError::Verification(ref e) => e.fmt(f),
+            SynthesisError::Located(ref e, ref location) => {
+                write!(f, "{} at {}", e, location)
+            },
+            _ => write!(f, "{}", self.self_description())
+        }
+    }
+}
+
+/// This is an error that could occur while verifying a proof, as opposed
+/// to the proof simply being invalid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerificationError {
+    /// The number of public inputs doesn't match the verifying key
+    InputCountMismatch {
+        expected: usize,
+        actual: usize
+    },
+    /// The verifying key is malformed
+    MalformedVerifyingKey,
+    /// An input required by the layout was not supplied
+    MissingInput(String),
+    /// An input was supplied that is not part of the layout
+    UnexpectedInput(String),
+    /// The layout names several inputs the same, so they can't be told apart
+    AmbiguousInput(String)
+}
+
+impl VerificationError {
+    pub fn self_description(&self) -> &str {
+        match *self {
+            VerificationError::InputCountMismatch { .. } => "wrong number of public inputs",
+            VerificationError::MalformedVerifyingKey => "malformed verifying key",
+            VerificationError::MissingInput(_) => "missing public input",
+            VerificationError::UnexpectedInput(_) => "unexpected public input",
+            VerificationError::AmbiguousInput(_) => "ambiguous public input"
+        }
+    }
+}
+
+impl Error for VerificationError {
+    fn description(&self) -> &str {
+        self.self_description()
+    }
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            VerificationError::InputCountMismatch { expected, actual } => {
+                write!(f, "{}: expected {}, got {}", self.self_description(), expected, actual)
+            },
+            VerificationError::MissingInput(ref name) |
+            VerificationError::UnexpectedInput(ref name) |
+            VerificationError::AmbiguousInput(ref name) => {
+                write!(f, "{} `{}`", self.self_description(), name)
+            },
+            _ => write!(f, "{}", self.self_description())
         }
     }
 }
@@ -265,6 +587,25 @@ pub trait ConstraintSystem<E: Engine>: Sized {
     /// downstream use; use `namespace` instead.
     fn pop_namespace(&mut self);
 
+    /// Same as `push_namespace`, but reports misuse as an error
+    /// instead of panicking.
+    fn try_push_namespace<NR, N>(&mut self, name_fn: N) -> Result<(), SynthesisError>
+        where NR: Into<String>, N: FnOnce() -> NR
+    {
+        self.push_namespace(name_fn);
+
+        Ok(())
+    }
+
+    /// Same as `pop_namespace`, but reports misuse as an error
+    /// instead of panicking.
+    fn try_pop_namespace(&mut self) -> Result<(), SynthesisError>
+    {
+        self.pop_namespace();
+
+        Ok(())
+    }
+
     /// Gets the "root" constraint system, bypassing the namespacing.
     /// Not intended for downstream use; use `namespace` instead.
     fn get_root(&mut self) -> &mut Self::Root;
@@ -280,6 +621,19 @@ pub trait ConstraintSystem<E: Engine>: Sized {
 
         Namespace(self.get_root(), PhantomData)
     }
+
+    /// Same as `namespace`, but fails instead of panicking if the
+    /// root constraint system refuses the namespace.
+    fn try_namespace<'a, NR, N>(
+        &'a mut self,
+        name_fn: N
+    ) -> Result<Namespace<'a, E, Self::Root>, SynthesisError>
+        where NR: Into<String>, N: FnOnce() -> NR
+    {
+        self.get_root().try_push_namespace(name_fn)?;
+
+        Ok(Namespace(self.get_root(), PhantomData))
+    }
 }
 
 /// This is a "namespaced" constraint system which borrows a constraint system (pushing
@@ -343,6 +697,17 @@ impl<'cs, E: Engine, CS: ConstraintSystem<E>> ConstraintSystem<E> for Namespace<
         panic!("only the root's pop_namespace should be called");
     }
 
+    fn try_push_namespace<NR, N>(&mut self, _: N) -> Result<(), SynthesisError>
+        where NR: Into<String>, N: FnOnce() -> NR
+    {
+        Err(SynthesisError::NamespaceMisuse)
+    }
+
+    fn try_pop_namespace(&mut self) -> Result<(), SynthesisError>
+    {
+        Err(SynthesisError::NamespaceMisuse)
+    }
+
     fn get_root(&mut self) -> &mut Self::Root
     {
         self.0.get_root()
@@ -410,6 +775,17 @@ impl<'cs, E: Engine, CS: ConstraintSystem<E>> ConstraintSystem<E> for &'cs mut C
         (**self).pop_namespace()
     }
 
+    fn try_push_namespace<NR, N>(&mut self, name_fn: N) -> Result<(), SynthesisError>
+        where NR: Into<String>, N: FnOnce() -> NR
+    {
+        (**self).try_push_namespace(name_fn)
+    }
+
+    fn try_pop_namespace(&mut self) -> Result<(), SynthesisError>
+    {
+        (**self).try_pop_namespace()
+    }
+
     fn get_root(&mut self) -> &mut Self::Root
     {
         (**self).get_root()
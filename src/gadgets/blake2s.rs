@@ -0,0 +1,279 @@
+//! BLAKE2s with a 256-bit digest, as specified in RFC 7693, with support
+//! for the personalization parameter.
+//!
+//! The input is a sequence of bytes given as bits, least significant bit
+//! of every byte first, which is also how the digest is returned. Every
+//! block costs 21280 constraints unless its words are constant.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::boolean::Boolean;
+use super::uint::UInt32;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+const IV: [u32; 8] = [
+    0x6A09E667, 0xBB67AE85, 0x3C6EF372, 0xA54FF53A,
+    0x510E527F, 0x9B05688C, 0x1F83D9AB, 0x5BE0CD19
+];
+
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0]
+];
+
+/// The length of a block in bits.
+pub const BLOCK_BITS: usize = 512;
+
+/// The length of a personalization string in bytes.
+pub const PERSONALIZATION_BYTES: usize = 8;
+
+// Rotation constants of the mixing function
+const R1: usize = 16;
+const R2: usize = 12;
+const R3: usize = 8;
+const R4: usize = 7;
+
+/// The mixing function G, which mixes two input words into four words
+/// of the state.
+#[allow(clippy::too_many_arguments)]
+fn mixing_g<E, CS>(
+    mut cs: CS,
+    v: &mut [UInt32],
+    a: usize,
+    b: usize,
+    c: usize,
+    d: usize,
+    x: &UInt32,
+    y: &UInt32
+) -> Result<(), SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    v[a] = UInt32::addmany(cs.namespace(|| "mixing step 1"), &[v[a].clone(), v[b].clone(), x.clone()])?;
+    v[d] = v[d].xor(cs.namespace(|| "mixing step 2"), &v[a])?.rotr(R1);
+    v[c] = UInt32::addmany(cs.namespace(|| "mixing step 3"), &[v[c].clone(), v[d].clone()])?;
+    v[b] = v[b].xor(cs.namespace(|| "mixing step 4"), &v[c])?.rotr(R2);
+    v[a] = UInt32::addmany(cs.namespace(|| "mixing step 5"), &[v[a].clone(), v[b].clone(), y.clone()])?;
+    v[d] = v[d].xor(cs.namespace(|| "mixing step 6"), &v[a])?.rotr(R3);
+    v[c] = UInt32::addmany(cs.namespace(|| "mixing step 7"), &[v[c].clone(), v[d].clone()])?;
+    v[b] = v[b].xor(cs.namespace(|| "mixing step 8"), &v[c])?.rotr(R4);
+
+    Ok(())
+}
+
+/// The compression function F. The offset `t` counts the bytes hashed
+/// so far including this block, and `f` marks the final block.
+fn blake2s_compression<E, CS>(
+    mut cs: CS,
+    h: &mut [UInt32],
+    m: &[UInt32],
+    t: u64,
+    f: bool
+) -> Result<(), SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(h.len(), 8);
+    assert_eq!(m.len(), 16);
+
+    let mut v = Vec::with_capacity(16);
+    v.extend_from_slice(h);
+    v.extend(IV.iter().map(|&iv| UInt32::constant(iv)));
+
+    assert_eq!(v.len(), 16);
+
+    v[12] = v[12].xor(cs.namespace(|| "first xor"), &UInt32::constant(t as u32))?;
+    v[13] = v[13].xor(cs.namespace(|| "second xor"), &UInt32::constant((t >> 32) as u32))?;
+
+    if f {
+        v[14] = v[14].xor(cs.namespace(|| "third xor"), &UInt32::constant(u32::MAX))?;
+    }
+
+    for (i, s) in SIGMA.iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("round {}", i));
+
+        mixing_g(cs.namespace(|| "mixing invocation 1"), &mut v, 0, 4, 8, 12, &m[s[0]], &m[s[1]])?;
+        mixing_g(cs.namespace(|| "mixing invocation 2"), &mut v, 1, 5, 9, 13, &m[s[2]], &m[s[3]])?;
+        mixing_g(cs.namespace(|| "mixing invocation 3"), &mut v, 2, 6, 10, 14, &m[s[4]], &m[s[5]])?;
+        mixing_g(cs.namespace(|| "mixing invocation 4"), &mut v, 3, 7, 11, 15, &m[s[6]], &m[s[7]])?;
+
+        mixing_g(cs.namespace(|| "mixing invocation 5"), &mut v, 0, 5, 10, 15, &m[s[8]], &m[s[9]])?;
+        mixing_g(cs.namespace(|| "mixing invocation 6"), &mut v, 1, 6, 11, 12, &m[s[10]], &m[s[11]])?;
+        mixing_g(cs.namespace(|| "mixing invocation 7"), &mut v, 2, 7, 8, 13, &m[s[12]], &m[s[13]])?;
+        mixing_g(cs.namespace(|| "mixing invocation 8"), &mut v, 3, 4, 9, 14, &m[s[14]], &m[s[15]])?;
+    }
+
+    for (i, h) in h.iter_mut().enumerate() {
+        let mut cs = cs.namespace(|| format!("h[{i}] ^ v[{i}] ^ v[{i} + 8]", i=i));
+
+        *h = h.xor(cs.namespace(|| "first xor"), &v[i])?;
+        *h = h.xor(cs.namespace(|| "second xor"), &v[i + 8])?;
+    }
+
+    Ok(())
+}
+
+/// Hashes the input bytes with the given personalization, and no key
+/// or salt. The personalization must be exactly eight bytes long.
+pub fn blake2s<E, CS>(
+    mut cs: CS,
+    input: &[Boolean],
+    personalization: &[u8]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(personalization.len(), PERSONALIZATION_BYTES);
+    assert!(input.len().is_multiple_of(8), "the input must consist of whole bytes");
+
+    // The parameter block sets a digest length of 32 bytes, a fanout
+    // and depth of 1, and the personalization
+    let mut h: Vec<_> = IV.iter().map(|&iv| UInt32::constant(iv)).collect();
+    h[0] = UInt32::constant(IV[0] ^ 0x01010000 ^ 32);
+    h[6] = UInt32::constant(IV[6] ^ LittleEndian::read_u32(&personalization[0..4]));
+    h[7] = UInt32::constant(IV[7] ^ LittleEndian::read_u32(&personalization[4..8]));
+
+    let mut blocks: Vec<Vec<UInt32>> = vec![];
+
+    for block in input.chunks(BLOCK_BITS) {
+        let mut this_block = Vec::with_capacity(16);
+        for word in block.chunks(32) {
+            let mut tmp = word.to_vec();
+            while tmp.len() < 32 {
+                tmp.push(Boolean::constant(false));
+            }
+            this_block.push(UInt32::from_bits(&tmp));
+        }
+        while this_block.len() < 16 {
+            this_block.push(UInt32::constant(0));
+        }
+        blocks.push(this_block);
+    }
+
+    // The empty message is hashed as a single block of zeros
+    if blocks.is_empty() {
+        blocks.push((0..16).map(|_| UInt32::constant(0)).collect());
+    }
+
+    let (last, rest) = blocks.split_last().expect("there is at least one block");
+
+    for (i, block) in rest.iter().enumerate() {
+        let cs = cs.namespace(|| format!("block {}", i));
+
+        blake2s_compression(cs, &mut h, block, ((i as u64) + 1) * 64, false)?;
+    }
+
+    {
+        let cs = cs.namespace(|| "final block");
+
+        blake2s_compression(cs, &mut h, last, (input.len() / 8) as u64, true)?;
+    }
+
+    Ok(h.iter().flat_map(|b| b.into_bits()).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bls12_381::Bls12;
+    use crate::gadgets::boolean::AllocatedBit;
+    use crate::r1cs::RecordingSystem;
+
+    use blake2_rfc::blake2s::Blake2s;
+    use byteorder::{ByteOrder, LittleEndian};
+    use rand::{Rng, thread_rng};
+
+    fn bytes_to_bits_le(bytes: &[u8]) -> Vec<bool> {
+        bytes.iter().flat_map(|&byte| (0..8).map(move |i| (byte >> i) & 1 == 1)).collect()
+    }
+
+    fn reference(input: &[u8], personalization: &[u8]) -> Vec<bool> {
+        // A 32-byte digest without a key, in sequential mode, with the
+        // personalization in the last two words
+        let mut params = [0x01010020, 0, 0, 0, 0, 0, 0, 0];
+        for (i, chunk) in personalization.chunks(4).enumerate() {
+            params[6 + i] = LittleEndian::read_u32(chunk);
+        }
+
+        let mut h = Blake2s::with_parameter_block(&params);
+        h.update(input);
+
+        bytes_to_bits_le(h.finalize().as_bytes())
+    }
+
+    fn alloc_bits<CS: ConstraintSystem<Bls12>>(mut cs: CS, bits: &[bool]) -> Vec<Boolean> {
+        bits.iter().enumerate().map(|(i, &b)| {
+            Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("input bit {}", i)), Some(b)).unwrap())
+        }).collect()
+    }
+
+    #[test]
+    fn test_blake2s_against_reference() {
+        let rng = &mut thread_rng();
+
+        let mut lengths = vec![0, 1, 4, 63, 64, 65, 128];
+        lengths.extend((0..3).map(|_| rng.gen_range(0, 200)));
+
+        for len in lengths {
+            let input: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            let personalization: Vec<u8> = (0..PERSONALIZATION_BYTES).map(|_| rng.gen()).collect();
+
+            let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+            let input_bits = alloc_bits(cs.namespace(|| "input"), &bytes_to_bits_le(&input));
+
+            let digest = blake2s(cs.namespace(|| "blake2s"), &input_bits, &personalization).unwrap();
+            let digest: Vec<bool> = digest.iter().map(|b| b.get_value().unwrap()).collect();
+            assert_eq!(digest, reference(&input, &personalization), "input of {} bytes", len);
+
+            cs.into_r1cs().check_satisfied().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_blake2s_zcash_personalization() {
+        // The personalization used by Zcash to derive diversified bases
+        let personalization = b"Zcash_gd";
+        let input = b"this is a test of the blake2s gadget";
+
+        let input_bits: Vec<_> = bytes_to_bits_le(input).into_iter().map(Boolean::constant).collect();
+
+        let mut cs = RecordingSystem::<Bls12>::new();
+        let digest = blake2s(&mut cs, &input_bits, personalization).unwrap();
+
+        // Constant inputs are folded entirely
+        assert!(digest.iter().all(|b| b.is_constant()));
+        assert_eq!(cs.into_r1cs().num_constraints(), 0);
+
+        let digest: Vec<bool> = digest.iter().map(|b| b.get_value().unwrap()).collect();
+        assert_eq!(digest, reference(input, personalization));
+        assert!(digest != reference(input, b"12345678"));
+    }
+
+    #[test]
+    fn test_blake2s_constraints() {
+        let rng = &mut thread_rng();
+        let input: Vec<u8> = (0..64).map(|_| rng.gen()).collect();
+
+        let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+        let input_bits = alloc_bits(cs.namespace(|| "input"), &bytes_to_bits_le(&input));
+        blake2s(cs.namespace(|| "blake2s"), &input_bits, b"12345678").unwrap();
+
+        let r1cs = cs.into_r1cs();
+        r1cs.check_satisfied().unwrap();
+        assert_eq!(r1cs.num_constraints() - BLOCK_BITS, 21280);
+    }
+}
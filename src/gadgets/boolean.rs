@@ -0,0 +1,775 @@
+use crate::pairing::{
+    Engine
+};
+
+use crate::pairing::ff::{
+    Field,
+    PrimeField,
+    BitIterator
+};
+
+use crate::{
+    SynthesisError,
+    ConstraintSystem,
+    LinearCombination,
+    Variable
+};
+
+use super::Allocate;
+
+/// Represents a variable in the constraint system which is guaranteed
+/// to be either zero or one.
+#[derive(Clone)]
+pub struct AllocatedBit {
+    pub(crate) variable: Variable,
+    pub(crate) value: Option<bool>
+}
+
+impl AllocatedBit {
+    pub fn get_value(&self) -> Option<bool> {
+        self.value
+    }
+
+    pub fn get_variable(&self) -> Variable {
+        self.variable
+    }
+
+    /// Allocate a variable in the constraint system which can only be a
+    /// boolean value. Further, constrain that the boolean is false
+    /// unless the condition is false.
+    pub fn alloc_conditionally<E, CS>(
+        mut cs: CS,
+        value: Option<bool>,
+        must_be_false: &AllocatedBit
+    ) -> Result<Self, SynthesisError>
+        where E: Engine, CS: ConstraintSystem<E>
+    {
+        let var = cs.alloc(|| "boolean", || field_of::<E>(value))?;
+
+        // Constrain: (1 - must_be_false - a) * a = 0
+        // if must_be_false is true, the equation
+        // reduces to -a * a = 0, which implies a = 0.
+        // if must_be_false is false, the equation
+        // reduces to (1 - a) * a = 0, which is a
+        // traditional boolean constraint.
+        cs.enforce(
+            || "boolean constraint",
+            |lc| lc + CS::one() - must_be_false.variable - var,
+            |lc| lc + var,
+            |lc| lc
+        );
+
+        Ok(AllocatedBit {
+            variable: var,
+            value: value
+        })
+    }
+
+    /// Allocate a variable in the constraint system which can only be a
+    /// boolean value.
+    pub fn alloc<E, CS>(
+        mut cs: CS,
+        value: Option<bool>
+    ) -> Result<Self, SynthesisError>
+        where E: Engine, CS: ConstraintSystem<E>
+    {
+        let var = cs.alloc(|| "boolean", || field_of::<E>(value))?;
+
+        // Constrain: (1 - a) * a = 0
+        // This constrains a to be either 0 or 1.
+        cs.enforce(
+            || "boolean constraint",
+            |lc| lc + CS::one() - var,
+            |lc| lc + var,
+            |lc| lc
+        );
+
+        Ok(AllocatedBit {
+            variable: var,
+            value: value
+        })
+    }
+
+    /// Performs an XOR operation over the two operands, returning
+    /// an `AllocatedBit`.
+    pub fn xor<E, CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self
+    ) -> Result<Self, SynthesisError>
+        where E: Engine, CS: ConstraintSystem<E>
+    {
+        let result_value = match (a.value, b.value) {
+            (Some(a), Some(b)) => Some(a ^ b),
+            _ => None
+        };
+
+        let result_var = cs.alloc(|| "xor result", || field_of::<E>(result_value))?;
+
+        // Constrain (a + a) * (b) = (a + b - c)
+        // Given that a and b are boolean constrained, if they
+        // are equal, the only solution for c is 0, and if they
+        // are different, the only solution for c is 1.
+        //
+        // ¬(a ∧ b) ∧ ¬(¬a ∧ ¬b) = c
+        // (1 - (a * b)) * (1 - ((1 - a) * (1 - b))) = c
+        // (1 - ab) * (1 - (1 - a - b + ab)) = c
+        // (1 - ab) * (a + b - ab) = c
+        // a + b - ab - (a^2)b - (b^2)a + (a^2)(b^2) = c
+        // a + b - ab - ab - ab + ab = c
+        // a + b - 2ab = c
+        // -2a * b = c - a - b
+        // 2a * b = a + b - c
+        // (a + a) * b = a + b - c
+        cs.enforce(
+            || "xor constraint",
+            |lc| lc + a.variable + a.variable,
+            |lc| lc + b.variable,
+            |lc| lc + a.variable + b.variable - result_var
+        );
+
+        Ok(AllocatedBit {
+            variable: result_var,
+            value: result_value
+        })
+    }
+
+    /// Performs an AND operation over the two operands, returning
+    /// an `AllocatedBit`.
+    pub fn and<E, CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self
+    ) -> Result<Self, SynthesisError>
+        where E: Engine, CS: ConstraintSystem<E>
+    {
+        let result_value = match (a.value, b.value) {
+            (Some(a), Some(b)) => Some(a & b),
+            _ => None
+        };
+
+        let result_var = cs.alloc(|| "and result", || field_of::<E>(result_value))?;
+
+        // Constrain (a) * (b) = (c), ensuring c is 1 iff
+        // a AND b are both 1.
+        cs.enforce(
+            || "and constraint",
+            |lc| lc + a.variable,
+            |lc| lc + b.variable,
+            |lc| lc + result_var
+        );
+
+        Ok(AllocatedBit {
+            variable: result_var,
+            value: result_value
+        })
+    }
+
+    /// Calculates `a AND (NOT b)`.
+    pub fn and_not<E, CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self
+    ) -> Result<Self, SynthesisError>
+        where E: Engine, CS: ConstraintSystem<E>
+    {
+        let result_value = match (a.value, b.value) {
+            (Some(a), Some(b)) => Some(a & !b),
+            _ => None
+        };
+
+        let result_var = cs.alloc(|| "and not result", || field_of::<E>(result_value))?;
+
+        // Constrain (a) * (1 - b) = (c), ensuring c is 1 iff
+        // a is true and b is false, and otherwise c is 0.
+        cs.enforce(
+            || "and not constraint",
+            |lc| lc + a.variable,
+            |lc| lc + CS::one() - b.variable,
+            |lc| lc + result_var
+        );
+
+        Ok(AllocatedBit {
+            variable: result_var,
+            value: result_value
+        })
+    }
+
+    /// Calculates `(NOT a) AND (NOT b)`.
+    pub fn nor<E, CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self
+    ) -> Result<Self, SynthesisError>
+        where E: Engine, CS: ConstraintSystem<E>
+    {
+        let result_value = match (a.value, b.value) {
+            (Some(a), Some(b)) => Some(!a & !b),
+            _ => None
+        };
+
+        let result_var = cs.alloc(|| "nor result", || field_of::<E>(result_value))?;
+
+        // Constrain (1 - a) * (1 - b) = (c), ensuring c is 1 iff
+        // a and b are both false, and otherwise c is 0.
+        cs.enforce(
+            || "nor constraint",
+            |lc| lc + CS::one() - a.variable,
+            |lc| lc + CS::one() - b.variable,
+            |lc| lc + result_var
+        );
+
+        Ok(AllocatedBit {
+            variable: result_var,
+            value: result_value
+        })
+    }
+}
+
+fn field_of<E: Engine>(value: Option<bool>) -> Result<E::Fr, SynthesisError> {
+    match value {
+        Some(true) => Ok(E::Fr::one()),
+        Some(false) => Ok(E::Fr::zero()),
+        None => Err(SynthesisError::AssignmentMissing)
+    }
+}
+
+/// Allocates the bits of a field element in little-endian order. Only
+/// `NUM_BITS` bits are allocated, but nothing prevents the bits from
+/// representing a value above the modulus.
+pub fn field_into_allocated_bits_le<E, CS, F>(
+    mut cs: CS,
+    value: Option<F>
+) -> Result<Vec<AllocatedBit>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>, F: PrimeField
+{
+    // Deconstruct in big-endian bit order
+    let values = match value {
+        Some(ref value) => {
+            let mut field_char = BitIterator::new(F::char());
+
+            let mut tmp = Vec::with_capacity(F::NUM_BITS as usize);
+
+            let mut found_one = false;
+            for b in BitIterator::new(value.into_repr()) {
+                // Skip leading bits
+                found_one |= field_char.next().unwrap();
+                if !found_one {
+                    continue;
+                }
+
+                tmp.push(Some(b));
+            }
+
+            assert_eq!(tmp.len(), F::NUM_BITS as usize);
+
+            tmp
+        },
+        None => {
+            vec![None; F::NUM_BITS as usize]
+        }
+    };
+
+    // Allocate in little-endian order
+    let bits = values.into_iter().rev().enumerate().map(|(i, b)| {
+        AllocatedBit::alloc(
+            cs.namespace(|| format!("bit {}", i)),
+            b
+        )
+    }).collect::<Result<Vec<_>, SynthesisError>>()?;
+
+    Ok(bits)
+}
+
+/// This is a boolean value which may be either a constant or
+/// an interpretation of an `AllocatedBit`.
+#[derive(Clone)]
+pub enum Boolean {
+    /// Existential view of the boolean variable
+    Is(AllocatedBit),
+    /// Negated view of the boolean variable
+    Not(AllocatedBit),
+    /// Constant (not an allocated variable)
+    Constant(bool)
+}
+
+impl Boolean {
+    pub fn is_constant(&self) -> bool {
+        matches!(*self, Boolean::Constant(_))
+    }
+
+    pub fn enforce_equal<E, CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self
+    ) -> Result<(), SynthesisError>
+        where E: Engine, CS: ConstraintSystem<E>
+    {
+        match (a, b) {
+            (&Boolean::Constant(a), &Boolean::Constant(b)) => {
+                if a == b {
+                    Ok(())
+                } else {
+                    Err(SynthesisError::Unsatisfiable)
+                }
+            },
+            (a, b) => {
+                let one = CS::one();
+                cs.enforce(
+                    || "equality constraint",
+                    |lc| lc + &a.lc(one, E::Fr::one()) - &b.lc(one, E::Fr::one()),
+                    |lc| lc + one,
+                    |lc| lc
+                );
+
+                Ok(())
+            }
+        }
+    }
+
+    pub fn get_value(&self) -> Option<bool> {
+        match *self {
+            Boolean::Constant(c) => Some(c),
+            Boolean::Is(ref v) => v.get_value(),
+            Boolean::Not(ref v) => v.get_value().map(|b| !b)
+        }
+    }
+
+    pub fn lc<E: Engine>(
+        &self,
+        one: Variable,
+        coeff: E::Fr
+    ) -> LinearCombination<E>
+    {
+        match *self {
+            Boolean::Constant(c) => {
+                if c {
+                    LinearCombination::<E>::zero() + (coeff, one)
+                } else {
+                    LinearCombination::<E>::zero()
+                }
+            },
+            Boolean::Is(ref v) => {
+                LinearCombination::<E>::zero() + (coeff, v.get_variable())
+            },
+            Boolean::Not(ref v) => {
+                LinearCombination::<E>::zero() + (coeff, one) - (coeff, v.get_variable())
+            }
+        }
+    }
+
+    /// Construct a boolean from a known constant
+    pub fn constant(b: bool) -> Self {
+        Boolean::Constant(b)
+    }
+
+    /// Return a negated interpretation of this boolean.
+    pub fn not(&self) -> Self {
+        match *self {
+            Boolean::Constant(c) => Boolean::Constant(!c),
+            Boolean::Is(ref v) => Boolean::Not(v.clone()),
+            Boolean::Not(ref v) => Boolean::Is(v.clone())
+        }
+    }
+
+    /// Perform XOR over two boolean operands
+    pub fn xor<'a, E, CS>(
+        cs: CS,
+        a: &'a Self,
+        b: &'a Self
+    ) -> Result<Self, SynthesisError>
+        where E: Engine, CS: ConstraintSystem<E>
+    {
+        match (a, b) {
+            (Boolean::Constant(false), x) | (x, Boolean::Constant(false)) => Ok(x.clone()),
+            (Boolean::Constant(true), x) | (x, Boolean::Constant(true)) => Ok(x.not()),
+            // a XOR (NOT b) = NOT(a XOR b)
+            (is @ Boolean::Is(_), not @ Boolean::Not(_)) | (not @ Boolean::Not(_), is @ Boolean::Is(_)) => {
+                Ok(Boolean::xor(
+                    cs,
+                    is,
+                    &not.not()
+                )?.not())
+            },
+            // a XOR b = (NOT a) XOR (NOT b)
+            (Boolean::Is(a), Boolean::Is(b)) | (Boolean::Not(a), Boolean::Not(b)) => {
+                Ok(Boolean::Is(AllocatedBit::xor(cs, a, b)?))
+            }
+        }
+    }
+
+    /// Perform AND over two boolean operands
+    pub fn and<'a, E, CS>(
+        cs: CS,
+        a: &'a Self,
+        b: &'a Self
+    ) -> Result<Self, SynthesisError>
+        where E: Engine, CS: ConstraintSystem<E>
+    {
+        match (a, b) {
+            // false AND x is always false
+            (Boolean::Constant(false), _) | (_, Boolean::Constant(false)) => Ok(Boolean::Constant(false)),
+            // true AND x is always x
+            (Boolean::Constant(true), x) | (x, Boolean::Constant(true)) => Ok(x.clone()),
+            // a AND (NOT b)
+            (Boolean::Is(is), Boolean::Not(not)) | (Boolean::Not(not), Boolean::Is(is)) => {
+                Ok(Boolean::Is(AllocatedBit::and_not(cs, is, not)?))
+            },
+            // (NOT a) AND (NOT b) = a NOR b
+            (Boolean::Not(a), Boolean::Not(b)) => {
+                Ok(Boolean::Is(AllocatedBit::nor(cs, a, b)?))
+            },
+            // a AND b
+            (Boolean::Is(a), Boolean::Is(b)) => {
+                Ok(Boolean::Is(AllocatedBit::and(cs, a, b)?))
+            }
+        }
+    }
+
+    /// Perform NAND over two boolean operands
+    pub fn nand<'a, E, CS>(
+        cs: CS,
+        a: &'a Self,
+        b: &'a Self
+    ) -> Result<Self, SynthesisError>
+        where E: Engine, CS: ConstraintSystem<E>
+    {
+        Ok(Boolean::and(cs, a, b)?.not())
+    }
+
+    /// Perform OR over two boolean operands
+    pub fn or<'a, E, CS>(
+        cs: CS,
+        a: &'a Self,
+        b: &'a Self
+    ) -> Result<Self, SynthesisError>
+        where E: Engine, CS: ConstraintSystem<E>
+    {
+        // a OR b = NOT((NOT a) AND (NOT b))
+        Ok(Boolean::and(cs, &a.not(), &b.not())?.not())
+    }
+
+    /// Returns `a` if the condition is true and `b` otherwise. Costs
+    /// a single constraint, unless the result is known to be one of
+    /// the operands or the condition.
+    pub fn conditionally_select<E, CS>(
+        mut cs: CS,
+        condition: &Self,
+        a: &Self,
+        b: &Self
+    ) -> Result<Self, SynthesisError>
+        where E: Engine, CS: ConstraintSystem<E>
+    {
+        match (condition, a, b) {
+            (&Boolean::Constant(true), a, _) => return Ok(a.clone()),
+            (&Boolean::Constant(false), _, b) => return Ok(b.clone()),
+            (_, &Boolean::Constant(a), &Boolean::Constant(b)) if a == b => return Ok(Boolean::Constant(a)),
+            (condition, &Boolean::Constant(true), &Boolean::Constant(false)) => return Ok(condition.clone()),
+            (condition, &Boolean::Constant(false), &Boolean::Constant(true)) => return Ok(condition.not()),
+            _ => {}
+        }
+
+        let result_value = match (condition.get_value(), a.get_value(), b.get_value()) {
+            (Some(c), Some(a), Some(b)) => Some(if c { a } else { b }),
+            _ => None
+        };
+
+        let result_var = cs.alloc(|| "selected", || field_of::<E>(result_value))?;
+
+        // Constrain (c) * (a - b) = (r - b), so the result is
+        // either a or b and therefore boolean as well.
+        let one = CS::one();
+        cs.enforce(
+            || "selection constraint",
+            |lc| lc + &condition.lc(one, E::Fr::one()),
+            |lc| lc + &a.lc(one, E::Fr::one()) - &b.lc(one, E::Fr::one()),
+            |lc| lc + result_var - &b.lc(one, E::Fr::one())
+        );
+
+        Ok(Boolean::Is(AllocatedBit {
+            variable: result_var,
+            value: result_value
+        }))
+    }
+
+    /// Computes the SHA-2 choice function `(a AND b) XOR ((NOT a) AND c)`,
+    /// which takes `b` if `a` is set and `c` otherwise.
+    pub fn ch<E, CS>(
+        cs: CS,
+        a: &Self,
+        b: &Self,
+        c: &Self
+    ) -> Result<Self, SynthesisError>
+        where E: Engine, CS: ConstraintSystem<E>
+    {
+        Boolean::conditionally_select(cs, a, b, c)
+    }
+
+    /// Computes the SHA-2 majority function `(a AND b) XOR (a AND c) XOR
+    /// (b AND c)`. Costs two constraints, or at most one if any of the
+    /// operands is constant.
+    pub fn maj<E, CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+        c: &Self
+    ) -> Result<Self, SynthesisError>
+        where E: Engine, CS: ConstraintSystem<E>
+    {
+        match (a, b, c) {
+            (&Boolean::Constant(a), &Boolean::Constant(b), &Boolean::Constant(c)) => {
+                return Ok(Boolean::Constant((a & b) ^ (a & c) ^ (b & c)));
+            },
+            // maj(false, x, y) = x AND y
+            (&Boolean::Constant(false), x, y) | (x, &Boolean::Constant(false), y) | (x, y, &Boolean::Constant(false)) => {
+                return Boolean::and(cs, x, y);
+            },
+            // maj(true, x, y) = x OR y
+            (&Boolean::Constant(true), x, y) | (x, &Boolean::Constant(true), y) | (x, y, &Boolean::Constant(true)) => {
+                return Boolean::or(cs, x, y);
+            },
+            _ => {}
+        }
+
+        let maj_value = match (a.get_value(), b.get_value(), c.get_value()) {
+            (Some(a), Some(b), Some(c)) => Some((a & b) ^ (a & c) ^ (b & c)),
+            _ => None
+        };
+
+        let bc = Boolean::and(cs.namespace(|| "b and c"), b, c)?;
+
+        let maj = cs.alloc(|| "maj", || field_of::<E>(maj_value))?;
+
+        // If a is false, the majority is b AND c, and otherwise it is
+        // b OR c = b + c - bc. Both cases are covered by
+        //
+        // (bc + bc - b - c) * (a) = bc - maj
+        let one = CS::one();
+        let mut two = E::Fr::one();
+        two.double();
+        cs.enforce(
+            || "maj computation",
+            |lc| lc + &bc.lc(one, two) - &b.lc(one, E::Fr::one()) - &c.lc(one, E::Fr::one()),
+            |lc| lc + &a.lc(one, E::Fr::one()),
+            |lc| lc + &bc.lc(one, E::Fr::one()) - maj
+        );
+
+        Ok(Boolean::Is(AllocatedBit {
+            variable: maj,
+            value: maj_value
+        }))
+    }
+}
+
+impl From<AllocatedBit> for Boolean {
+    fn from(b: AllocatedBit) -> Boolean {
+        Boolean::Is(b)
+    }
+}
+
+impl<E: Engine> Allocate<E> for Boolean {
+    type Value = bool;
+
+    fn allocate<CS>(
+        cs: CS,
+        value: Option<&bool>
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        Ok(Boolean::Is(AllocatedBit::alloc(cs, value.cloned())?))
+    }
+
+    fn get_value(&self) -> Option<bool> {
+        Boolean::get_value(self)
+    }
+
+    fn inputize<CS>(
+        &self,
+        mut cs: CS
+    ) -> Result<(), SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let input = cs.alloc_input(|| "input", || field_of::<E>(self.get_value()))?;
+
+        let one = CS::one();
+        cs.enforce(
+            || "input constraint",
+            |lc| lc + &self.lc(one, E::Fr::one()),
+            |lc| lc + one,
+            |lc| lc + input
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bls12_381::{Bls12, Fr};
+    use crate::r1cs::RecordingSystem;
+    use crate::Index;
+
+    use rand::{Rand, thread_rng};
+
+    #[derive(Clone, Copy, Debug)]
+    enum OperandType {
+        True,
+        False,
+        AllocatedTrue,
+        AllocatedFalse,
+        NegatedAllocatedTrue,
+        NegatedAllocatedFalse
+    }
+
+    const OPERAND_TYPES: [OperandType; 6] = [
+        OperandType::True,
+        OperandType::False,
+        OperandType::AllocatedTrue,
+        OperandType::AllocatedFalse,
+        OperandType::NegatedAllocatedTrue,
+        OperandType::NegatedAllocatedFalse
+    ];
+
+    fn operand<CS: ConstraintSystem<Bls12>>(cs: CS, ty: OperandType) -> Boolean {
+        let alloc = |cs: CS, value| Boolean::from(AllocatedBit::alloc(cs, Some(value)).unwrap());
+
+        match ty {
+            OperandType::True => Boolean::constant(true),
+            OperandType::False => Boolean::constant(false),
+            OperandType::AllocatedTrue => alloc(cs, true),
+            OperandType::AllocatedFalse => alloc(cs, false),
+            OperandType::NegatedAllocatedTrue => alloc(cs, false).not(),
+            OperandType::NegatedAllocatedFalse => alloc(cs, true).not()
+        }
+    }
+
+    #[test]
+    fn test_allocated_bit() {
+        let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+        AllocatedBit::alloc(cs.namespace(|| "bit"), Some(true)).unwrap();
+
+        let mut r1cs = cs.into_r1cs();
+        r1cs.check_satisfied().unwrap();
+
+        let index = r1cs.index_of("bit/boolean").unwrap();
+        assert_eq!(index, Index::Aux(0));
+        r1cs.aux_assignment.as_mut().unwrap()[0] = Fr::from_str("2").unwrap();
+        assert!(r1cs.check_satisfied().is_err());
+    }
+
+    #[test]
+    fn test_boolean_operations() {
+        for &a_ty in OPERAND_TYPES.iter() {
+            for &b_ty in OPERAND_TYPES.iter() {
+                for op in 0..4 {
+                    let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+
+                    let a = operand(cs.namespace(|| "a"), a_ty);
+                    let b = operand(cs.namespace(|| "b"), b_ty);
+                    let (a_value, b_value) = (a.get_value().unwrap(), b.get_value().unwrap());
+
+                    let (c, expected) = match op {
+                        0 => (Boolean::xor(cs.namespace(|| "op"), &a, &b).unwrap(), a_value ^ b_value),
+                        1 => (Boolean::and(cs.namespace(|| "op"), &a, &b).unwrap(), a_value & b_value),
+                        2 => (Boolean::nand(cs.namespace(|| "op"), &a, &b).unwrap(), !(a_value & b_value)),
+                        _ => (Boolean::or(cs.namespace(|| "op"), &a, &b).unwrap(), a_value | b_value)
+                    };
+                    assert_eq!(c.get_value(), Some(expected), "{:?} op {} {:?}", a_ty, op, b_ty);
+
+                    Boolean::enforce_equal(cs.namespace(|| "check"), &c, &Boolean::constant(expected)).unwrap();
+
+                    let r1cs = cs.into_r1cs();
+                    r1cs.check_satisfied().unwrap();
+
+                    // Operations involving constants are folded
+                    let expected_constraints = if a.is_constant() || b.is_constant() { 0 } else { 1 };
+                    assert_eq!(r1cs.constraints.iter().filter(|c| c.annotation.starts_with("op/")).count(), expected_constraints);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_boolean_select() {
+        for &c_ty in OPERAND_TYPES.iter() {
+            for &a_ty in OPERAND_TYPES.iter() {
+                for &b_ty in OPERAND_TYPES.iter() {
+                    let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+
+                    let c = operand(cs.namespace(|| "c"), c_ty);
+                    let a = operand(cs.namespace(|| "a"), a_ty);
+                    let b = operand(cs.namespace(|| "b"), b_ty);
+
+                    let selected = Boolean::conditionally_select(cs.namespace(|| "select"), &c, &a, &b).unwrap();
+                    let expected = if c.get_value().unwrap() { a.get_value() } else { b.get_value() };
+                    assert_eq!(selected.get_value(), expected);
+
+                    Boolean::enforce_equal(cs.namespace(|| "check"), &selected, &Boolean::constant(expected.unwrap())).unwrap();
+
+                    let r1cs = cs.into_r1cs();
+                    r1cs.check_satisfied().unwrap();
+                    assert!(r1cs.constraints.iter().filter(|c| c.annotation.starts_with("select/")).count() <= 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_boolean_ch_maj() {
+        for &a_ty in OPERAND_TYPES.iter() {
+            for &b_ty in OPERAND_TYPES.iter() {
+                for &c_ty in OPERAND_TYPES.iter() {
+                    let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+
+                    let a = operand(cs.namespace(|| "a"), a_ty);
+                    let b = operand(cs.namespace(|| "b"), b_ty);
+                    let c = operand(cs.namespace(|| "c"), c_ty);
+                    let (a_value, b_value, c_value) = (a.get_value().unwrap(), b.get_value().unwrap(), c.get_value().unwrap());
+
+                    let ch = Boolean::ch(cs.namespace(|| "ch"), &a, &b, &c).unwrap();
+                    let maj = Boolean::maj(cs.namespace(|| "maj"), &a, &b, &c).unwrap();
+
+                    let expected_ch = (a_value & b_value) ^ (!a_value & c_value);
+                    let expected_maj = (a_value & b_value) ^ (a_value & c_value) ^ (b_value & c_value);
+                    assert_eq!(ch.get_value(), Some(expected_ch));
+                    assert_eq!(maj.get_value(), Some(expected_maj));
+
+                    Boolean::enforce_equal(cs.namespace(|| "check ch"), &ch, &Boolean::constant(expected_ch)).unwrap();
+                    Boolean::enforce_equal(cs.namespace(|| "check maj"), &maj, &Boolean::constant(expected_maj)).unwrap();
+
+                    let r1cs = cs.into_r1cs();
+                    r1cs.check_satisfied().unwrap();
+
+                    let maj_constraints = r1cs.constraints.iter().filter(|c| c.annotation.starts_with("maj/")).count();
+                    if a.is_constant() || b.is_constant() || c.is_constant() {
+                        assert!(maj_constraints <= 1);
+                    } else {
+                        assert_eq!(maj_constraints, 2);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_field_into_allocated_bits_le() {
+        let rng = &mut thread_rng();
+        let value = Fr::rand(rng);
+
+        let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+        let bits = field_into_allocated_bits_le(&mut cs, Some(value)).unwrap();
+        assert_eq!(bits.len(), Fr::NUM_BITS as usize);
+
+        let repr = value.into_repr();
+        for (i, bit) in bits.iter().enumerate() {
+            let limb = repr.as_ref()[i / 64];
+            assert_eq!(bit.get_value(), Some((limb >> (i % 64)) & 1 == 1));
+        }
+
+        let r1cs = cs.into_r1cs();
+        assert_eq!(r1cs.num_constraints(), Fr::NUM_BITS as usize);
+        r1cs.check_satisfied().unwrap();
+    }
+}
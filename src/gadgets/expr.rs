@@ -0,0 +1,503 @@
+//! Arithmetic expressions that compile into constraints.
+//!
+//! An `Expr` is an affine combination of variables, so adding, subtracting
+//! and scaling by constants is free. Only the product of two expressions
+//! that both involve variables allocates a new variable, together with a
+//! single constraint binding it. Values are computed alongside whenever the
+//! assignments of the variables are known, so the same code serves both
+//! key generation and proving.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::pairing::ff::{
+    Field
+};
+
+use crate::{
+    SynthesisError,
+    ConstraintSystem,
+    LinearCombination,
+    Variable
+};
+
+use std::ops::{Add, Sub, Mul, Neg};
+
+#[derive(Clone)]
+pub struct Expr<E: Engine> {
+    // Terms of the variables, without the constant term
+    lc: LinearCombination<E>,
+    constant: E::Fr,
+
+    value: Option<E::Fr>
+}
+
+impl<E: Engine> Expr<E> {
+    pub fn constant(value: E::Fr) -> Self {
+        Expr {
+            lc: LinearCombination::zero(),
+            constant: value,
+            value: Some(value)
+        }
+    }
+
+    pub fn zero() -> Self {
+        Self::constant(E::Fr::zero())
+    }
+
+    pub fn one() -> Self {
+        Self::constant(E::Fr::one())
+    }
+
+    /// Wraps a variable allocated elsewhere, together with
+    /// its assignment if it is known.
+    pub fn from_variable(var: Variable, value: Option<E::Fr>) -> Self {
+        Expr {
+            lc: LinearCombination::zero() + var,
+            constant: E::Fr::zero(),
+            value: value
+        }
+    }
+
+    /// Allocates a private variable holding the value.
+    pub fn alloc<CS, F>(
+        mut cs: CS,
+        value: F
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>, F: FnOnce() -> Result<E::Fr, SynthesisError>
+    {
+        let mut new_value = None;
+        let var = cs.alloc(|| "value", || {
+            let tmp = value()?;
+            new_value = Some(tmp);
+
+            Ok(tmp)
+        })?;
+
+        Ok(Self::from_variable(var, new_value))
+    }
+
+    /// Allocates a public input holding the value.
+    pub fn alloc_input<CS, F>(
+        mut cs: CS,
+        value: F
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>, F: FnOnce() -> Result<E::Fr, SynthesisError>
+    {
+        let mut new_value = None;
+        let var = cs.alloc_input(|| "input", || {
+            let tmp = value()?;
+            new_value = Some(tmp);
+
+            Ok(tmp)
+        })?;
+
+        Ok(Self::from_variable(var, new_value))
+    }
+
+    pub fn get_value(&self) -> Option<E::Fr> {
+        self.value
+    }
+
+    /// Returns whether the expression involves no variables.
+    pub fn is_constant(&self) -> bool {
+        self.lc.is_empty()
+    }
+
+    /// The expression as a linear combination, for use in `enforce`.
+    pub fn lc<CS: ConstraintSystem<E>>(&self) -> LinearCombination<E> {
+        self.lc.clone() + (self.constant, CS::one())
+    }
+
+    /// Multiplies two expressions. This costs one variable and one
+    /// constraint, unless either of them is a constant.
+    pub fn multiply<CS>(
+        &self,
+        mut cs: CS,
+        other: &Self
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        if self.is_constant() {
+            return Ok(other.clone() * self.constant);
+        }
+        if other.is_constant() {
+            return Ok(self.clone() * other.constant);
+        }
+
+        let mut value = None;
+        let product = cs.alloc(|| "product", || {
+            let mut tmp = self.value.ok_or(SynthesisError::AssignmentMissing)?;
+            tmp.mul_assign(&other.value.ok_or(SynthesisError::AssignmentMissing)?);
+            value = Some(tmp);
+
+            Ok(tmp)
+        })?;
+
+        cs.enforce(
+            || "multiplication constraint",
+            |lc| lc + &self.lc::<CS>(),
+            |lc| lc + &other.lc::<CS>(),
+            |lc| lc + product
+        );
+
+        Ok(Self::from_variable(product, value))
+    }
+
+    pub fn square<CS>(
+        &self,
+        cs: CS
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        self.multiply(cs, self)
+    }
+
+    /// Raises the expression to a constant power by square and multiply,
+    /// which costs one constraint per squaring and per multiplication.
+    pub(crate) fn pow<CS>(
+        &self,
+        mut cs: CS,
+        exponent: u64
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        if exponent == 0 {
+            return Ok(Self::one());
+        }
+
+        // Starting below the most significant bit
+        let top = 63 - exponent.leading_zeros() as usize;
+
+        let mut result = self.clone();
+        for i in (0..top).rev() {
+            result = result.square(cs.namespace(|| format!("square {}", i)))?;
+
+            if (exponent >> i) & 1 == 1 {
+                result = result.multiply(cs.namespace(|| format!("multiply {}", i)), self)?;
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Enforces that both expressions are equal. Constant expressions
+    /// that differ are reported as `Unsatisfiable` right away.
+    pub fn assert_eq<CS>(
+        &self,
+        mut cs: CS,
+        other: &Self
+    ) -> Result<(), SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let difference = self.clone() - other;
+        if difference.is_constant() {
+            if difference.constant.is_zero() {
+                return Ok(());
+            } else {
+                return Err(SynthesisError::Unsatisfiable);
+            }
+        }
+
+        cs.enforce(
+            || "equality constraint",
+            |lc| lc + &difference.lc::<CS>(),
+            |lc| lc + CS::one(),
+            |lc| lc
+        );
+
+        Ok(())
+    }
+
+    /// Enforces that the expression is either zero or one.
+    pub fn assert_boolean<CS>(
+        &self,
+        mut cs: CS
+    ) -> Result<(), SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        if self.is_constant() {
+            if self.constant.is_zero() || self.constant == E::Fr::one() {
+                return Ok(());
+            } else {
+                return Err(SynthesisError::Unsatisfiable);
+            }
+        }
+
+        cs.enforce(
+            || "boolean constraint",
+            |lc| lc + CS::one() - &self.lc::<CS>(),
+            |lc| lc + &self.lc::<CS>(),
+            |lc| lc
+        );
+
+        Ok(())
+    }
+
+    /// Computes the inverse, which also proves that the
+    /// expression is not zero.
+    pub fn inverse<CS>(
+        &self,
+        mut cs: CS
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        if self.is_constant() {
+            return self.constant.inverse().map(Self::constant).ok_or(SynthesisError::DivisionByZero);
+        }
+
+        let mut value = None;
+        let inverse = cs.alloc(|| "inverse", || {
+            let tmp = self.value.ok_or(SynthesisError::AssignmentMissing)?;
+            let tmp = tmp.inverse().ok_or(SynthesisError::DivisionByZero)?;
+            value = Some(tmp);
+
+            Ok(tmp)
+        })?;
+
+        cs.enforce(
+            || "inverse constraint",
+            |lc| lc + &self.lc::<CS>(),
+            |lc| lc + inverse,
+            |lc| lc + CS::one()
+        );
+
+        Ok(Self::from_variable(inverse, value))
+    }
+
+    /// Returns `if_true` if the condition is one and `if_false` if it is
+    /// zero, for a single constraint. The condition is not constrained to
+    /// be boolean here; use `assert_boolean` unless that is already known.
+    pub fn select<CS>(
+        cs: CS,
+        condition: &Self,
+        if_true: &Self,
+        if_false: &Self
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        // if_false + condition * (if_true - if_false)
+        let delta = if_true.clone() - if_false;
+        let selected = condition.multiply(cs, &delta)?;
+
+        Ok(selected + if_false)
+    }
+
+    /// Returns a variable equal to the expression. A new variable is
+    /// only allocated if the expression isn't a single variable already.
+    pub fn to_variable<CS>(
+        &self,
+        mut cs: CS
+    ) -> Result<Variable, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        if self.constant.is_zero() && self.lc.len() == 1 && self.lc.0[0].1 == E::Fr::one() {
+            return Ok(self.lc.0[0].0);
+        }
+
+        let var = cs.alloc(|| "variable", || {
+            self.value.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        cs.enforce(
+            || "variable constraint",
+            |lc| lc + &self.lc::<CS>(),
+            |lc| lc + CS::one(),
+            |lc| lc + var
+        );
+
+        Ok(var)
+    }
+}
+
+impl<E: Engine> Add<Expr<E>> for Expr<E> {
+    type Output = Expr<E>;
+
+    fn add(self, other: Expr<E>) -> Expr<E> {
+        self + &other
+    }
+}
+
+impl<'a, E: Engine> Add<&'a Expr<E>> for Expr<E> {
+    type Output = Expr<E>;
+
+    fn add(mut self, other: &'a Expr<E>) -> Expr<E> {
+        self.lc = self.lc + &other.lc;
+        self.constant.add_assign(&other.constant);
+        self.value = match (self.value, other.value) {
+            (Some(mut a), Some(b)) => {
+                a.add_assign(&b);
+                Some(a)
+            },
+            _ => None
+        };
+
+        self
+    }
+}
+
+impl<E: Engine> Sub<Expr<E>> for Expr<E> {
+    type Output = Expr<E>;
+
+    fn sub(self, other: Expr<E>) -> Expr<E> {
+        self - &other
+    }
+}
+
+impl<'a, E: Engine> Sub<&'a Expr<E>> for Expr<E> {
+    type Output = Expr<E>;
+
+    fn sub(mut self, other: &'a Expr<E>) -> Expr<E> {
+        self.lc = self.lc - &other.lc;
+        self.constant.sub_assign(&other.constant);
+        self.value = match (self.value, other.value) {
+            (Some(mut a), Some(b)) => {
+                a.sub_assign(&b);
+                Some(a)
+            },
+            _ => None
+        };
+
+        self
+    }
+}
+
+impl<E: Engine> Mul<E::Fr> for Expr<E> {
+    type Output = Expr<E>;
+
+    fn mul(mut self, scalar: E::Fr) -> Expr<E> {
+        self.lc = self.lc * scalar;
+        self.constant.mul_assign(&scalar);
+        if let Some(ref mut value) = self.value {
+            value.mul_assign(&scalar);
+        }
+
+        self
+    }
+}
+
+impl<E: Engine> Neg for Expr<E> {
+    type Output = Expr<E>;
+
+    fn neg(mut self) -> Expr<E> {
+        self.lc = -self.lc;
+        self.constant.negate();
+        if let Some(ref mut value) = self.value {
+            value.negate();
+        }
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bls12_381::{Bls12, Fr};
+    use crate::pairing::ff::PrimeField;
+    use crate::r1cs::{R1CS, RecordingSystem};
+    use crate::Circuit;
+
+    struct Cubic {
+        x: Option<Fr>
+    }
+
+    // x^3 + x + 5 = out
+    impl Circuit<Bls12> for Cubic {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS
+        ) -> Result<(), SynthesisError>
+        {
+            let x = Expr::alloc(cs.namespace(|| "x"), || self.x.ok_or(SynthesisError::AssignmentMissing))?;
+            let x2 = x.square(cs.namespace(|| "x^2"))?;
+            let x3 = x2.multiply(cs.namespace(|| "x^3"), &x)?;
+
+            let result = x3 + &x + Expr::constant(Fr::from_str("5").unwrap());
+            let out = Expr::alloc_input(cs.namespace(|| "out"), || {
+                result.get_value().ok_or(SynthesisError::AssignmentMissing)
+            })?;
+
+            result.assert_eq(cs.namespace(|| "result"), &out)
+        }
+    }
+
+    #[test]
+    fn test_expr_circuit() {
+        let shape = R1CS::<Bls12>::record(Cubic { x: None }).unwrap();
+        assert_eq!(shape.num_aux(), 3);
+        assert_eq!(shape.num_constraints(), 3);
+
+        let mut r1cs = R1CS::<Bls12>::record_with_witness(Cubic { x: Some(Fr::from_str("3").unwrap()) }).unwrap();
+        assert_eq!(r1cs.input_assignment.as_ref().unwrap()[1], Fr::from_str("35").unwrap());
+        r1cs.check_satisfied().unwrap();
+
+        r1cs.input_assignment.as_mut().unwrap()[1] = Fr::from_str("36").unwrap();
+        let err = r1cs.check_satisfied().unwrap_err();
+        assert_eq!(err.location().unwrap().path, Some("result/equality constraint".into()));
+    }
+
+    #[test]
+    fn test_expr_vocabulary() {
+        let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+
+        let two = Expr::<Bls12>::constant(Fr::from_str("2").unwrap());
+        let three = Expr::constant(Fr::from_str("3").unwrap());
+        let six = two.multiply(cs.namespace(|| "six"), &three).unwrap();
+        assert!(six.is_constant());
+        assert_eq!(six.get_value(), Some(Fr::from_str("6").unwrap()));
+
+        let a = Expr::alloc(cs.namespace(|| "a"), || Ok(Fr::from_str("7").unwrap())).unwrap();
+        let scaled = a.multiply(cs.namespace(|| "scaled"), &six).unwrap() - &a;
+        assert_eq!(scaled.get_value(), Some(Fr::from_str("35").unwrap()));
+        assert_eq!(a.to_variable(cs.namespace(|| "same")).unwrap(), a.to_variable(cs.namespace(|| "again")).unwrap());
+
+        let inverse = a.inverse(cs.namespace(|| "inverse")).unwrap();
+        let product = inverse.multiply(cs.namespace(|| "product"), &a).unwrap();
+        product.assert_eq(cs.namespace(|| "product is one"), &Expr::one()).unwrap();
+
+        let bit = Expr::alloc(cs.namespace(|| "bit"), || Ok(Fr::one())).unwrap();
+        bit.assert_boolean(cs.namespace(|| "bit is boolean")).unwrap();
+        let selected = Expr::select(cs.namespace(|| "select"), &bit, &scaled, &six).unwrap();
+        assert_eq!(selected.get_value(), Some(Fr::from_str("35").unwrap()));
+        let folded = Expr::select(cs.namespace(|| "folded"), &Expr::zero(), &scaled, &six).unwrap();
+        assert!(folded.is_constant());
+
+        match Expr::<Bls12>::zero().inverse(cs.namespace(|| "zero")) {
+            Err(SynthesisError::DivisionByZero) => {},
+            _ => panic!("zero must not be invertible")
+        }
+        match two.assert_eq(cs.namespace(|| "two is three"), &three) {
+            Err(SynthesisError::Unsatisfiable) => {},
+            _ => panic!("different constants must not be equal")
+        }
+
+        let r1cs = cs.into_r1cs();
+        assert_eq!(r1cs.num_aux(), 5);
+        assert_eq!(r1cs.num_constraints(), 5);
+        r1cs.check_satisfied().unwrap();
+    }
+
+    #[test]
+    fn test_expr_pow() {
+        let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+
+        let a = Expr::alloc(cs.namespace(|| "a"), || Ok(Fr::from_str("3").unwrap())).unwrap();
+        for &(exponent, expected) in [(0, "1"), (1, "3"), (5, "243"), (7, "2187"), (8, "6561")].iter() {
+            let result = a.pow(cs.namespace(|| format!("a^{}", exponent)), exponent).unwrap();
+            assert_eq!(result.get_value(), Some(Fr::from_str(expected).unwrap()));
+        }
+
+        let constant = Expr::<Bls12>::constant(Fr::from_str("2").unwrap());
+        let result = constant.pow(cs.namespace(|| "2^10"), 10).unwrap();
+        assert!(result.is_constant());
+        assert_eq!(result.get_value(), Some(Fr::from_str("1024").unwrap()));
+
+        // x^5 and x^7 take three and four, x^8 three squarings
+        let r1cs = cs.into_r1cs();
+        assert_eq!(r1cs.num_constraints(), 10);
+        r1cs.check_satisfied().unwrap();
+    }
+}
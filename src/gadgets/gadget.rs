@@ -0,0 +1,393 @@
+//! Gadgets with typed inputs and outputs.
+//!
+//! A `Gadget` allocates its inputs and outputs through `Allocate`, so that
+//! it can be synthesized on its own as a `GadgetCircuit`. With a native
+//! `Reference` next to it, `check_gadget` tests the gadget on random
+//! samples, and `gadget_cost` reports its size.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::{
+    SynthesisError,
+    Circuit,
+    ConstraintSystem
+};
+
+use crate::r1cs::{
+    R1CS,
+    RecordingSystem
+};
+use crate::r1cs::diff::diff_r1cs;
+
+use super::expr::Expr;
+
+use rand::Rng;
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Allocated values that gadgets consume and produce. The shape of the
+/// allocation must not depend on the value, so that keys can be generated
+/// without one.
+pub trait Allocate<E: Engine>: Sized {
+    type Value;
+
+    /// Allocates private variables holding the value, if it is known.
+    fn allocate<CS>(
+        cs: CS,
+        value: Option<&Self::Value>
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>;
+
+    fn get_value(&self) -> Option<Self::Value>;
+
+    /// Exposes the allocated value as public inputs.
+    fn inputize<CS>(
+        &self,
+        cs: CS
+    ) -> Result<(), SynthesisError>
+        where CS: ConstraintSystem<E>;
+}
+
+/// A reusable piece of a circuit with typed inputs and outputs. The witness
+/// holds any private values the gadget needs beyond its inputs, and is
+/// only present when proving.
+pub trait Gadget<E: Engine> {
+    type Input: Allocate<E>;
+    type Output: Allocate<E>;
+    type Witness;
+
+    fn synthesize<CS>(
+        &self,
+        cs: CS,
+        input: &Self::Input,
+        witness: Option<&Self::Witness>
+    ) -> Result<Self::Output, SynthesisError>
+        where CS: ConstraintSystem<E>;
+
+    /// Synthesizes the gadget in a namespace of its own.
+    fn apply<CS, N, NR>(
+        &self,
+        mut cs: CS,
+        name_fn: N,
+        input: &Self::Input,
+        witness: Option<&Self::Witness>
+    ) -> Result<Self::Output, SynthesisError>
+        where CS: ConstraintSystem<E>, NR: Into<String>, N: FnOnce() -> NR
+    {
+        self.synthesize(cs.namespace(name_fn), input, witness)
+    }
+}
+
+/// The native computation a gadget is supposed to perform, which
+/// allows testing the gadget in isolation with `check_gadget`.
+pub trait Reference<E: Engine>: Gadget<E> {
+    /// Draws a random input together with a witness that fits it.
+    fn sample<R: Rng>(
+        &self,
+        rng: &mut R
+    ) -> (<Self::Input as Allocate<E>>::Value, Self::Witness);
+
+    fn evaluate(
+        &self,
+        input: &<Self::Input as Allocate<E>>::Value,
+        witness: &Self::Witness
+    ) -> Result<<Self::Output as Allocate<E>>::Value, SynthesisError>;
+}
+
+/// A circuit consisting of a single gadget. The input is allocated as
+/// private variables in the "input" namespace, and the output is
+/// exposed as public inputs in the "output" namespace.
+pub struct GadgetCircuit<'a, E: Engine, G: Gadget<E> + 'a> {
+    pub gadget: &'a G,
+    pub input: Option<<G::Input as Allocate<E>>::Value>,
+    pub witness: Option<G::Witness>,
+    _marker: PhantomData<E>
+}
+
+impl<'a, E: Engine, G: Gadget<E> + 'a> GadgetCircuit<'a, E, G> {
+    pub fn new(
+        gadget: &'a G,
+        input: Option<<G::Input as Allocate<E>>::Value>,
+        witness: Option<G::Witness>
+    ) -> Self
+    {
+        GadgetCircuit {
+            gadget: gadget,
+            input: input,
+            witness: witness,
+            _marker: PhantomData
+        }
+    }
+}
+
+impl<'a, E: Engine, G: Gadget<E> + 'a> Circuit<E> for GadgetCircuit<'a, E, G> {
+    fn synthesize<CS: ConstraintSystem<E>>(
+        self,
+        cs: &mut CS
+    ) -> Result<(), SynthesisError>
+    {
+        synthesize_exposed(self.gadget, cs, self.input.as_ref(), self.witness.as_ref())?;
+
+        Ok(())
+    }
+}
+
+fn synthesize_exposed<E, G, CS>(
+    gadget: &G,
+    cs: &mut CS,
+    input: Option<&<G::Input as Allocate<E>>::Value>,
+    witness: Option<&G::Witness>
+) -> Result<G::Output, SynthesisError>
+    where E: Engine, G: Gadget<E>, CS: ConstraintSystem<E>
+{
+    let input = G::Input::allocate(cs.namespace(|| "input"), input)?;
+    let output = gadget.apply(&mut *cs, || "gadget", &input, witness)?;
+    output.inputize(cs.namespace(|| "output"))?;
+
+    Ok(output)
+}
+
+/// The size of a gadget, not counting the allocation of its inputs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GadgetCost {
+    pub constraints: usize,
+    pub aux: usize
+}
+
+impl fmt::Display for GadgetCost {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{} constraints, {} auxillary variables", self.constraints, self.aux)
+    }
+}
+
+pub fn gadget_cost<E, G>(gadget: &G) -> Result<GadgetCost, SynthesisError>
+    where E: Engine, G: Gadget<E>
+{
+    let r1cs = R1CS::record(GadgetCircuit::new(gadget, None, None))?;
+
+    Ok(GadgetCost {
+        constraints: r1cs.constraints.iter().filter(|c| c.annotation.starts_with("gadget/")).count(),
+        aux: r1cs.aux_names.iter().filter(|name| name.starts_with("gadget/")).count()
+    })
+}
+
+/// A failure of a gadget to agree with its reference.
+#[derive(Debug)]
+pub enum GadgetCheckError {
+    /// Synthesis failed, or the constraints were not satisfied
+    Synthesis(SynthesisError),
+    /// The output differs from the output of the reference
+    WrongOutput,
+    /// The constraints depend on the witness, so parameters
+    /// generated without one would not fit the proofs
+    WitnessDependentShape
+}
+
+impl From<SynthesisError> for GadgetCheckError {
+    fn from(e: SynthesisError) -> GadgetCheckError {
+        GadgetCheckError::Synthesis(e)
+    }
+}
+
+impl Error for GadgetCheckError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            GadgetCheckError::Synthesis(ref e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for GadgetCheckError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            GadgetCheckError::Synthesis(ref e) => write!(f, "{}", e),
+            GadgetCheckError::WrongOutput => write!(f, "output differs from the reference"),
+            GadgetCheckError::WitnessDependentShape => write!(f, "constraints depend on the witness")
+        }
+    }
+}
+
+/// Checks the gadget against its reference on random samples. For every
+/// sample the constraints must be satisfied, the output must match the
+/// reference, and the constraints must be the same as without a witness.
+pub fn check_gadget<E, G, R>(
+    gadget: &G,
+    rng: &mut R,
+    samples: usize
+) -> Result<(), GadgetCheckError>
+    where E: Engine, G: Reference<E>, R: Rng, <G::Output as Allocate<E>>::Value: PartialEq
+{
+    let shape = R1CS::record(GadgetCircuit::new(gadget, None, None))?;
+
+    for _ in 0..samples {
+        let (input, witness) = gadget.sample(rng);
+        let expected = gadget.evaluate(&input, &witness)?;
+
+        let mut cs = RecordingSystem::new_with_witness();
+        let output = synthesize_exposed(gadget, &mut cs, Some(&input), Some(&witness))?;
+        if output.get_value() != Some(expected) {
+            return Err(GadgetCheckError::WrongOutput);
+        }
+
+        let r1cs = cs.into_r1cs();
+        if diff_r1cs(&shape, &r1cs).keys_changed {
+            return Err(GadgetCheckError::WitnessDependentShape);
+        }
+        r1cs.check_satisfied()?;
+    }
+
+    Ok(())
+}
+
+impl<E: Engine> Allocate<E> for Expr<E> {
+    type Value = E::Fr;
+
+    fn allocate<CS>(
+        cs: CS,
+        value: Option<&E::Fr>
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        Expr::alloc(cs, || value.cloned().ok_or(SynthesisError::AssignmentMissing))
+    }
+
+    fn get_value(&self) -> Option<E::Fr> {
+        Expr::get_value(self)
+    }
+
+    fn inputize<CS>(
+        &self,
+        mut cs: CS
+    ) -> Result<(), SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let input = Expr::alloc_input(&mut cs, || {
+            self.get_value().ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        self.assert_eq(&mut cs, &input)
+    }
+}
+
+impl<E: Engine, A: Allocate<E>, B: Allocate<E>> Allocate<E> for (A, B) {
+    type Value = (A::Value, B::Value);
+
+    fn allocate<CS>(
+        mut cs: CS,
+        value: Option<&Self::Value>
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let a = A::allocate(cs.namespace(|| "0"), value.map(|v| &v.0))?;
+        let b = B::allocate(cs.namespace(|| "1"), value.map(|v| &v.1))?;
+
+        Ok((a, b))
+    }
+
+    fn get_value(&self) -> Option<Self::Value> {
+        match (self.0.get_value(), self.1.get_value()) {
+            (Some(a), Some(b)) => Some((a, b)),
+            _ => None
+        }
+    }
+
+    fn inputize<CS>(
+        &self,
+        mut cs: CS
+    ) -> Result<(), SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        self.0.inputize(cs.namespace(|| "0"))?;
+        self.1.inputize(cs.namespace(|| "1"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bls12_381::{Bls12, Fr};
+    use crate::pairing::ff::Field;
+
+    use rand::thread_rng;
+
+    // x^3, or x^2 if broken
+    struct Cube {
+        broken: bool,
+        branching: bool
+    }
+
+    impl Gadget<Bls12> for Cube {
+        type Input = Expr<Bls12>;
+        type Output = Expr<Bls12>;
+        type Witness = ();
+
+        fn synthesize<CS>(
+            &self,
+            mut cs: CS,
+            x: &Expr<Bls12>,
+            _: Option<&()>
+        ) -> Result<Expr<Bls12>, SynthesisError>
+            where CS: ConstraintSystem<Bls12>
+        {
+            let x2 = x.square(cs.namespace(|| "x^2"))?;
+            if self.broken {
+                return Ok(x2);
+            }
+
+            // Skipping constraints when the value is known
+            if self.branching && x.get_value().is_some() {
+                return Expr::alloc(cs.namespace(|| "x^3"), || {
+                    let mut tmp = x2.get_value().unwrap();
+                    tmp.mul_assign(&x.get_value().unwrap());
+
+                    Ok(tmp)
+                });
+            }
+
+            x2.multiply(cs.namespace(|| "x^3"), x)
+        }
+    }
+
+    impl Reference<Bls12> for Cube {
+        fn sample<R: Rng>(&self, rng: &mut R) -> (Fr, ()) {
+            (rng.gen(), ())
+        }
+
+        fn evaluate(&self, x: &Fr, _: &()) -> Result<Fr, SynthesisError> {
+            let mut tmp = *x;
+            tmp.square();
+            tmp.mul_assign(x);
+
+            Ok(tmp)
+        }
+    }
+
+    #[test]
+    fn test_check_gadget() {
+        let rng = &mut thread_rng();
+
+        let cube = Cube { broken: false, branching: false };
+        check_gadget(&cube, rng, 5).unwrap();
+        assert_eq!(gadget_cost(&cube).unwrap(), GadgetCost { constraints: 2, aux: 2 });
+
+        let r1cs = R1CS::record_with_witness(GadgetCircuit::new(&cube, Some(Fr::one()), Some(()))).unwrap();
+        assert_eq!(r1cs.input_names[1], "output/input");
+        assert_eq!(r1cs.aux_names[0], "input/value");
+        r1cs.check_satisfied().unwrap();
+
+        match check_gadget(&Cube { broken: true, branching: false }, rng, 5) {
+            Err(GadgetCheckError::WrongOutput) => {},
+            _ => panic!("the output is x^2")
+        }
+
+        match check_gadget(&Cube { broken: false, branching: true }, rng, 5) {
+            Err(GadgetCheckError::WitnessDependentShape) => {},
+            _ => panic!("the constraints depend on the witness")
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! Keccak-256 as used by Ethereum, that is the Keccak sponge with a rate
+//! of 1088 bits and the original `pad10*1` padding rather than the domain
+//! separation of SHA-3.
+//!
+//! Bits are ordered as in the byte encoding of the lanes, least significant
+//! bit of every byte first. The permutation is written in terms of lanes, so
+//! that the column parities of theta are computed once and shared by the
+//! five lanes of a column, and chi uses the negated view of a bit instead of
+//! allocating its negation. Both steps then cost two constraints per bit of
+//! the state, and rho, pi and iota are free, so a permutation costs 153600
+//! constraints. Constant lanes such as the capacity of the initial state and
+//! the padding are folded.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::boolean::Boolean;
+use super::uint::UInt64;
+
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001, 0x0000000000008082, 0x800000000000808A, 0x8000000080008000,
+    0x000000000000808B, 0x0000000080000001, 0x8000000080008081, 0x8000000000008009,
+    0x000000000000008A, 0x0000000000000088, 0x0000000080008009, 0x000000008000000A,
+    0x000000008000808B, 0x800000000000008B, 0x8000000000008089, 0x8000000000008003,
+    0x8000000000008002, 0x8000000000000080, 0x000000000000800A, 0x800000008000000A,
+    0x8000000080008081, 0x8000000000008080, 0x0000000080000001, 0x8000000080008008
+];
+
+// Rotation offsets of rho, for the lane at x + 5y
+const ROTATION_OFFSETS: [usize; 25] = [
+    0, 1, 62, 28, 27,
+    36, 44, 6, 55, 20,
+    3, 10, 43, 25, 39,
+    41, 45, 15, 21, 8,
+    18, 2, 61, 56, 14
+];
+
+/// The number of input bits absorbed per permutation.
+pub const RATE_BITS: usize = 1088;
+
+/// The length of the digest in bits.
+pub const DIGEST_BITS: usize = 256;
+
+fn keccak_round<E, CS>(
+    mut cs: CS,
+    a: &[UInt64],
+    round_constant: u64
+) -> Result<Vec<UInt64>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    // theta: C[x] = A[x, 0] ^ ... ^ A[x, 4]
+    let c = (0..5).map(|x| {
+        let mut cs = cs.namespace(|| format!("parity of column {}", x));
+
+        let mut parity = a[x].clone();
+        for y in 1..5 {
+            parity = parity.xor(cs.namespace(|| format!("xor of row {}", y)), &a[x + 5 * y])?;
+        }
+
+        Ok(parity)
+    }).collect::<Result<Vec<_>, SynthesisError>>()?;
+
+    // theta: D[x] = C[x - 1] ^ rot(C[x + 1], 1)
+    let d = (0..5).map(|x| {
+        c[(x + 4) % 5].xor(cs.namespace(|| format!("theta effect {}", x)), &c[(x + 1) % 5].rotl(1))
+    }).collect::<Result<Vec<_>, SynthesisError>>()?;
+
+    // theta: A[x, y] = A[x, y] ^ D[x]
+    let a = a.iter().enumerate().map(|(i, lane)| {
+        lane.xor(cs.namespace(|| format!("theta {}", i)), &d[i % 5])
+    }).collect::<Result<Vec<_>, SynthesisError>>()?;
+
+    // rho and pi: B[y, 2x + 3y] = rot(A[x, y], r[x, y])
+    let mut b = vec![UInt64::constant(0); 25];
+    for x in 0..5 {
+        for y in 0..5 {
+            b[y + 5 * ((2 * x + 3 * y) % 5)] = a[x + 5 * y].rotl(ROTATION_OFFSETS[x + 5 * y]);
+        }
+    }
+
+    // chi: A[x, y] = B[x, y] ^ ((NOT B[x + 1, y]) AND B[x + 2, y])
+    let mut a = (0..25).map(|i| {
+        let (x, y) = (i % 5, i / 5);
+
+        let t = b[(x + 1) % 5 + 5 * y].not().and(
+            cs.namespace(|| format!("chi and {}", i)),
+            &b[(x + 2) % 5 + 5 * y]
+        )?;
+
+        b[i].xor(cs.namespace(|| format!("chi xor {}", i)), &t)
+    }).collect::<Result<Vec<_>, SynthesisError>>()?;
+
+    // iota
+    a[0] = a[0].xor(cs.namespace(|| "iota"), &UInt64::constant(round_constant))?;
+
+    Ok(a)
+}
+
+/// The Keccak-f[1600] permutation of a state of 25 lanes, indexed by
+/// `x + 5y`.
+pub fn keccak_f1600<E, CS>(
+    mut cs: CS,
+    state: &[UInt64]
+) -> Result<Vec<UInt64>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(state.len(), 25);
+
+    let mut state = state.to_vec();
+    for (i, &round_constant) in ROUND_CONSTANTS.iter().enumerate() {
+        state = keccak_round(
+            cs.namespace(|| format!("round {}", i)),
+            &state,
+            round_constant
+        )?;
+    }
+
+    Ok(state)
+}
+
+/// Hashes a message of any bit length. For byte strings this is the
+/// `keccak256` of Ethereum. Returns the 256 bits of the digest.
+pub fn keccak256<E, CS>(
+    mut cs: CS,
+    input: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    // pad10*1: a set bit, zeros, and a set bit at the end of a block
+    let mut padded = input.to_vec();
+    padded.push(Boolean::constant(true));
+    while !(padded.len() + 1).is_multiple_of(RATE_BITS) {
+        padded.push(Boolean::constant(false));
+    }
+    padded.push(Boolean::constant(true));
+
+    let mut state = vec![UInt64::constant(0); 25];
+    for (i, block) in padded.chunks(RATE_BITS).enumerate() {
+        let mut cs = cs.namespace(|| format!("block {}", i));
+
+        for (j, lane) in block.chunks(64).enumerate() {
+            state[j] = state[j].xor(
+                cs.namespace(|| format!("absorb lane {}", j)),
+                &UInt64::from_bits(lane)
+            )?;
+        }
+
+        state = keccak_f1600(cs.namespace(|| "permutation"), &state)?;
+    }
+
+    Ok(state.iter().flat_map(|lane| lane.into_bits()).take(DIGEST_BITS).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bls12_381::Bls12;
+    use crate::gadgets::boolean::AllocatedBit;
+    use crate::r1cs::RecordingSystem;
+
+    use tiny_keccak::Keccak;
+    use rand::{Rng, thread_rng};
+
+    fn bytes_to_bits_le(bytes: &[u8]) -> Vec<bool> {
+        bytes.iter().flat_map(|&byte| (0..8).map(move |i| (byte >> i) & 1 == 1)).collect()
+    }
+
+    fn reference(input: &[u8]) -> Vec<bool> {
+        let mut h = Keccak::new_keccak256();
+        h.update(input);
+
+        let mut res = [0u8; 32];
+        h.finalize(&mut res);
+
+        bytes_to_bits_le(&res)
+    }
+
+    fn alloc_bits<CS: ConstraintSystem<Bls12>>(mut cs: CS, bits: &[bool]) -> Vec<Boolean> {
+        bits.iter().enumerate().map(|(i, &b)| {
+            Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("input bit {}", i)), Some(b)).unwrap())
+        }).collect()
+    }
+
+    #[test]
+    fn test_keccak256_against_reference() {
+        let rng = &mut thread_rng();
+
+        for len in [0, 32, 135, 136].iter().cloned().chain(Some(rng.gen_range(0, 200))) {
+            let input: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+
+            let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+            let input_bits = alloc_bits(cs.namespace(|| "input"), &bytes_to_bits_le(&input));
+
+            let digest = keccak256(cs.namespace(|| "keccak"), &input_bits).unwrap();
+            let digest: Vec<bool> = digest.iter().map(|b| b.get_value().unwrap()).collect();
+            assert_eq!(digest, reference(&input), "input of {} bytes", len);
+
+            cs.into_r1cs().check_satisfied().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_keccak256_constant_input() {
+        let input_bits: Vec<_> = bytes_to_bits_le(b"").into_iter().map(Boolean::constant).collect();
+
+        let mut cs = RecordingSystem::<Bls12>::new();
+        let digest = keccak256(&mut cs, &input_bits).unwrap();
+        assert_eq!(cs.into_r1cs().num_constraints(), 0);
+
+        // keccak256 of the empty string
+        let digest: Vec<bool> = digest.iter().map(|b| b.get_value().unwrap()).collect();
+        let expected = [
+            0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c, 0x92, 0x7e, 0x7d, 0xb2, 0xdc, 0xc7, 0x03, 0xc0,
+            0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b, 0x7b, 0xfa, 0xd8, 0x04, 0x5d, 0x85, 0xa4, 0x70
+        ];
+        assert_eq!(digest, bytes_to_bits_le(&expected));
+    }
+
+    #[test]
+    fn test_keccak_f1600_constraints() {
+        let rng = &mut thread_rng();
+
+        let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+        let state: Vec<_> = (0..25).map(|i| {
+            UInt64::alloc(cs.namespace(|| format!("lane {}", i)), Some(rng.gen())).unwrap()
+        }).collect();
+        keccak_f1600(cs.namespace(|| "permutation"), &state).unwrap();
+
+        let r1cs = cs.into_r1cs();
+        r1cs.check_satisfied().unwrap();
+        assert_eq!(r1cs.constraints.iter().filter(|c| c.annotation.starts_with("permutation/")).count(), 153600);
+    }
+}
@@ -0,0 +1,406 @@
+//! Lookups of constants by bits, as in windowed fixed-base scalar
+//! multiplication.
+//!
+//! Bits index a table in little-endian order. An entry is a polynomial in
+//! the bits that is linear in each of them, so that with the product of
+//! the low two bits at hand, any three bits select an entry with a single
+//! constraint: the top bit times a linear combination of the others. The
+//! costs in constraints are
+//!
+//! | gadget                                   | cost          |
+//! |------------------------------------------|---------------|
+//! | `lookup2`                                | 1             |
+//! | `lookup3`                                | 2             |
+//! | `lookup3_xy`                             | 3             |
+//! | `lookup3_xy_with_conditional_negation`   | 3             |
+//! | `mux` of `k >= 3` bits                   | `2^(k - 2)`   |
+//!
+//! `mux` looks up the low three bits in every eighth of the table, sharing
+//! their product, and then selects among the results by the other bits.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::pairing::ff::{
+    Field
+};
+
+use crate::{
+    SynthesisError,
+    ConstraintSystem,
+    LinearCombination,
+    Variable
+};
+
+use super::boolean::Boolean;
+use super::num::AllocatedNum;
+
+// The entry of the table at the index that the bits make up
+fn entry_value<E: Engine>(bits: &[Boolean], table: &[E::Fr]) -> Option<E::Fr> {
+    let mut index = 0;
+    for (i, bit) in bits.iter().enumerate() {
+        if bit.get_value()? {
+            index |= 1 << i;
+        }
+    }
+
+    Some(table[index])
+}
+
+// c0 + b (c1 - c0)
+fn lookup1_lc<E: Engine>(
+    one: Variable,
+    bit: &Boolean,
+    table: &[E::Fr]
+) -> LinearCombination<E>
+{
+    let mut slope = table[1];
+    slope.sub_assign(&table[0]);
+
+    LinearCombination::zero() + (table[0], one) + &bit.lc(one, slope)
+}
+
+// c0 + b0 (c1 - c0) + b1 (c2 - c0) + b0 b1 (c3 - c2 - c1 + c0), where
+// the product of the bits is given
+fn lookup2_lc<E: Engine>(
+    one: Variable,
+    bits: &[Boolean],
+    product: &Boolean,
+    table: &[E::Fr]
+) -> LinearCombination<E>
+{
+    let mut low = table[1];
+    low.sub_assign(&table[0]);
+
+    let mut high = table[2];
+    high.sub_assign(&table[0]);
+
+    let mut both = table[3];
+    both.sub_assign(&table[2]);
+    both.sub_assign(&low);
+
+    LinearCombination::zero()
+        + (table[0], one)
+        + &bits[0].lc(one, low)
+        + &bits[1].lc(one, high)
+        + &product.lc(one, both)
+}
+
+// Allocates the entry of the table at the index of the bits. The entry is
+// `select(low) + top * select(high - low)` for the halves of the table
+// that the top bit chooses between, where `select` picks from a half by
+// the other bits.
+fn alloc_entry<E, CS, F>(
+    mut cs: CS,
+    bits: &[Boolean],
+    table: &[E::Fr],
+    select: F
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>, F: Fn(&[E::Fr]) -> LinearCombination<E>
+{
+    assert_eq!(table.len(), 1 << bits.len(), "the table must have an entry for every index");
+
+    let top = &bits[bits.len() - 1];
+    let (low, high) = table.split_at(table.len() / 2);
+    let slopes: Vec<E::Fr> = low.iter().zip(high.iter()).map(|(l, h)| {
+        let mut slope = *h;
+        slope.sub_assign(l);
+
+        slope
+    }).collect();
+
+    let entry = AllocatedNum::alloc(cs.namespace(|| "entry"), || {
+        entry_value::<E>(bits, table).ok_or(SynthesisError::AssignmentMissing)
+    })?;
+
+    cs.enforce(
+        || "lookup constraint",
+        |lc| lc + &top.lc(CS::one(), E::Fr::one()),
+        |lc| lc + &select(&slopes),
+        |lc| lc + entry.get_variable() - &select(low)
+    );
+
+    Ok(entry)
+}
+
+fn lookup3_with_product<E, CS>(
+    cs: CS,
+    bits: &[Boolean],
+    product: &Boolean,
+    table: &[E::Fr]
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    alloc_entry(cs, bits, table, |quarter| lookup2_lc(CS::one(), &bits[..2], product, quarter))
+}
+
+/// Returns the entry of a table of four constants at the index of two
+/// little-endian bits. Costs a single constraint.
+pub fn lookup2<E, CS>(
+    cs: CS,
+    bits: &[Boolean],
+    table: &[E::Fr]
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(bits.len(), 2);
+
+    alloc_entry(cs, bits, table, |half| lookup1_lc(CS::one(), &bits[0], half))
+}
+
+/// Returns the entry of a table of eight constants at the index of three
+/// little-endian bits. Costs two constraints.
+pub fn lookup3<E, CS>(
+    mut cs: CS,
+    bits: &[Boolean],
+    table: &[E::Fr]
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(bits.len(), 3);
+
+    let product = Boolean::and(cs.namespace(|| "product"), &bits[0], &bits[1])?;
+
+    lookup3_with_product(cs.namespace(|| "lookup"), bits, &product, table)
+}
+
+/// Returns the coordinates of one of eight constant points, at the index
+/// of three little-endian bits. Costs three constraints.
+pub fn lookup3_xy<E, CS>(
+    mut cs: CS,
+    bits: &[Boolean],
+    coords: &[(E::Fr, E::Fr)]
+) -> Result<(AllocatedNum<E>, AllocatedNum<E>), SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(bits.len(), 3);
+    assert_eq!(coords.len(), 8);
+
+    let xs: Vec<E::Fr> = coords.iter().map(|c| c.0).collect();
+    let ys: Vec<E::Fr> = coords.iter().map(|c| c.1).collect();
+
+    let product = Boolean::and(cs.namespace(|| "product"), &bits[0], &bits[1])?;
+
+    let x = lookup3_with_product(cs.namespace(|| "x"), bits, &product, &xs)?;
+    let y = lookup3_with_product(cs.namespace(|| "y"), bits, &product, &ys)?;
+
+    Ok((x, y))
+}
+
+/// Returns the coordinates of one of four constant points, at the index of
+/// the low two bits, with the y-coordinate negated if the third bit is set.
+/// This suits curves on which negation only flips y. Costs three
+/// constraints.
+pub fn lookup3_xy_with_conditional_negation<E, CS>(
+    mut cs: CS,
+    bits: &[Boolean],
+    coords: &[(E::Fr, E::Fr)]
+) -> Result<(AllocatedNum<E>, AllocatedNum<E>), SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(bits.len(), 3);
+    assert_eq!(coords.len(), 4);
+
+    let xs: Vec<E::Fr> = coords.iter().map(|c| c.0).collect();
+    let ys: Vec<E::Fr> = coords.iter().map(|c| c.1).collect();
+
+    let x = lookup2(cs.namespace(|| "x"), &bits[..2], &xs)?;
+
+    let product = Boolean::and(cs.namespace(|| "product"), &bits[0], &bits[1])?;
+
+    let y_value = entry_value::<E>(&bits[..2], &ys).and_then(|mut y| {
+        if bits[2].get_value()? {
+            y.negate();
+        }
+
+        Some(y)
+    });
+    let y = AllocatedNum::alloc(cs.namespace(|| "y"), || {
+        y_value.ok_or(SynthesisError::AssignmentMissing)
+    })?;
+
+    let mut minus_two = E::Fr::one();
+    minus_two.double();
+    minus_two.negate();
+
+    // Constrain: y' * (1 - 2 * negate) = y
+    cs.enforce(
+        || "negation constraint",
+        |lc| lc + &lookup2_lc(CS::one(), &bits[..2], &product, &ys),
+        |lc| lc + CS::one() + &bits[2].lc(CS::one(), minus_two),
+        |lc| lc + y.get_variable()
+    );
+
+    Ok((x, y))
+}
+
+/// Returns the entry of a table of `2^k` constants at the index of `k`
+/// little-endian bits. Costs `2^(k - 2)` constraints for `k >= 2`, and a
+/// single one for a single bit.
+pub fn mux<E, CS>(
+    mut cs: CS,
+    bits: &[Boolean],
+    table: &[E::Fr]
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert!(!bits.is_empty(), "a table of a single constant needs no lookup");
+    assert_eq!(table.len(), 1 << bits.len(), "the table must have an entry for every index");
+
+    match bits.len() {
+        1 => return alloc_entry(cs, bits, table, |half| LinearCombination::zero() + (half[0], CS::one())),
+        2 => return lookup2(cs, bits, table),
+        _ => {}
+    }
+
+    let product = Boolean::and(cs.namespace(|| "product"), &bits[0], &bits[1])?;
+
+    let mut entries = table.chunks(8).enumerate().map(|(i, chunk)| {
+        lookup3_with_product(cs.namespace(|| format!("lookup {}", i)), &bits[..3], &product, chunk)
+    }).collect::<Result<Vec<_>, _>>()?;
+
+    // Each of the remaining bits halves the candidates
+    for (i, bit) in bits[3..].iter().enumerate() {
+        entries = entries.chunks(2).enumerate().map(|(j, pair)| {
+            AllocatedNum::conditionally_select(
+                cs.namespace(|| format!("select {} {}", i, j)),
+                &pair[1],
+                &pair[0],
+                bit
+            )
+        }).collect::<Result<Vec<_>, _>>()?;
+    }
+
+    Ok(entries.pop().expect("a single candidate remains"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bn256::{Bn256, Fr};
+    use crate::r1cs::{R1CS, RecordingSystem};
+    use crate::gadgets::boolean::AllocatedBit;
+
+    use rand::{Rng, thread_rng};
+
+    fn alloc_bits(cs: &mut RecordingSystem<Bn256>, name: &str, index: usize, k: usize) -> Vec<Boolean> {
+        (0..k).map(|i| {
+            let bit = AllocatedBit::alloc(cs.namespace(|| format!("{} {}", name, i)), Some((index >> i) & 1 == 1)).unwrap();
+
+            Boolean::from(bit)
+        }).collect()
+    }
+
+    fn count(r1cs: &R1CS<Bn256>, prefix: &str) -> usize {
+        r1cs.constraints.iter().filter(|c| c.annotation.starts_with(prefix)).count()
+    }
+
+    #[test]
+    fn test_lookups() {
+        let rng = &mut thread_rng();
+        let table: Vec<Fr> = (0..8).map(|_| rng.gen()).collect();
+        let coords: Vec<(Fr, Fr)> = (0..8).map(|_| (rng.gen(), rng.gen())).collect();
+
+        for index in 0..8 {
+            let mut cs = RecordingSystem::<Bn256>::new_with_witness();
+            let bits = alloc_bits(&mut cs, "bit", index, 3);
+
+            let two = lookup2(cs.namespace(|| "lookup2"), &bits[..2], &table[..4]).unwrap();
+            let three = lookup3(cs.namespace(|| "lookup3"), &bits, &table).unwrap();
+            let (x, y) = lookup3_xy(cs.namespace(|| "xy"), &bits, &coords).unwrap();
+            let (nx, ny) = lookup3_xy_with_conditional_negation(cs.namespace(|| "negation"), &bits, &coords[..4]).unwrap();
+
+            assert_eq!(two.get_value(), Some(table[index & 3]));
+            assert_eq!(three.get_value(), Some(table[index]));
+            assert_eq!((x.get_value(), y.get_value()), (Some(coords[index].0), Some(coords[index].1)));
+
+            let mut negated = coords[index & 3].1;
+            if index & 4 != 0 {
+                negated.negate();
+            }
+            assert_eq!((nx.get_value(), ny.get_value()), (Some(coords[index & 3].0), Some(negated)));
+
+            let r1cs = cs.into_r1cs();
+            r1cs.check_satisfied().unwrap();
+            assert_eq!(count(&r1cs, "lookup2/"), 1);
+            assert_eq!(count(&r1cs, "lookup3/"), 2);
+            assert_eq!(count(&r1cs, "xy/"), 3);
+            assert_eq!(count(&r1cs, "negation/"), 3);
+        }
+    }
+
+    #[test]
+    fn test_negated_and_constant_bits() {
+        let rng = &mut thread_rng();
+        let table: Vec<Fr> = (0..8).map(|_| rng.gen()).collect();
+
+        for index in 0..4 {
+            let mut cs = RecordingSystem::<Bn256>::new_with_witness();
+            let mut bits: Vec<Boolean> = alloc_bits(&mut cs, "bit", !index, 2).iter().map(|b| b.not()).collect();
+            bits.push(Boolean::constant(true));
+
+            let entry = lookup3(cs.namespace(|| "lookup3"), &bits, &table).unwrap();
+            assert_eq!(entry.get_value(), Some(table[index | 4]));
+
+            cs.into_r1cs().check_satisfied().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_mux() {
+        let rng = &mut thread_rng();
+
+        for k in 1..7 {
+            let table: Vec<Fr> = (0..1 << k).map(|_| rng.gen()).collect();
+
+            for _ in 0..4 {
+                let index = rng.gen_range(0, 1 << k);
+
+                let mut cs = RecordingSystem::<Bn256>::new_with_witness();
+                let bits = alloc_bits(&mut cs, "bit", index, k);
+                let entry = mux(cs.namespace(|| "mux"), &bits, &table).unwrap();
+                assert_eq!(entry.get_value(), Some(table[index]));
+
+                let r1cs = cs.into_r1cs();
+                r1cs.check_satisfied().unwrap();
+                assert_eq!(count(&r1cs, "mux/"), if k < 2 { 1 } else { 1 << (k - 2) });
+            }
+        }
+    }
+
+    #[test]
+    fn test_wrong_entry() {
+        let rng = &mut thread_rng();
+        let table: Vec<Fr> = (0..32).map(|_| rng.gen()).collect();
+        let coords: Vec<(Fr, Fr)> = (0..4).map(|_| (rng.gen(), rng.gen())).collect();
+
+        for index in 0..8 {
+            let mut cs = RecordingSystem::<Bn256>::new_with_witness();
+            let bits = alloc_bits(&mut cs, "bit", index, 3);
+            lookup3(cs.namespace(|| "lookup3"), &bits, &table[..8]).unwrap();
+
+            let mut r1cs = cs.into_r1cs();
+            r1cs.aux_assignment.as_mut().unwrap().last_mut().unwrap().add_assign(&Fr::one());
+            assert!(r1cs.check_satisfied().is_err());
+
+            // The entry must not be negated unless the bit is set
+            let mut cs = RecordingSystem::<Bn256>::new_with_witness();
+            let bits = alloc_bits(&mut cs, "bit", index, 3);
+            lookup3_xy_with_conditional_negation(cs.namespace(|| "negation"), &bits, &coords).unwrap();
+
+            let mut r1cs = cs.into_r1cs();
+            r1cs.aux_assignment.as_mut().unwrap().last_mut().unwrap().negate();
+            assert!(r1cs.check_satisfied().is_err());
+        }
+
+        let mut cs = RecordingSystem::<Bn256>::new_with_witness();
+        let bits = alloc_bits(&mut cs, "bit", 21, 5);
+        mux(cs.namespace(|| "mux"), &bits, &table).unwrap();
+
+        let mut r1cs = cs.into_r1cs();
+        r1cs.aux_assignment.as_mut().unwrap().last_mut().unwrap().add_assign(&Fr::one());
+        assert!(r1cs.check_satisfied().is_err());
+    }
+}
@@ -0,0 +1,442 @@
+//! Merkle paths in a constraint system. Every level of a path costs a
+//! conditional swap of two constraints that orders the node and its
+//! sibling, and a hash. With Poseidon of width 3 that is 243 constraints
+//! per level, and 1323 with the MiMC sponge.
+//!
+//! Paths in sparse trees take their bits from the decomposition of the key,
+//! which costs 354 constraints over BN256, and have one level per bit.
+//! Inclusion, exclusion and insertion are then the membership and update
+//! of a leaf that is required to be non-zero or zero.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use crate::gadgets::boolean::{
+    AllocatedBit,
+    Boolean
+};
+use crate::gadgets::expr::Expr;
+use crate::gadgets::num::AllocatedNum;
+
+use super::MerkleHasher;
+use super::native::{
+    MerklePath,
+    SparseMerkleProof
+};
+
+/// An authentication path of a fixed depth in a constraint system.
+#[derive(Clone)]
+pub struct AllocatedPath<E: Engine> {
+    /// Whether the node on the path is a right child, from the bottom up
+    pub bits: Vec<Boolean>,
+    /// The siblings from the bottom up
+    pub siblings: Vec<AllocatedNum<E>>
+}
+
+impl<E: Engine> AllocatedPath<E> {
+    /// Allocates the bits and siblings of the path as private variables.
+    /// The path is only needed when proving, but must have the given depth.
+    pub fn alloc<CS>(
+        mut cs: CS,
+        depth: usize,
+        path: Option<&MerklePath<E>>
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        if let Some(path) = path {
+            assert_eq!(path.depth(), depth);
+        }
+
+        let bits = path.map(|path| path.bits());
+
+        let mut result = AllocatedPath {
+            bits: Vec::with_capacity(depth),
+            siblings: Vec::with_capacity(depth)
+        };
+        for i in 0..depth {
+            let bit = AllocatedBit::alloc(
+                cs.namespace(|| format!("bit {}", i)),
+                bits.as_ref().map(|bits| bits[i])
+            )?;
+            result.bits.push(Boolean::from(bit));
+
+            let sibling = AllocatedNum::alloc(cs.namespace(|| format!("sibling {}", i)), || {
+                path.map(|path| path.siblings[i]).ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            result.siblings.push(sibling);
+        }
+
+        Ok(result)
+    }
+
+    /// Allocates the path to the key in a sparse tree. The bits of the
+    /// path are the strict decomposition of the key, so that every key has
+    /// exactly one position, and the proof is only needed when proving.
+    pub fn alloc_sparse<CS>(
+        mut cs: CS,
+        key: &AllocatedNum<E>,
+        proof: Option<&SparseMerkleProof<E>>
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let bits = key.to_bits_le_strict(cs.namespace(|| "key bits"))?;
+
+        let siblings = (0..bits.len()).map(|i| {
+            AllocatedNum::alloc(cs.namespace(|| format!("sibling {}", i)), || {
+                proof.map(|proof| proof.siblings[i]).ok_or(SynthesisError::AssignmentMissing)
+            })
+        }).collect::<Result<Vec<_>, SynthesisError>>()?;
+
+        Ok(AllocatedPath {
+            bits: bits,
+            siblings: siblings
+        })
+    }
+
+    pub fn depth(&self) -> usize {
+        self.bits.len()
+    }
+}
+
+/// Computes the root of a tree that holds the leaf on the path.
+pub fn compute_root<E, CS, H>(
+    mut cs: CS,
+    hasher: &H,
+    leaf: &AllocatedNum<E>,
+    path: &AllocatedPath<E>
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>, H: MerkleHasher<E>
+{
+    let mut node = leaf.clone();
+    for (i, (bit, sibling)) in path.bits.iter().zip(path.siblings.iter()).enumerate() {
+        let mut cs = cs.namespace(|| format!("level {}", i));
+
+        // The node is on the right if the bit is set
+        let (left, right) = AllocatedNum::conditionally_reverse(
+            cs.namespace(|| "swap"),
+            &node,
+            sibling,
+            bit
+        )?;
+
+        node = hasher.hash_circuit(cs.namespace(|| "hash"), &left, &right)?;
+    }
+
+    Ok(node)
+}
+
+fn enforce_equal<E, CS>(
+    mut cs: CS,
+    a: &AllocatedNum<E>,
+    b: &AllocatedNum<E>
+)
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    cs.enforce(
+        || "equality constraint",
+        |lc| lc + a.get_variable() - b.get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc
+    );
+}
+
+/// Enforces that the tree with the given root holds the leaf on the path.
+pub fn assert_membership<E, CS, H>(
+    mut cs: CS,
+    hasher: &H,
+    leaf: &AllocatedNum<E>,
+    path: &AllocatedPath<E>,
+    root: &AllocatedNum<E>
+) -> Result<(), SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>, H: MerkleHasher<E>
+{
+    let computed = compute_root(cs.namespace(|| "path"), hasher, leaf, path)?;
+    enforce_equal(cs.namespace(|| "root"), &computed, root);
+
+    Ok(())
+}
+
+/// Enforces that the tree with the old root holds the old leaf on the
+/// path, and returns the root of the tree in which the leaf is replaced
+/// by the new one. This costs two paths.
+pub fn update<E, CS, H>(
+    mut cs: CS,
+    hasher: &H,
+    old_leaf: &AllocatedNum<E>,
+    new_leaf: &AllocatedNum<E>,
+    path: &AllocatedPath<E>,
+    old_root: &AllocatedNum<E>
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>, H: MerkleHasher<E>
+{
+    assert_membership(cs.namespace(|| "old"), hasher, old_leaf, path, old_root)?;
+
+    compute_root(cs.namespace(|| "new"), hasher, new_leaf, path)
+}
+
+/// Enforces that the key of the sparse path holds the value, which must
+/// not be zero, in the tree with the given root.
+pub fn assert_inclusion<E, CS, H>(
+    mut cs: CS,
+    hasher: &H,
+    value: &AllocatedNum<E>,
+    path: &AllocatedPath<E>,
+    root: &AllocatedNum<E>
+) -> Result<(), SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>, H: MerkleHasher<E>
+{
+    value.assert_nonzero(cs.namespace(|| "value is not zero"))?;
+
+    assert_membership(cs.namespace(|| "membership"), hasher, value, path, root)
+}
+
+/// Enforces that the key of the sparse path is absent from the tree with
+/// the given root, that is its leaf is zero.
+pub fn assert_exclusion<E, CS, H>(
+    mut cs: CS,
+    hasher: &H,
+    path: &AllocatedPath<E>,
+    root: &AllocatedNum<E>
+) -> Result<(), SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>, H: MerkleHasher<E>
+{
+    let empty = AllocatedNum::from_expr(cs.namespace(|| "empty leaf"), &Expr::zero())?;
+
+    assert_membership(cs.namespace(|| "membership"), hasher, &empty, path, root)
+}
+
+/// Enforces that the key of the sparse path is absent from the tree with
+/// the old root, and returns the root after inserting the value, which
+/// must not be zero.
+pub fn insert<E, CS, H>(
+    mut cs: CS,
+    hasher: &H,
+    value: &AllocatedNum<E>,
+    path: &AllocatedPath<E>,
+    old_root: &AllocatedNum<E>
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>, H: MerkleHasher<E>
+{
+    value.assert_nonzero(cs.namespace(|| "value is not zero"))?;
+    let empty = AllocatedNum::from_expr(cs.namespace(|| "empty leaf"), &Expr::zero())?;
+
+    update(cs.namespace(|| "update"), hasher, &empty, value, path, old_root)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use super::super::native::{MerkleTree, SparseMerkleTree};
+    use super::super::Sha256Hasher;
+
+    use crate::pairing::bn256::{Bn256, Fr};
+    use crate::pairing::ff::Field;
+    #[cfg(feature = "mimc")]
+    use crate::gadgets::mimc::MiMCParams;
+    use crate::gadgets::poseidon::PoseidonParams;
+    use crate::r1cs::RecordingSystem;
+
+    use rand::{Rng, thread_rng};
+
+    fn random_tree<H: MerkleHasher<Bn256>>(hasher: H, depth: usize) -> MerkleTree<Bn256, H> {
+        let rng = &mut thread_rng();
+
+        let mut tree = MerkleTree::new(hasher, depth, Fr::zero());
+        for _ in 0..5 {
+            tree.set(rng.gen_range(0, 1 << depth), rng.gen());
+        }
+
+        tree
+    }
+
+    fn check_membership<H: MerkleHasher<Bn256>>(hasher: H, depth: usize, constraints_per_level: usize) {
+        let rng = &mut thread_rng();
+        let tree = random_tree(hasher, depth);
+        let index = rng.gen_range(0, 1 << depth);
+
+        let mut cs = RecordingSystem::<Bn256>::new_with_witness();
+        let leaf = AllocatedNum::alloc(cs.namespace(|| "leaf"), || Ok(tree.leaf(index))).unwrap();
+        let path = AllocatedPath::alloc(cs.namespace(|| "path"), depth, Some(&tree.path(index))).unwrap();
+        let root = AllocatedNum::alloc(cs.namespace(|| "root"), || Ok(tree.root())).unwrap();
+        root.inputize(cs.namespace(|| "root input")).unwrap();
+
+        assert_membership(cs.namespace(|| "membership"), tree.hasher(), &leaf, &path, &root).unwrap();
+
+        let mut r1cs = cs.into_r1cs();
+        r1cs.check_satisfied().unwrap();
+        assert_eq!(
+            r1cs.constraints.iter().filter(|c| c.annotation.starts_with("membership/")).count(),
+            depth * constraints_per_level + 1
+        );
+
+        // Another root must not verify
+        r1cs.input_assignment.as_mut().unwrap()[1].add_assign(&Fr::one());
+        assert!(r1cs.check_satisfied().is_err());
+    }
+
+    #[test]
+    fn test_membership() {
+        check_membership(PoseidonParams::<Bn256>::new(3, 128, 5).unwrap(), 20, 243);
+        #[cfg(feature = "mimc")]
+        check_membership(MiMCParams::<Bn256>::circomlib(), 4, 1323);
+        check_membership(Sha256Hasher, 2, 45849);
+    }
+
+    #[test]
+    fn test_wrong_leaf_or_path() {
+        let rng = &mut thread_rng();
+        let mut tree = random_tree(PoseidonParams::<Bn256>::new(3, 128, 5).unwrap(), 8);
+        let index = rng.gen_range(0, 1 << 8);
+
+        // Swapping a leaf with an equal sibling would not be noticed
+        tree.set(index, rng.gen());
+        tree.set(index ^ 1, rng.gen());
+
+        let mut wrong_path = tree.path(index);
+        wrong_path.index ^= 1;
+
+        let cases = [
+            (rng.gen(), tree.path(index)),
+            (tree.leaf(index), wrong_path)
+        ];
+        for (leaf, path) in cases.iter() {
+            let mut cs = RecordingSystem::<Bn256>::new_with_witness();
+            let leaf = AllocatedNum::alloc(cs.namespace(|| "leaf"), || Ok(*leaf)).unwrap();
+            let path = AllocatedPath::alloc(cs.namespace(|| "path"), 8, Some(path)).unwrap();
+            let root = AllocatedNum::alloc(cs.namespace(|| "root"), || Ok(tree.root())).unwrap();
+            assert_membership(cs.namespace(|| "membership"), tree.hasher(), &leaf, &path, &root).unwrap();
+
+            assert!(cs.into_r1cs().check_satisfied().is_err());
+        }
+    }
+
+    #[test]
+    fn test_update() {
+        let rng = &mut thread_rng();
+        let mut tree = random_tree(PoseidonParams::<Bn256>::new(3, 128, 5).unwrap(), 10);
+        let index = rng.gen_range(0, 1 << 10);
+        let (old_leaf, new_leaf, old_root, path) = (tree.leaf(index), rng.gen(), tree.root(), tree.path(index));
+        tree.set(index, new_leaf);
+
+        let mut cs = RecordingSystem::<Bn256>::new_with_witness();
+        let old_leaf = AllocatedNum::alloc(cs.namespace(|| "old leaf"), || Ok(old_leaf)).unwrap();
+        let new_leaf = AllocatedNum::alloc(cs.namespace(|| "new leaf"), || Ok(new_leaf)).unwrap();
+        let path = AllocatedPath::alloc(cs.namespace(|| "path"), 10, Some(&path)).unwrap();
+        let old_root = AllocatedNum::alloc(cs.namespace(|| "old root"), || Ok(old_root)).unwrap();
+
+        let new_root = update(cs.namespace(|| "update"), tree.hasher(), &old_leaf, &new_leaf, &path, &old_root).unwrap();
+        assert_eq!(new_root.get_value(), Some(tree.root()));
+
+        let r1cs = cs.into_r1cs();
+        r1cs.check_satisfied().unwrap();
+        assert_eq!(r1cs.constraints.iter().filter(|c| c.annotation.starts_with("update/")).count(), 2 * 10 * 243 + 1);
+    }
+
+    #[test]
+    fn test_shape_without_witness() {
+        let params = PoseidonParams::<Bn256>::new(3, 128, 5).unwrap();
+
+        let mut cs = RecordingSystem::<Bn256>::new();
+        let leaf = AllocatedNum::alloc(cs.namespace(|| "leaf"), || Err(SynthesisError::AssignmentMissing)).unwrap();
+        let path = AllocatedPath::alloc(cs.namespace(|| "path"), 32, None).unwrap();
+        let root = compute_root(cs.namespace(|| "path root"), &params, &leaf, &path).unwrap();
+        assert!(root.get_value().is_none());
+
+        assert_eq!(cs.into_r1cs().num_constraints(), 32 + 32 * 243);
+    }
+
+    fn sparse_tree() -> SparseMerkleTree<Bn256, PoseidonParams<Bn256>> {
+        let rng = &mut thread_rng();
+
+        let mut tree = SparseMerkleTree::new(PoseidonParams::new(3, 128, 5).unwrap());
+        for _ in 0..3 {
+            tree.insert(&rng.gen(), rng.gen());
+        }
+
+        tree
+    }
+
+    // Allocates the key, its path and the root, which is public
+    fn alloc_sparse<CS: ConstraintSystem<Bn256>>(
+        mut cs: CS,
+        tree: &SparseMerkleTree<Bn256, PoseidonParams<Bn256>>,
+        key: &Fr
+    ) -> (AllocatedPath<Bn256>, AllocatedNum<Bn256>)
+    {
+        let proof = tree.prove(key);
+
+        let key = AllocatedNum::alloc(cs.namespace(|| "key"), || Ok(*key)).unwrap();
+        let path = AllocatedPath::alloc_sparse(cs.namespace(|| "path"), &key, Some(&proof)).unwrap();
+        let root = AllocatedNum::alloc(cs.namespace(|| "root"), || Ok(tree.root())).unwrap();
+        root.inputize(cs.namespace(|| "root input")).unwrap();
+
+        (path, root)
+    }
+
+    #[test]
+    fn test_sparse_inclusion_and_exclusion() {
+        let rng = &mut thread_rng();
+        let mut tree = sparse_tree();
+        let (key, value): (Fr, Fr) = (rng.gen(), rng.gen());
+        tree.insert(&key, value);
+
+        let mut cs = RecordingSystem::<Bn256>::new_with_witness();
+        let (path, root) = alloc_sparse(cs.namespace(|| "sparse"), &tree, &key);
+        assert_eq!(path.depth(), 254);
+        let value_var = AllocatedNum::alloc(cs.namespace(|| "value"), || Ok(value)).unwrap();
+        assert_inclusion(cs.namespace(|| "inclusion"), tree.hasher(), &value_var, &path, &root).unwrap();
+
+        let r1cs = cs.into_r1cs();
+        r1cs.check_satisfied().unwrap();
+        assert_eq!(r1cs.constraints.iter().filter(|c| c.annotation.starts_with("sparse/path/")).count(), 354);
+        assert_eq!(r1cs.constraints.iter().filter(|c| c.annotation.starts_with("inclusion/")).count(), 1 + 254 * 243 + 1);
+
+        // The key is present, so it can't be excluded
+        let mut cs = RecordingSystem::<Bn256>::new_with_witness();
+        let (path, root) = alloc_sparse(cs.namespace(|| "sparse"), &tree, &key);
+        assert_exclusion(cs.namespace(|| "exclusion"), tree.hasher(), &path, &root).unwrap();
+        assert!(cs.into_r1cs().check_satisfied().is_err());
+
+        // A key that was never inserted can
+        let mut cs = RecordingSystem::<Bn256>::new_with_witness();
+        let (path, root) = alloc_sparse(cs.namespace(|| "sparse"), &tree, &rng.gen());
+        assert_exclusion(cs.namespace(|| "exclusion"), tree.hasher(), &path, &root).unwrap();
+        cs.into_r1cs().check_satisfied().unwrap();
+    }
+
+    #[test]
+    fn test_sparse_insertion() {
+        let rng = &mut thread_rng();
+        let mut tree = sparse_tree();
+        let (key, value): (Fr, Fr) = (rng.gen(), rng.gen());
+
+        let mut cs = RecordingSystem::<Bn256>::new_with_witness();
+        let (path, old_root) = alloc_sparse(cs.namespace(|| "sparse"), &tree, &key);
+        let value_var = AllocatedNum::alloc(cs.namespace(|| "value"), || Ok(value)).unwrap();
+        let new_root = insert(cs.namespace(|| "insert"), tree.hasher(), &value_var, &path, &old_root).unwrap();
+
+        tree.insert(&key, value);
+        assert_eq!(new_root.get_value(), Some(tree.root()));
+        cs.into_r1cs().check_satisfied().unwrap();
+
+        // Inserting the key again fails, as it is present now
+        let mut cs = RecordingSystem::<Bn256>::new_with_witness();
+        let (path, old_root) = alloc_sparse(cs.namespace(|| "sparse"), &tree, &key);
+        let value_var = AllocatedNum::alloc(cs.namespace(|| "value"), || Ok(value)).unwrap();
+        insert(cs.namespace(|| "insert"), tree.hasher(), &value_var, &path, &old_root).unwrap();
+        assert!(cs.into_r1cs().check_satisfied().is_err());
+
+        // And so does inserting zero
+        let mut cs = RecordingSystem::<Bn256>::new_with_witness();
+        let (path, old_root) = alloc_sparse(cs.namespace(|| "sparse"), &tree, &rng.gen());
+        let zero = AllocatedNum::alloc(cs.namespace(|| "value"), || Ok(Fr::zero())).unwrap();
+        assert!(insert(cs.namespace(|| "insert"), tree.hasher(), &zero, &path, &old_root).is_err());
+    }
+}
@@ -0,0 +1,219 @@
+//! Binary Merkle trees over field elements.
+//!
+//! A tree is generic over the two-to-one compression of its nodes, which
+//! `MerkleHasher` provides both natively and in a constraint system. It is
+//! implemented by the parameters of Poseidon and MiMC, and by `Sha256Hasher`.
+//!
+//! Besides trees of a fixed small depth, indexed by position, there are
+//! sparse trees that map field elements to values, with one level for every
+//! bit of the key. They are only feasible because nearly all of their
+//! subtrees are empty, so that their roots are known in advance.
+//!
+//! `native` builds trees and their authentication paths, and `circuit`
+//! recomputes roots from paths to prove membership or an update.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::pairing::ff::{
+    BitIterator,
+    Field,
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use crate::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::boolean::Boolean;
+use super::expr::Expr;
+#[cfg(feature = "mimc")]
+use super::mimc::MiMCParams;
+#[cfg(feature = "mimc")]
+use super::mimc;
+use super::num::{
+    AllocatedNum,
+    Num
+};
+use super::poseidon::PoseidonParams;
+use super::poseidon;
+use super::sha256::{
+    native_sha256,
+    sha256
+};
+
+pub mod circuit;
+pub mod native;
+
+/// The hash of two children into their parent. Both implementations must
+/// agree on every input.
+pub trait MerkleHasher<E: Engine> {
+    fn hash(&self, left: &E::Fr, right: &E::Fr) -> E::Fr;
+
+    fn hash_circuit<CS>(
+        &self,
+        cs: CS,
+        left: &AllocatedNum<E>,
+        right: &AllocatedNum<E>
+    ) -> Result<AllocatedNum<E>, SynthesisError>
+        where CS: ConstraintSystem<E>;
+}
+
+/// Poseidon of width 3, which takes the children as its rate. This is the
+/// hash of circomlib for two inputs.
+impl<E: Engine> MerkleHasher<E> for PoseidonParams<E> {
+    fn hash(&self, left: &E::Fr, right: &E::Fr) -> E::Fr {
+        assert_eq!(self.width(), 3, "the rate must be two elements");
+
+        poseidon::native::hash(self, &[*left, *right])
+    }
+
+    fn hash_circuit<CS>(
+        &self,
+        mut cs: CS,
+        left: &AllocatedNum<E>,
+        right: &AllocatedNum<E>
+    ) -> Result<AllocatedNum<E>, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        assert_eq!(self.width(), 3, "the rate must be two elements");
+
+        let input = [Expr::from(left.clone()), Expr::from(right.clone())];
+        let hash = poseidon::circuit::hash(cs.namespace(|| "poseidon"), self, &input)?;
+
+        AllocatedNum::from_expr(cs.namespace(|| "hash"), &hash)
+    }
+}
+
+/// The MiMC sponge with the zero key and one output, as in the trees of
+/// Tornado Cash.
+#[cfg(feature = "mimc")]
+impl<E: Engine> MerkleHasher<E> for MiMCParams<E> {
+    fn hash(&self, left: &E::Fr, right: &E::Fr) -> E::Fr {
+        mimc::native::sponge(self, &[*left, *right], &E::Fr::zero(), 1)[0]
+    }
+
+    fn hash_circuit<CS>(
+        &self,
+        mut cs: CS,
+        left: &AllocatedNum<E>,
+        right: &AllocatedNum<E>
+    ) -> Result<AllocatedNum<E>, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let input = [Expr::from(left.clone()), Expr::from(right.clone())];
+        let hash = mimc::circuit::sponge(cs.namespace(|| "mimc"), self, &input, &Expr::zero(), 1)?;
+
+        AllocatedNum::from_expr(cs.namespace(|| "hash"), &hash[0])
+    }
+}
+
+/// SHA-256 of the 32-byte big-endian encodings of both children, with the
+/// digest read as a big-endian integer and truncated to `CAPACITY` bits.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha256Hasher;
+
+impl<E: Engine> MerkleHasher<E> for Sha256Hasher {
+    fn hash(&self, left: &E::Fr, right: &E::Fr) -> E::Fr {
+        let input: Vec<bool> = [left, right].iter()
+            .flat_map(|x| BitIterator::new(x.into_repr()).collect::<Vec<_>>())
+            .collect();
+
+        let digest = native_sha256(&input);
+
+        let mut repr = <E::Fr as PrimeField>::Repr::default();
+        for (i, &bit) in digest.iter().rev().take(E::Fr::CAPACITY as usize).enumerate() {
+            if bit {
+                repr.as_mut()[i / 64] |= 1 << (i % 64);
+            }
+        }
+
+        E::Fr::from_repr(repr).expect("truncated below the modulus")
+    }
+
+    fn hash_circuit<CS>(
+        &self,
+        mut cs: CS,
+        left: &AllocatedNum<E>,
+        right: &AllocatedNum<E>
+    ) -> Result<AllocatedNum<E>, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let mut input = vec![];
+        for (name, x) in [("left", left), ("right", right)].iter() {
+            let bits = x.to_bits_le_strict(cs.namespace(|| format!("{} bits", name)))?;
+
+            input.extend((bits.len()..256).map(|_| Boolean::constant(false)));
+            input.extend(bits.into_iter().rev());
+        }
+
+        let digest = sha256(cs.namespace(|| "sha256"), &input)?;
+
+        let mut packed = Num::zero();
+        let mut coeff = E::Fr::one();
+        for bit in digest.iter().rev().take(E::Fr::CAPACITY as usize) {
+            packed = packed.add_bool_with_coeff(CS::one(), bit, coeff);
+            coeff.double();
+        }
+
+        let hash = AllocatedNum::alloc(cs.namespace(|| "hash"), || {
+            packed.get_value().ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        cs.enforce(
+            || "packing constraint",
+            |lc| lc + &packed.lc(E::Fr::one()),
+            |lc| lc + CS::one(),
+            |lc| lc + hash.get_variable()
+        );
+
+        Ok(hash)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bn256::{Bn256, Fr};
+    use crate::r1cs::RecordingSystem;
+
+    use rand::{Rng, thread_rng};
+
+    fn check_hasher<H: MerkleHasher<Bn256>>(hasher: &H, constraints: usize) {
+        let rng = &mut thread_rng();
+        let (left, right): (Fr, Fr) = (rng.gen(), rng.gen());
+
+        let mut cs = RecordingSystem::<Bn256>::new_with_witness();
+        let left_var = AllocatedNum::alloc(cs.namespace(|| "left"), || Ok(left)).unwrap();
+        let right_var = AllocatedNum::alloc(cs.namespace(|| "right"), || Ok(right)).unwrap();
+        let hash = hasher.hash_circuit(cs.namespace(|| "hash"), &left_var, &right_var).unwrap();
+        assert_eq!(hash.get_value(), Some(hasher.hash(&left, &right)));
+
+        let r1cs = cs.into_r1cs();
+        r1cs.check_satisfied().unwrap();
+        assert_eq!(r1cs.constraints.iter().filter(|c| c.annotation.starts_with("hash/")).count(), constraints);
+    }
+
+    #[test]
+    fn test_hashers_agree_with_native() {
+        check_hasher(&PoseidonParams::<Bn256>::new(3, 128, 5).unwrap(), 241);
+        #[cfg(feature = "mimc")]
+        check_hasher(&MiMCParams::<Bn256>::circomlib(), 1321);
+        // Two decompositions into bits, two blocks of which the second is
+        // constant padding, and the packing of the digest
+        check_hasher(&Sha256Hasher, 2 * 354 + 25990 + 19148 + 1);
+    }
+
+    #[test]
+    fn test_sha256_hasher_encoding() {
+        // SHA-256 of 64 zero bytes is f5a5fd42...2759fb4b, of which the
+        // low 253 bits remain
+        let hash = MerkleHasher::<Bn256>::hash(&Sha256Hasher, &Fr::zero(), &Fr::zero());
+        let expected = Fr::from_str("9791847529173172168588187966728841921337020455261439670493918120297306389323").unwrap();
+        assert_eq!(hash, expected);
+    }
+}
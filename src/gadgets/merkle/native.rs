@@ -0,0 +1,404 @@
+//! Merkle trees over field elements, outside of circuits.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::pairing::ff::{
+    BitIterator,
+    Field,
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use std::collections::{BTreeMap, HashMap};
+
+use super::MerkleHasher;
+
+/// The siblings on the way from a leaf to the root.
+#[derive(Clone)]
+pub struct MerklePath<E: Engine> {
+    /// The position of the leaf, whose bits tell from the bottom up
+    /// whether the node on the path is a right child
+    pub index: u64,
+    /// The siblings from the bottom up
+    pub siblings: Vec<E::Fr>
+}
+
+impl<E: Engine> MerklePath<E> {
+    pub fn depth(&self) -> usize {
+        self.siblings.len()
+    }
+
+    /// Whether the node on the path is a right child, from the bottom up.
+    pub fn bits(&self) -> Vec<bool> {
+        (0..self.depth()).map(|i| (self.index >> i) & 1 == 1).collect()
+    }
+
+    /// Computes the root of a tree that holds the leaf on this path.
+    pub fn root<H: MerkleHasher<E>>(&self, hasher: &H, leaf: &E::Fr) -> E::Fr {
+        self.siblings.iter().zip(self.bits()).fold(*leaf, |node, (sibling, is_right)| {
+            if is_right {
+                hasher.hash(sibling, &node)
+            } else {
+                hasher.hash(&node, sibling)
+            }
+        })
+    }
+}
+
+/// A tree of a fixed depth, in which every leaf starts out empty. Only the
+/// nodes above leaves that were set are stored, so deep trees are cheap as
+/// long as they hold few leaves.
+pub struct MerkleTree<E: Engine, H: MerkleHasher<E>> {
+    hasher: H,
+    // The roots of empty subtrees, by height
+    empty: Vec<E::Fr>,
+    // Nodes of non-empty subtrees, by height and index
+    nodes: HashMap<(usize, u64), E::Fr>
+}
+
+impl<E: Engine, H: MerkleHasher<E>> MerkleTree<E, H> {
+    pub fn new(
+        hasher: H,
+        depth: usize,
+        empty_leaf: E::Fr
+    ) -> Self
+    {
+        assert!(depth < 64, "indices must fit into 64 bits");
+
+        let mut empty = vec![empty_leaf];
+        for _ in 0..depth {
+            let node = {
+                let below = empty.last().unwrap();
+
+                hasher.hash(below, below)
+            };
+            empty.push(node);
+        }
+
+        MerkleTree {
+            hasher: hasher,
+            empty: empty,
+            nodes: HashMap::new()
+        }
+    }
+
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    pub fn depth(&self) -> usize {
+        self.empty.len() - 1
+    }
+
+    /// The root of an empty subtree of the given height.
+    pub fn empty_root(&self, height: usize) -> E::Fr {
+        self.empty[height]
+    }
+
+    pub fn root(&self) -> E::Fr {
+        self.node(self.depth(), 0)
+    }
+
+    pub fn leaf(&self, index: u64) -> E::Fr {
+        self.node(0, index)
+    }
+
+    fn node(&self, height: usize, index: u64) -> E::Fr {
+        self.nodes.get(&(height, index)).cloned().unwrap_or(self.empty[height])
+    }
+
+    /// Sets the leaf and updates the nodes above it.
+    pub fn set(&mut self, index: u64, leaf: E::Fr) {
+        assert!(index >> self.depth() == 0, "the index is out of the tree");
+
+        self.nodes.insert((0, index), leaf);
+
+        let mut node = leaf;
+        for height in 0..self.depth() {
+            let index = index >> height;
+            let sibling = self.node(height, index ^ 1);
+
+            node = if index & 1 == 1 {
+                self.hasher.hash(&sibling, &node)
+            } else {
+                self.hasher.hash(&node, &sibling)
+            };
+            self.nodes.insert((height + 1, index >> 1), node);
+        }
+    }
+
+    /// The authentication path of the leaf.
+    pub fn path(&self, index: u64) -> MerklePath<E> {
+        assert!(index >> self.depth() == 0, "the index is out of the tree");
+
+        MerklePath {
+            index: index,
+            siblings: (0..self.depth()).map(|height| self.node(height, (index >> height) ^ 1)).collect()
+        }
+    }
+}
+
+/// A proof that a key holds a value in a sparse Merkle tree. It proves
+/// inclusion if the value is not zero, and exclusion otherwise.
+#[derive(Clone)]
+pub struct SparseMerkleProof<E: Engine> {
+    pub key: E::Fr,
+    pub value: E::Fr,
+    /// The siblings from the bottom up
+    pub siblings: Vec<E::Fr>
+}
+
+impl<E: Engine> SparseMerkleProof<E> {
+    pub fn is_inclusion(&self) -> bool {
+        !self.value.is_zero()
+    }
+
+    /// Computes the root of a tree in which the key holds the value.
+    pub fn root<H: MerkleHasher<E>>(&self, hasher: &H) -> E::Fr {
+        self.siblings.iter().zip(key_bits(&self.key)).fold(self.value, |node, (sibling, is_right)| {
+            if is_right {
+                hasher.hash(sibling, &node)
+            } else {
+                hasher.hash(&node, sibling)
+            }
+        })
+    }
+
+    pub fn verify<H: MerkleHasher<E>>(&self, hasher: &H, root: &E::Fr) -> bool {
+        self.root(hasher) == *root
+    }
+}
+
+/// The bits of the key from the bottom of a sparse tree up, that is
+/// `NUM_BITS` bits in little-endian order.
+pub fn key_bits<F: PrimeField>(key: &F) -> Vec<bool> {
+    let mut bits: Vec<bool> = BitIterator::new(key.into_repr()).collect();
+    bits.reverse();
+    bits.truncate(F::NUM_BITS as usize);
+
+    bits
+}
+
+/// A key-value store as a Merkle tree of depth `NUM_BITS`, in which the
+/// value of a key is the leaf at the position given by the bits of the
+/// key. Absent keys hold zero. Nodes that are the root of an empty subtree
+/// are not stored, so every key takes at most one node per level.
+pub struct SparseMerkleTree<E: Engine, H: MerkleHasher<E>> {
+    hasher: H,
+    // The roots of empty subtrees, by height
+    empty: Vec<E::Fr>,
+    // Nodes of non-empty subtrees, by height and position
+    nodes: BTreeMap<(usize, <E::Fr as PrimeField>::Repr), E::Fr>
+}
+
+impl<E: Engine, H: MerkleHasher<E>> SparseMerkleTree<E, H> {
+    pub fn new(hasher: H) -> Self {
+        let mut empty = vec![E::Fr::zero()];
+        for _ in 0..E::Fr::NUM_BITS {
+            let node = {
+                let below = empty.last().unwrap();
+
+                hasher.hash(below, below)
+            };
+            empty.push(node);
+        }
+
+        SparseMerkleTree {
+            hasher: hasher,
+            empty: empty,
+            nodes: BTreeMap::new()
+        }
+    }
+
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    pub fn depth(&self) -> usize {
+        E::Fr::NUM_BITS as usize
+    }
+
+    pub fn root(&self) -> E::Fr {
+        self.node(self.depth(), <E::Fr as PrimeField>::Repr::default())
+    }
+
+    pub fn get(&self, key: &E::Fr) -> E::Fr {
+        self.node(0, key.into_repr())
+    }
+
+    fn node(&self, height: usize, position: <E::Fr as PrimeField>::Repr) -> E::Fr {
+        self.nodes.get(&(height, position)).cloned().unwrap_or(self.empty[height])
+    }
+
+    fn set_node(&mut self, height: usize, position: <E::Fr as PrimeField>::Repr, node: E::Fr) {
+        if node == self.empty[height] {
+            self.nodes.remove(&(height, position));
+        } else {
+            self.nodes.insert((height, position), node);
+        }
+    }
+
+    /// Sets the value of the key and returns the previous one. Setting
+    /// the value to zero removes the key.
+    pub fn insert(&mut self, key: &E::Fr, value: E::Fr) -> E::Fr {
+        let old = self.get(key);
+
+        let mut position = key.into_repr();
+        let mut node = value;
+        for height in 0..self.depth() {
+            self.set_node(height, position, node);
+
+            let mut sibling_position = position;
+            sibling_position.as_mut()[0] ^= 1;
+            let sibling = self.node(height, sibling_position);
+
+            node = if position.is_odd() {
+                self.hasher.hash(&sibling, &node)
+            } else {
+                self.hasher.hash(&node, &sibling)
+            };
+            position.shr(1);
+        }
+        self.set_node(self.depth(), position, node);
+
+        old
+    }
+
+    pub fn remove(&mut self, key: &E::Fr) -> E::Fr {
+        self.insert(key, E::Fr::zero())
+    }
+
+    /// Proves the inclusion of the key, or its exclusion if it is absent.
+    pub fn prove(&self, key: &E::Fr) -> SparseMerkleProof<E> {
+        let mut position = key.into_repr();
+        let mut siblings = Vec::with_capacity(self.depth());
+        for height in 0..self.depth() {
+            let mut sibling_position = position;
+            sibling_position.as_mut()[0] ^= 1;
+            siblings.push(self.node(height, sibling_position));
+
+            position.shr(1);
+        }
+
+        SparseMerkleProof {
+            key: *key,
+            value: self.get(key),
+            siblings: siblings
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bn256::{Bn256, Fr};
+    use crate::pairing::ff::{Field, PrimeField};
+    #[cfg(feature = "mimc")]
+    use crate::gadgets::mimc::MiMCParams;
+    use crate::gadgets::poseidon::PoseidonParams;
+
+    use rand::{Rng, thread_rng};
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_tree_paths() {
+        let rng = &mut thread_rng();
+        let params = PoseidonParams::<Bn256>::new(3, 128, 5).unwrap();
+        let mut tree = MerkleTree::new(params.clone(), 20, Fr::zero());
+        assert_eq!(tree.root(), tree.empty_root(20));
+
+        let mut leaves = vec![];
+        for _ in 0..10 {
+            let (index, leaf): (u64, Fr) = (rng.gen_range(0, 1 << 20), rng.gen());
+            tree.set(index, leaf);
+            leaves.retain(|&(i, _)| i != index);
+            leaves.push((index, leaf));
+        }
+
+        for &(index, leaf) in leaves.iter() {
+            assert_eq!(tree.leaf(index), leaf);
+
+            let path = tree.path(index);
+            assert_eq!(path.depth(), 20);
+            assert_eq!(path.root(&params, &leaf), tree.root());
+            assert!(path.root(&params, &Fr::one()) != tree.root());
+        }
+
+        // Neighbours share all siblings but the first one
+        tree.set(6, Fr::one());
+        tree.set(7, Fr::zero());
+        let (left, right) = (tree.path(6), tree.path(7));
+        assert_eq!(left.siblings[0], Fr::zero());
+        assert_eq!(right.siblings[0], Fr::one());
+        assert_eq!(left.siblings[1..], right.siblings[1..]);
+        assert_eq!(left.bits()[..3], [false, true, true]);
+    }
+
+    #[test]
+    #[cfg(feature = "mimc")]
+    fn test_tornado_zeros() {
+        // The empty leaf of Tornado Cash is keccak256("tornado") reduced into the field
+        let empty_leaf = Fr::from_str("21663839004416932945382355908790599225266501822907911457504978515578255421292").unwrap();
+        let tree = MerkleTree::new(MiMCParams::<Bn256>::circomlib(), 1, empty_leaf);
+
+        assert_eq!(tree.root(), Fr::from_str("16923532097304556005972200564242292693309333953544141029519619077135960040221").unwrap());
+    }
+
+    #[test]
+    fn test_sparse_tree() {
+        let rng = &mut thread_rng();
+        let params = PoseidonParams::<Bn256>::new(3, 128, 5).unwrap();
+        let mut tree = SparseMerkleTree::new(params.clone());
+        assert_eq!(tree.depth(), 254);
+        let empty_root = tree.root();
+
+        let entries: Vec<(Fr, Fr)> = (0..5).map(|_| (rng.gen(), rng.gen())).collect();
+        for &(key, value) in entries.iter() {
+            assert_eq!(tree.insert(&key, value), Fr::zero());
+        }
+
+        // Keys share the nodes above their common prefix
+        let positions: BTreeSet<_> = entries.iter().flat_map(|&(key, _)| {
+            (0..255).map(move |height| {
+                let mut position = key.into_repr();
+                position.shr(height);
+
+                (height, position)
+            })
+        }).collect();
+        assert_eq!(tree.nodes.len(), positions.len());
+
+        for &(key, value) in entries.iter() {
+            assert_eq!(tree.get(&key), value);
+
+            let proof = tree.prove(&key);
+            assert!(proof.is_inclusion());
+            assert!(proof.verify(&params, &tree.root()));
+            assert!(!proof.verify(&params, &empty_root));
+        }
+
+        // An absent key, including one next to a present key
+        let mut neighbour = entries[0].0.into_repr();
+        neighbour.as_mut()[0] ^= 1;
+        for key in [rng.gen(), Fr::from_repr(neighbour).unwrap()].iter() {
+            let proof = tree.prove(key);
+            assert!(!proof.is_inclusion());
+            assert!(proof.verify(&params, &tree.root()));
+
+            // Claiming any value for it fails
+            let mut forged = proof.clone();
+            forged.value = rng.gen();
+            assert!(!forged.verify(&params, &tree.root()));
+        }
+
+        // Removing all keys leaves nothing behind
+        for &(key, value) in entries.iter() {
+            assert_eq!(tree.remove(&key), value);
+        }
+        assert_eq!(tree.root(), empty_root);
+        assert!(tree.nodes.is_empty());
+    }
+}
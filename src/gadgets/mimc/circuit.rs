@@ -0,0 +1,149 @@
+//! MiMC in a constraint system. Adding the key and the round constants
+//! is free, so a round only costs raising to the exponent, which is three
+//! constraints for `x^5` and 660 for the 220 rounds of circomlib. Rounds
+//! in which the left half is a constant are folded.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use crate::gadgets::expr::Expr;
+
+use super::MiMCParams;
+
+/// Encrypts the pair `(xl, xr)` under the key `k` in the same way as
+/// `native::feistel`.
+pub fn feistel<E, CS>(
+    mut cs: CS,
+    params: &MiMCParams<E>,
+    xl: &Expr<E>,
+    xr: &Expr<E>,
+    k: &Expr<E>
+) -> Result<(Expr<E>, Expr<E>), SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    let last = params.rounds() - 1;
+
+    let mut xl = xl.clone();
+    let mut xr = xr.clone();
+    for (i, &c) in params.round_constants().iter().enumerate() {
+        let t = xl.clone() + k + Expr::constant(c);
+        let tmp = t.pow(cs.namespace(|| format!("round {}", i)), params.exponent())? + &xr;
+
+        if i < last {
+            xr = xl;
+            xl = tmp;
+        } else {
+            xr = tmp;
+        }
+    }
+
+    Ok((xl, xr))
+}
+
+/// The sponge of `native::sponge`.
+pub fn sponge<E, CS>(
+    mut cs: CS,
+    params: &MiMCParams<E>,
+    input: &[Expr<E>],
+    k: &Expr<E>,
+    num_outputs: usize
+) -> Result<Vec<Expr<E>>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    let mut r = Expr::zero();
+    let mut c = Expr::zero();
+
+    for (i, x) in input.iter().enumerate() {
+        let (new_r, new_c) = feistel(cs.namespace(|| format!("absorb {}", i)), params, &(r + x), &c, k)?;
+        r = new_r;
+        c = new_c;
+    }
+
+    let mut outputs = Vec::with_capacity(num_outputs);
+    for i in 0..num_outputs {
+        if i > 0 {
+            let (new_r, new_c) = feistel(cs.namespace(|| format!("squeeze {}", i)), params, &r, &c, k)?;
+            r = new_r;
+            c = new_c;
+        }
+
+        outputs.push(r.clone());
+    }
+
+    Ok(outputs)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use super::super::native;
+
+    use crate::pairing::bn256::{Bn256, Fr};
+    use crate::pairing::bls12_381::{Bls12, Fr as Bls12Fr};
+    use crate::pairing::ff::Field;
+    use crate::r1cs::RecordingSystem;
+
+    use rand::{Rng, thread_rng};
+
+    #[test]
+    fn test_feistel_agrees_with_native() {
+        let rng = &mut thread_rng();
+        let params = MiMCParams::<Bn256>::circomlib();
+        let (xl, xr, k): (Fr, Fr, Fr) = (rng.gen(), rng.gen(), rng.gen());
+
+        let mut cs = RecordingSystem::<Bn256>::new_with_witness();
+        let xl_var = Expr::alloc(cs.namespace(|| "xl"), || Ok(xl)).unwrap();
+        let xr_var = Expr::alloc(cs.namespace(|| "xr"), || Ok(xr)).unwrap();
+        let k_var = Expr::alloc(cs.namespace(|| "k"), || Ok(k)).unwrap();
+        let (out_l, out_r) = feistel(cs.namespace(|| "mimc"), &params, &xl_var, &xr_var, &k_var).unwrap();
+
+        let (expected_l, expected_r) = native::feistel(&params, xl, xr, &k);
+        assert_eq!(out_l.get_value(), Some(expected_l));
+        assert_eq!(out_r.get_value(), Some(expected_r));
+
+        let image = Expr::alloc_input(cs.namespace(|| "image"), || Ok(expected_r)).unwrap();
+        out_r.assert_eq(cs.namespace(|| "output"), &image).unwrap();
+
+        let mut r1cs = cs.into_r1cs();
+        r1cs.check_satisfied().unwrap();
+        assert_eq!(r1cs.constraints.iter().filter(|c| c.annotation.starts_with("mimc/")).count(), 660);
+
+        r1cs.input_assignment.as_mut().unwrap()[1].add_assign(&Fr::one());
+        assert!(r1cs.check_satisfied().is_err());
+    }
+
+    #[test]
+    fn test_sponge_agrees_with_native() {
+        let rng = &mut thread_rng();
+        let params = MiMCParams::<Bls12>::new(b"seed", 110, 5);
+        let input: Vec<Bls12Fr> = (0..3).map(|_| rng.gen()).collect();
+        let k = Bls12Fr::zero();
+
+        let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+        let input_vars: Vec<_> = input.iter().enumerate().map(|(i, &x)| {
+            Expr::alloc(cs.namespace(|| format!("input {}", i)), || Ok(x)).unwrap()
+        }).collect();
+        let outputs = sponge(cs.namespace(|| "sponge"), &params, &input_vars, &Expr::constant(k), 2).unwrap();
+
+        let expected = native::sponge(&params, &input, &k, 2);
+        let values: Vec<_> = outputs.iter().map(|o| o.get_value().unwrap()).collect();
+        assert_eq!(values, expected);
+
+        cs.into_r1cs().check_satisfied().unwrap();
+
+        // Constants are folded completely
+        let mut cs = RecordingSystem::<Bls12>::new();
+        let constants: Vec<_> = input.iter().map(|&x| Expr::constant(x)).collect();
+        let outputs = sponge(&mut cs, &params, &constants, &Expr::constant(k), 2).unwrap();
+        assert!(outputs.iter().all(|o| o.is_constant()));
+        assert_eq!(outputs.iter().map(|o| o.get_value().unwrap()).collect::<Vec<_>>(), expected);
+        assert_eq!(cs.into_r1cs().num_constraints(), 0);
+    }
+}
@@ -0,0 +1,132 @@
+//! The MiMC block cipher, from "MiMC: Efficient Encryption and
+//! Cryptographic Hashing with Minimal Multiplicative Complexity" by
+//! Albrecht, Grassi, Rechberger, Roy and Tiessen, in its Feistel mode and
+//! the sponge built on it.
+//!
+//! Every round of the Feistel network adds the key and a round constant to
+//! the left half, raises it to a small power and adds the result to the
+//! right half before swapping the halves. The last round does not swap.
+//! The round constants are derived from a seed with Keccak-256 in the same
+//! way as circomlib does, and `MiMCParams::circomlib` is the instance of
+//! its `MiMCSponge`, so hashes agree with circomlib over BN256.
+//!
+//! `native` computes the cipher outside of circuits, and `circuit`
+//! computes it in a constraint system.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::pairing::ff::{
+    Field,
+    PrimeField
+};
+
+use tiny_keccak::Keccak;
+
+pub mod circuit;
+pub mod native;
+
+/// The seed of the round constants of circomlib.
+pub const CIRCOMLIB_SEED: &[u8] = b"mimcsponge";
+
+/// The number of rounds of circomlib.
+pub const CIRCOMLIB_ROUNDS: usize = 220;
+
+/// An instance of the MiMC Feistel network over the scalar field of `E`.
+#[derive(Clone)]
+pub struct MiMCParams<E: Engine> {
+    exponent: u64,
+    round_constants: Vec<E::Fr>
+}
+
+impl<E: Engine> MiMCParams<E> {
+    /// Derives the round constants from the seed. The constants of the
+    /// first and the last round are zero, and the others are the Keccak-256
+    /// chain starting at the hash of the seed, reduced into the field.
+    ///
+    /// The Feistel network is a permutation for any exponent, but it
+    /// should be coprime to `p - 1` for MiMC to be secure.
+    pub fn new(
+        seed: &[u8],
+        rounds: usize,
+        exponent: u64
+    ) -> Self
+    {
+        assert!(rounds >= 2, "the first and the last round are fixed");
+
+        let mut digest = keccak256(seed);
+        let mut round_constants = vec![E::Fr::zero(); rounds];
+        for c in round_constants[1..rounds - 1].iter_mut() {
+            digest = keccak256(&digest);
+            *c = reduce(&digest);
+        }
+
+        MiMCParams {
+            exponent: exponent,
+            round_constants: round_constants
+        }
+    }
+
+    /// The parameters of `MiMCSponge` of circomlib, with 220 rounds and
+    /// the exponent 5.
+    pub fn circomlib() -> Self {
+        Self::new(CIRCOMLIB_SEED, CIRCOMLIB_ROUNDS, 5)
+    }
+
+    pub fn exponent(&self) -> u64 {
+        self.exponent
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.round_constants.len()
+    }
+
+    pub fn round_constants(&self) -> &[E::Fr] {
+        &self.round_constants
+    }
+}
+
+fn keccak256(input: &[u8]) -> [u8; 32] {
+    let mut h = Keccak::new_keccak256();
+    h.update(input);
+
+    let mut res = [0u8; 32];
+    h.finalize(&mut res);
+
+    res
+}
+
+// Interprets big-endian bytes as an integer modulo the characteristic
+fn reduce<F: PrimeField>(bytes: &[u8]) -> F {
+    let base = F::from_str("256").unwrap();
+
+    bytes.iter().fold(F::zero(), |mut acc, &byte| {
+        acc.mul_assign(&base);
+        acc.add_assign(&F::from_repr((byte as u64).into()).unwrap());
+
+        acc
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bn256::{Bn256, Fr};
+
+    #[test]
+    fn test_circomlib_constants() {
+        let params = MiMCParams::<Bn256>::circomlib();
+        assert_eq!(params.rounds(), 220);
+        assert_eq!(params.exponent(), 5);
+
+        // The first constants of mimcsponge.circom
+        let constants = params.round_constants();
+        assert_eq!(constants[0], Fr::zero());
+        assert_eq!(constants[1], Fr::from_str("7120861356467848435263064379192047478074060781135320967663101236819528304084").unwrap());
+        assert_eq!(constants[2], Fr::from_str("5024705281721889198577876690145313457398658950011302225525409148828000436681").unwrap());
+        assert_eq!(constants[218], Fr::from_str("2119542016932434047340813757208803962484943912710204325088879681995922344971").unwrap());
+        assert_eq!(constants[219], Fr::zero());
+    }
+}
@@ -0,0 +1,116 @@
+//! MiMC over field elements, outside of circuits.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::pairing::ff::{
+    Field
+};
+
+use super::MiMCParams;
+
+/// Encrypts the pair `(xl, xr)` under the key `k` with the Feistel
+/// network, and returns the new pair.
+pub fn feistel<E: Engine>(
+    params: &MiMCParams<E>,
+    mut xl: E::Fr,
+    mut xr: E::Fr,
+    k: &E::Fr
+) -> (E::Fr, E::Fr)
+{
+    let last = params.rounds() - 1;
+
+    for (i, c) in params.round_constants().iter().enumerate() {
+        // xL, xR := xR + (xL + k + c)^e, xL
+        let mut t = xl;
+        t.add_assign(k);
+        t.add_assign(c);
+
+        let mut tmp = t.pow([params.exponent()]);
+        tmp.add_assign(&xr);
+
+        if i < last {
+            xr = xl;
+            xl = tmp;
+        } else {
+            xr = tmp;
+        }
+    }
+
+    (xl, xr)
+}
+
+/// The sponge of circomlib with a rate and a capacity of one element.
+/// Every input is added to the rate before the state is encrypted under
+/// the key, and every output after the first one encrypts the state once
+/// more.
+pub fn sponge<E: Engine>(
+    params: &MiMCParams<E>,
+    input: &[E::Fr],
+    k: &E::Fr,
+    num_outputs: usize
+) -> Vec<E::Fr>
+{
+    let mut r = E::Fr::zero();
+    let mut c = E::Fr::zero();
+
+    for x in input {
+        r.add_assign(x);
+
+        let (new_r, new_c) = feistel(params, r, c, k);
+        r = new_r;
+        c = new_c;
+    }
+
+    let mut outputs = Vec::with_capacity(num_outputs);
+    for i in 0..num_outputs {
+        if i > 0 {
+            let (new_r, new_c) = feistel(params, r, c, k);
+            r = new_r;
+            c = new_c;
+        }
+
+        outputs.push(r);
+    }
+
+    outputs
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bn256::{Bn256, Fr};
+    use crate::pairing::ff::PrimeField;
+
+    fn elements(values: &[&str]) -> Vec<Fr> {
+        values.iter().map(|v| Fr::from_str(v).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_circomlib_vectors() {
+        let params = MiMCParams::<Bn256>::circomlib();
+
+        let (xl, xr) = feistel(&params, Fr::from_str("1").unwrap(), Fr::from_str("2").unwrap(), &Fr::from_str("3").unwrap());
+        assert_eq!(xl, Fr::from_str("18444058245820418255538785847032978363886102372504864086197416499869253008979").unwrap());
+        assert_eq!(xr, Fr::from_str("2646733164649743153031645792459389637917704265581895142760676293265176296759").unwrap());
+
+        // multiHash([1, 2]) of circomlib
+        let outputs = sponge(&params, &elements(&["1", "2"]), &Fr::zero(), 1);
+        assert_eq!(outputs, elements(&["19814528709687996974327303300007262407299502847885145507292406548098437687919"]));
+
+        // multiHash([1, 2, 3], 0, 3) of circomlib
+        let outputs = sponge(&params, &elements(&["1", "2", "3"]), &Fr::zero(), 3);
+        assert_eq!(outputs, elements(&[
+            "13347232259103605288126215296295968657023270572136673486116911774162409637522",
+            "21631365138607353745907388069625267508930592880820057533356376809857973361392",
+            "20873567787080299535990585760555761221525906582034981122227302874458019883150"
+        ]));
+
+        // The first level of the empty tree of Tornado Cash
+        let zero = elements(&["21663839004416932945382355908790599225266501822907911457504978515578255421292"])[0];
+        let outputs = sponge(&params, &[zero, zero], &Fr::zero(), 1);
+        assert_eq!(outputs, elements(&["16923532097304556005972200564242292693309333953544141029519619077135960040221"]));
+    }
+}
@@ -0,0 +1,21 @@
+//! Reusable building blocks for writing circuits on top of the
+//! `ConstraintSystem` trait. Gadgets follow the convention of taking the
+//! constraint system by value, so that callers pass in a namespace for
+//! every gadget they use.
+
+pub mod blake2s;
+pub mod boolean;
+pub mod expr;
+pub mod keccak;
+pub mod lookup;
+pub mod merkle;
+#[cfg(feature = "mimc")]
+pub mod mimc;
+pub mod num;
+pub mod poseidon;
+pub mod range;
+pub mod sha256;
+pub mod uint;
+
+mod gadget;
+pub use self::gadget::*;
@@ -0,0 +1,804 @@
+use crate::pairing::{
+    Engine
+};
+
+use crate::pairing::ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr,
+    BitIterator
+};
+
+use crate::{
+    SynthesisError,
+    ConstraintSystem,
+    LinearCombination,
+    Variable
+};
+
+use super::Allocate;
+use super::boolean::{
+    self,
+    AllocatedBit,
+    Boolean
+};
+use super::expr::Expr;
+
+pub struct AllocatedNum<E: Engine> {
+    value: Option<E::Fr>,
+    variable: Variable
+}
+
+impl<E: Engine> Clone for AllocatedNum<E> {
+    fn clone(&self) -> Self {
+        AllocatedNum {
+            value: self.value,
+            variable: self.variable
+        }
+    }
+}
+
+impl<E: Engine> AllocatedNum<E> {
+    pub fn alloc<CS, F>(
+        mut cs: CS,
+        value: F
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>, F: FnOnce() -> Result<E::Fr, SynthesisError>
+    {
+        let mut new_value = None;
+        let var = cs.alloc(|| "num", || {
+            let tmp = value()?;
+
+            new_value = Some(tmp);
+
+            Ok(tmp)
+        })?;
+
+        Ok(AllocatedNum {
+            value: new_value,
+            variable: var
+        })
+    }
+
+    pub fn inputize<CS>(
+        &self,
+        mut cs: CS
+    ) -> Result<(), SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let input = cs.alloc_input(
+            || "input variable",
+            || self.value.ok_or(SynthesisError::AssignmentMissing)
+        )?;
+
+        cs.enforce(
+            || "enforce input is correct",
+            |lc| lc + input,
+            |lc| lc + CS::one(),
+            |lc| lc + self.variable
+        );
+
+        Ok(())
+    }
+
+    /// Deconstructs this allocated number into its
+    /// boolean representation in little-endian bit
+    /// order, requiring that the representation
+    /// strictly exists "in the field" (i.e., a
+    /// congruency is not allowed.)
+    pub fn to_bits_le_strict<CS>(
+        &self,
+        mut cs: CS
+    ) -> Result<Vec<Boolean>, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        pub fn kary_and<E, CS>(
+            mut cs: CS,
+            v: &[AllocatedBit]
+        ) -> Result<AllocatedBit, SynthesisError>
+            where E: Engine, CS: ConstraintSystem<E>
+        {
+            assert!(!v.is_empty());
+
+            // Let's keep this simple for now and just AND them all
+            // manually
+            let mut cur = None;
+
+            for (i, v) in v.iter().enumerate() {
+                if cur.is_none() {
+                    cur = Some(v.clone());
+                } else {
+                    cur = Some(AllocatedBit::and(
+                        cs.namespace(|| format!("and {}", i)),
+                        cur.as_ref().unwrap(),
+                        v
+                    )?);
+                }
+            }
+
+            Ok(cur.expect("v.len() > 0"))
+        }
+
+        // We want to ensure that the bit representation of a is
+        // less than or equal to r - 1.
+        let mut a = self.value.map(|e| BitIterator::new(e.into_repr()));
+        let mut b = E::Fr::char();
+        b.sub_noborrow(&1.into());
+
+        let mut result = vec![];
+
+        // Runs of ones in r
+        let mut last_run = None;
+        let mut current_run = vec![];
+
+        let mut found_one = false;
+        let mut i = 0;
+        for b in BitIterator::new(b) {
+            let a_bit = a.as_mut().map(|e| e.next().unwrap());
+
+            // Skip over unset bits at the beginning
+            found_one |= b;
+            if !found_one {
+                // a_bit should also be false
+                if let Some(e) = a_bit {
+                    assert!(!e);
+                }
+                continue;
+            }
+
+            if b {
+                // This is part of a run of ones. Let's just
+                // allocate the boolean with the expected value.
+                let a_bit = AllocatedBit::alloc(
+                    cs.namespace(|| format!("bit {}", i)),
+                    a_bit
+                )?;
+                // ... and add it to the current run of ones.
+                current_run.push(a_bit.clone());
+                result.push(a_bit);
+            } else {
+                if !current_run.is_empty() {
+                    // This is the start of a run of zeros, but we need
+                    // to k-ary AND against `last_run` first.
+
+                    if last_run.is_some() {
+                        current_run.push(last_run.clone().unwrap());
+                    }
+                    last_run = Some(kary_and(
+                        cs.namespace(|| format!("run ending at {}", i)),
+                        &current_run
+                    )?);
+                    current_run.truncate(0);
+                }
+
+                // If `last_run` is true, `a` must be false, or it would
+                // not be in the field.
+                //
+                // If `last_run` is false, `a` can be true or false.
+
+                let a_bit = AllocatedBit::alloc_conditionally(
+                    cs.namespace(|| format!("bit {}", i)),
+                    a_bit,
+                    last_run.as_ref().expect("char always starts with a one")
+                )?;
+                result.push(a_bit);
+            }
+
+            i += 1;
+        }
+
+        // char is prime, so we'll always end on
+        // a run of zeros.
+        assert_eq!(current_run.len(), 0);
+
+        // Now, we have `result` in big-endian order.
+        // However, now we have to unpack self!
+
+        let mut lc = LinearCombination::zero();
+        let mut coeff = E::Fr::one();
+
+        for bit in result.iter().rev() {
+            lc = lc + (coeff, bit.get_variable());
+
+            coeff.double();
+        }
+
+        cs.enforce(
+            || "unpacking constraint",
+            |lc| lc + self.variable,
+            |lc| lc + CS::one(),
+            |_| lc
+        );
+
+        // Convert into booleans, and reverse for little-endian bit order
+        Ok(result.into_iter().map(Boolean::from).rev().collect())
+    }
+
+    /// Convert the allocated number into its little-endian representation.
+    /// Note that this does not strongly enforce that the commitment is
+    /// "in the field."
+    pub fn to_bits_le<CS>(
+        &self,
+        mut cs: CS
+    ) -> Result<Vec<Boolean>, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let bits = boolean::field_into_allocated_bits_le(
+            &mut cs,
+            self.value
+        )?;
+
+        let mut lc = LinearCombination::zero();
+        let mut coeff = E::Fr::one();
+
+        for bit in bits.iter() {
+            lc = lc + (coeff, bit.get_variable());
+
+            coeff.double();
+        }
+
+        cs.enforce(
+            || "unpacking constraint",
+            |lc| lc + self.variable,
+            |lc| lc + CS::one(),
+            |_| lc
+        );
+
+        Ok(bits.into_iter().map(Boolean::from).collect())
+    }
+
+    pub fn mul<CS>(
+        &self,
+        mut cs: CS,
+        other: &Self
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let mut value = None;
+
+        let var = cs.alloc(|| "product num", || {
+            let mut tmp = self.value.ok_or(SynthesisError::AssignmentMissing)?;
+            tmp.mul_assign(&other.value.ok_or(SynthesisError::AssignmentMissing)?);
+
+            value = Some(tmp);
+
+            Ok(tmp)
+        })?;
+
+        // Constrain: a * b = ab
+        cs.enforce(
+            || "multiplication constraint",
+            |lc| lc + self.variable,
+            |lc| lc + other.variable,
+            |lc| lc + var
+        );
+
+        Ok(AllocatedNum {
+            value: value,
+            variable: var
+        })
+    }
+
+    pub fn square<CS>(
+        &self,
+        mut cs: CS
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let mut value = None;
+
+        let var = cs.alloc(|| "squared num", || {
+            let mut tmp = self.value.ok_or(SynthesisError::AssignmentMissing)?;
+            tmp.square();
+
+            value = Some(tmp);
+
+            Ok(tmp)
+        })?;
+
+        // Constrain: a * a = aa
+        cs.enforce(
+            || "squaring constraint",
+            |lc| lc + self.variable,
+            |lc| lc + self.variable,
+            |lc| lc + var
+        );
+
+        Ok(AllocatedNum {
+            value: value,
+            variable: var
+        })
+    }
+
+    /// Computes the inverse, which also proves that the number is not zero.
+    pub fn inverse<CS>(
+        &self,
+        mut cs: CS
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let mut value = None;
+
+        let var = cs.alloc(|| "inverse num", || {
+            let tmp = self.value.ok_or(SynthesisError::AssignmentMissing)?;
+            let tmp = tmp.inverse().ok_or(SynthesisError::DivisionByZero)?;
+
+            value = Some(tmp);
+
+            Ok(tmp)
+        })?;
+
+        // Constrain: a * inv = 1
+        cs.enforce(
+            || "inverse constraint",
+            |lc| lc + self.variable,
+            |lc| lc + var,
+            |lc| lc + CS::one()
+        );
+
+        Ok(AllocatedNum {
+            value: value,
+            variable: var
+        })
+    }
+
+    pub fn assert_nonzero<CS>(
+        &self,
+        cs: CS
+    ) -> Result<(), SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        self.inverse(cs)?;
+
+        Ok(())
+    }
+
+    /// Returns a bit that is set iff the number is zero. Costs two
+    /// constraints.
+    pub fn is_zero<CS>(
+        &self,
+        cs: CS
+    ) -> Result<Boolean, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        lc_is_zero(cs, LinearCombination::zero() + self.variable, self.value)
+    }
+
+    /// Returns a bit that is set iff both numbers are equal. Costs two
+    /// constraints.
+    pub fn equals<CS>(
+        cs: CS,
+        a: &Self,
+        b: &Self
+    ) -> Result<Boolean, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let value = match (a.value, b.value) {
+            (Some(mut a), Some(b)) => {
+                a.sub_assign(&b);
+                Some(a)
+            },
+            _ => None
+        };
+
+        lc_is_zero(cs, LinearCombination::zero() + a.variable - b.variable, value)
+    }
+
+    /// Returns `a` if the condition is true and `b` otherwise. Costs
+    /// a single constraint.
+    pub fn conditionally_select<CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+        condition: &Boolean
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        match *condition {
+            Boolean::Constant(true) => return Ok(a.clone()),
+            Boolean::Constant(false) => return Ok(b.clone()),
+            _ => {}
+        }
+
+        let c = Self::alloc(
+            cs.namespace(|| "conditional select result"),
+            || {
+                if condition.get_value().ok_or(SynthesisError::AssignmentMissing)? {
+                    a.value.ok_or(SynthesisError::AssignmentMissing)
+                } else {
+                    b.value.ok_or(SynthesisError::AssignmentMissing)
+                }
+            }
+        )?;
+
+        // Constrain: (a - b) * condition = (c - b)
+        cs.enforce(
+            || "conditional select constraint",
+            |lc| lc + a.variable - b.variable,
+            |lc| lc + &condition.lc(CS::one(), E::Fr::one()),
+            |lc| lc + c.variable - b.variable
+        );
+
+        Ok(c)
+    }
+
+    /// Takes two allocated numbers (a, b) and returns
+    /// (b, a) if the condition is true, and (a, b)
+    /// otherwise.
+    pub fn conditionally_reverse<CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+        condition: &Boolean
+    ) -> Result<(Self, Self), SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let c = Self::conditionally_select(
+            cs.namespace(|| "first"),
+            b,
+            a,
+            condition
+        )?;
+
+        // d = a + b - c
+        let d = Self::alloc(
+            cs.namespace(|| "second"),
+            || {
+                let mut tmp = a.value.ok_or(SynthesisError::AssignmentMissing)?;
+                tmp.add_assign(&b.value.ok_or(SynthesisError::AssignmentMissing)?);
+                tmp.sub_assign(&c.value.ok_or(SynthesisError::AssignmentMissing)?);
+
+                Ok(tmp)
+            }
+        )?;
+
+        cs.enforce(
+            || "sum is preserved",
+            |lc| lc + a.variable + b.variable - c.variable,
+            |lc| lc + CS::one(),
+            |lc| lc + d.variable
+        );
+
+        Ok((c, d))
+    }
+
+    pub fn get_value(&self) -> Option<E::Fr> {
+        self.value
+    }
+
+    pub fn get_variable(&self) -> Variable {
+        self.variable
+    }
+
+    /// Binds an expression to a number. This costs a constraint unless
+    /// the expression is a single variable already.
+    pub fn from_expr<CS>(
+        cs: CS,
+        expr: &Expr<E>
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        Ok(AllocatedNum {
+            variable: expr.to_variable(cs)?,
+            value: expr.get_value()
+        })
+    }
+}
+
+/// Allocates a bit that is set iff the linear combination is zero.
+pub(crate) fn lc_is_zero<E, CS>(
+    mut cs: CS,
+    x: LinearCombination<E>,
+    value: Option<E::Fr>
+) -> Result<Boolean, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    let result_value = value.map(|v| v.is_zero());
+    let result = cs.alloc(|| "is zero", || {
+        let value = result_value.ok_or(SynthesisError::AssignmentMissing)?;
+
+        Ok(if value { E::Fr::one() } else { E::Fr::zero() })
+    })?;
+
+    // Any value works for the inverse of zero
+    let inverse = cs.alloc(|| "inverse", || {
+        let value = value.ok_or(SynthesisError::AssignmentMissing)?;
+
+        Ok(value.inverse().unwrap_or(E::Fr::zero()))
+    })?;
+
+    // Constrain: x * inv = 1 - r
+    // A non-zero x forces r to zero below, and then the inverse
+    // must exist. For x = 0 this leaves only r = 1, so the
+    // result is boolean without a separate constraint.
+    cs.enforce(
+        || "inverse constraint",
+        |lc| lc + &x,
+        |lc| lc + inverse,
+        |lc| lc + CS::one() - result
+    );
+
+    // Constrain: x * r = 0
+    cs.enforce(
+        || "zero constraint",
+        |lc| lc + &x,
+        |lc| lc + result,
+        |lc| lc
+    );
+
+    Ok(Boolean::Is(AllocatedBit {
+        variable: result,
+        value: result_value
+    }))
+}
+
+pub struct Num<E: Engine> {
+    value: Option<E::Fr>,
+    lc: LinearCombination<E>
+}
+
+impl<E: Engine> From<AllocatedNum<E>> for Num<E> {
+    fn from(num: AllocatedNum<E>) -> Num<E> {
+        Num {
+            value: num.value,
+            lc: LinearCombination::<E>::zero() + num.variable
+        }
+    }
+}
+
+impl<E: Engine> Num<E> {
+    pub fn zero() -> Self {
+        Num {
+            value: Some(E::Fr::zero()),
+            lc: LinearCombination::zero()
+        }
+    }
+
+    pub fn get_value(&self) -> Option<E::Fr> {
+        self.value
+    }
+
+    pub fn lc(&self, coeff: E::Fr) -> LinearCombination<E> {
+        LinearCombination::zero() + (coeff, &self.lc)
+    }
+
+    pub fn add_bool_with_coeff(
+        self,
+        one: Variable,
+        bit: &Boolean,
+        coeff: E::Fr
+    ) -> Self
+    {
+        let newval = match (self.value, bit.get_value()) {
+            (Some(mut curval), Some(bval)) => {
+                if bval {
+                    curval.add_assign(&coeff);
+                }
+
+                Some(curval)
+            },
+            _ => None
+        };
+
+        Num {
+            value: newval,
+            lc: self.lc + &bit.lc(one, coeff)
+        }
+    }
+}
+
+impl<E: Engine> From<AllocatedNum<E>> for Expr<E> {
+    fn from(num: AllocatedNum<E>) -> Expr<E> {
+        Expr::from_variable(num.variable, num.value)
+    }
+}
+
+impl<E: Engine> Allocate<E> for AllocatedNum<E> {
+    type Value = E::Fr;
+
+    fn allocate<CS>(
+        cs: CS,
+        value: Option<&E::Fr>
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        AllocatedNum::alloc(cs, || value.cloned().ok_or(SynthesisError::AssignmentMissing))
+    }
+
+    fn get_value(&self) -> Option<E::Fr> {
+        self.value
+    }
+
+    fn inputize<CS>(
+        &self,
+        cs: CS
+    ) -> Result<(), SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        AllocatedNum::inputize(self, cs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bls12_381::{Bls12, Fr};
+    use crate::r1cs::{R1CS, RecordingSystem, evaluate};
+
+    use rand::{Rand, thread_rng};
+
+    fn alloc(cs: &mut RecordingSystem<Bls12>, name: &str, value: Fr) -> AllocatedNum<Bls12> {
+        AllocatedNum::alloc(cs.namespace(|| name), || Ok(value)).unwrap()
+    }
+
+    fn aux(r1cs: &R1CS<Bls12>, path: &str) -> usize {
+        match r1cs.index_of(path) {
+            Some(crate::Index::Aux(i)) => i,
+            _ => panic!("no auxillary variable `{}`", path)
+        }
+    }
+
+    #[test]
+    fn test_num_arithmetic() {
+        let rng = &mut thread_rng();
+        let x = Fr::rand(rng);
+        let y = Fr::rand(rng);
+
+        let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+        let a = alloc(&mut cs, "a", x);
+        let b = alloc(&mut cs, "b", y);
+
+        let product = a.mul(cs.namespace(|| "mul"), &b).unwrap();
+        let square = a.square(cs.namespace(|| "square")).unwrap();
+        let inverse = a.inverse(cs.namespace(|| "inverse")).unwrap();
+        product.inputize(cs.namespace(|| "product")).unwrap();
+
+        let mut expected = x;
+        expected.mul_assign(&y);
+        assert_eq!(product.get_value(), Some(expected));
+        let mut expected = x;
+        expected.square();
+        assert_eq!(square.get_value(), Some(expected));
+        assert_eq!(inverse.get_value(), x.inverse());
+
+        let mut r1cs = cs.into_r1cs();
+        assert_eq!(r1cs.num_constraints(), 4);
+        r1cs.check_satisfied().unwrap();
+
+        let i = aux(&r1cs, "inverse/inverse num");
+        r1cs.aux_assignment.as_mut().unwrap()[i] = y;
+        assert!(r1cs.check_satisfied().is_err());
+
+        let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+        let zero = alloc(&mut cs, "zero", Fr::zero());
+        let err = zero.inverse(cs.namespace(|| "inverse")).err().expect("zero must not be invertible");
+        match *err.kind() {
+            SynthesisError::DivisionByZero => {},
+            _ => panic!("unexpected error {}", err)
+        }
+    }
+
+    #[test]
+    fn test_num_equality() {
+        let rng = &mut thread_rng();
+        let x = Fr::rand(rng);
+        let y = Fr::rand(rng);
+
+        for &(a, b) in [(x, x), (x, y), (Fr::zero(), y), (Fr::zero(), Fr::zero())].iter() {
+            let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+            let na = alloc(&mut cs, "a", a);
+            let nb = alloc(&mut cs, "b", b);
+
+            let equal = AllocatedNum::equals(cs.namespace(|| "equals"), &na, &nb).unwrap();
+            let zero = na.is_zero(cs.namespace(|| "is zero")).unwrap();
+            assert_eq!(equal.get_value(), Some(a == b));
+            assert_eq!(zero.get_value(), Some(a.is_zero()));
+
+            let mut r1cs = cs.into_r1cs();
+            assert_eq!(r1cs.num_constraints(), 4);
+            r1cs.check_satisfied().unwrap();
+
+            // Claiming the opposite result fails whatever the inverse is
+            let result = aux(&r1cs, "equals/is zero");
+            let inverse = aux(&r1cs, "equals/inverse");
+            let flipped = if a == b { Fr::zero() } else { Fr::one() };
+            r1cs.aux_assignment.as_mut().unwrap()[result] = flipped;
+            for candidate in [Fr::zero(), Fr::one(), Fr::rand(rng)].iter() {
+                r1cs.aux_assignment.as_mut().unwrap()[inverse] = *candidate;
+                assert!(r1cs.check_satisfied().is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_num_conditional_reversal() {
+        let rng = &mut thread_rng();
+        let x = Fr::rand(rng);
+        let y = Fr::rand(rng);
+
+        for &condition in [false, true].iter() {
+            let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+            let a = alloc(&mut cs, "a", x);
+            let b = alloc(&mut cs, "b", y);
+            let bit = Boolean::from(AllocatedBit::alloc(cs.namespace(|| "condition"), Some(condition)).unwrap());
+
+            let selected = AllocatedNum::conditionally_select(cs.namespace(|| "select"), &a, &b, &bit).unwrap();
+            let (c, d) = AllocatedNum::conditionally_reverse(cs.namespace(|| "reverse"), &a, &b, &bit).unwrap();
+
+            if condition {
+                assert_eq!(selected.get_value(), Some(x));
+                assert_eq!((c.get_value(), d.get_value()), (Some(y), Some(x)));
+            } else {
+                assert_eq!(selected.get_value(), Some(y));
+                assert_eq!((c.get_value(), d.get_value()), (Some(x), Some(y)));
+            }
+
+            cs.into_r1cs().check_satisfied().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_num_to_bits_le_strict() {
+        let rng = &mut thread_rng();
+
+        let mut negative_one = Fr::one();
+        negative_one.negate();
+
+        for &value in [Fr::zero(), Fr::one(), negative_one, Fr::rand(rng)].iter() {
+            let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+            let num = alloc(&mut cs, "num", value);
+            let bits = num.to_bits_le_strict(cs.namespace(|| "bits")).unwrap();
+            assert_eq!(bits.len(), Fr::NUM_BITS as usize);
+
+            let mut packed = Fr::zero();
+            let mut coeff = Fr::one();
+            for bit in bits.iter() {
+                if bit.get_value().unwrap() {
+                    packed.add_assign(&coeff);
+                }
+                coeff.double();
+            }
+            assert_eq!(packed, value);
+
+            cs.into_r1cs().check_satisfied().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_num_to_bits_le_strict_rejects_modulus() {
+        let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+        let num = alloc(&mut cs, "num", Fr::zero());
+        num.to_bits_le_strict(cs.namespace(|| "bits")).unwrap();
+
+        let mut r1cs = cs.into_r1cs();
+
+        // Zero is congruent to the modulus itself, which has the same
+        // number of bits. Bits are named from the most significant one.
+        let modulus: Vec<bool> = BitIterator::new(Fr::char()).skip_while(|b| !*b).collect();
+        assert_eq!(modulus.len(), Fr::NUM_BITS as usize);
+        for (i, &bit) in modulus.iter().enumerate() {
+            let j = aux(&r1cs, &format!("bits/bit {}/boolean", i));
+            r1cs.aux_assignment.as_mut().unwrap()[j] = if bit { Fr::one() } else { Fr::zero() };
+        }
+
+        // Recompute the runs of ones accordingly
+        for constraint in r1cs.constraints.clone().iter() {
+            if constraint.annotation.ends_with("/and constraint") {
+                let input = r1cs.input_assignment.clone().unwrap();
+                let assignment = r1cs.aux_assignment.clone().unwrap();
+
+                let mut product = evaluate::<Bls12>(&constraint.a, &input, &assignment);
+                product.mul_assign(&evaluate::<Bls12>(&constraint.b, &input, &assignment));
+                match constraint.c[0].0 {
+                    crate::Index::Aux(j) => r1cs.aux_assignment.as_mut().unwrap()[j] = product,
+                    _ => unreachable!()
+                }
+            }
+        }
+
+        let err = r1cs.check_satisfied().unwrap_err();
+        let path = err.location().unwrap().path.clone().unwrap();
+        assert!(path.ends_with("/boolean constraint"), "{}", path);
+    }
+}
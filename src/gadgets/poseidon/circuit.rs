@@ -0,0 +1,144 @@
+//! Poseidon in a constraint system. Round constants and the MDS matrix
+//! are applied to linear combinations for free, so a permutation only costs
+//! the S-boxes, which take one constraint per squaring and multiplication
+//! of `x^alpha`. For the S-box `x^5` that is three constraints each, so the
+//! width 3 instance costs 243 constraints. S-boxes of constant elements
+//! are folded, such as the capacity element in the first round of `hash`.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use crate::gadgets::expr::Expr;
+
+use super::PoseidonParams;
+
+/// Applies the permutation to the state.
+pub fn permute<E, CS>(
+    mut cs: CS,
+    params: &PoseidonParams<E>,
+    state: &[Expr<E>]
+) -> Result<Vec<Expr<E>>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(state.len(), params.width());
+
+    let mut state = state.to_vec();
+    for round in 0..params.num_rounds() {
+        let mut cs = cs.namespace(|| format!("round {}", round));
+
+        for (s, &c) in state.iter_mut().zip(params.round_constants(round)) {
+            *s = s.clone() + Expr::constant(c);
+        }
+
+        let sboxes = if params.is_full_round(round) { params.width() } else { 1 };
+        for (i, s) in state.iter_mut().take(sboxes).enumerate() {
+            *s = s.pow(cs.namespace(|| format!("sbox {}", i)), params.alpha())?;
+        }
+
+        state = params.mds_matrix().iter().map(|row| {
+            row.iter().zip(state.iter()).fold(Expr::zero(), |acc, (&m, s)| acc + s.clone() * m)
+        }).collect();
+    }
+
+    Ok(state)
+}
+
+/// Hashes exactly `rate` elements in the same way as `native::hash`.
+pub fn hash<E, CS>(
+    cs: CS,
+    params: &PoseidonParams<E>,
+    input: &[Expr<E>]
+) -> Result<Expr<E>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(input.len(), params.rate());
+
+    let mut state = Vec::with_capacity(params.width());
+    state.push(Expr::zero());
+    state.extend_from_slice(input);
+
+    let state = permute(cs, params, &state)?;
+
+    Ok(state[0].clone())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use super::super::native;
+
+    use crate::pairing::bn256::{Bn256, Fr as Bn256Fr};
+    use crate::pairing::bls12_381::{Bls12, Fr};
+    use crate::r1cs::RecordingSystem;
+
+    use rand::{Rng, thread_rng};
+
+    #[test]
+    fn test_permutation_agrees_with_native() {
+        let rng = &mut thread_rng();
+        let params = PoseidonParams::<Bls12>::new(3, 128, 5).unwrap();
+
+        for _ in 0..5 {
+            let mut expected: Vec<Fr> = (0..3).map(|_| rng.gen()).collect();
+
+            let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+            let state: Vec<_> = expected.iter().enumerate().map(|(i, &v)| {
+                Expr::alloc(cs.namespace(|| format!("state {}", i)), || Ok(v)).unwrap()
+            }).collect();
+            let permuted = permute(cs.namespace(|| "poseidon"), &params, &state).unwrap();
+
+            native::permute(&params, &mut expected);
+            let values: Vec<_> = permuted.iter().map(|s| s.get_value().unwrap()).collect();
+            assert_eq!(values, expected);
+
+            // Bind the output, so that the constraints check it
+            for (i, (s, v)) in permuted.iter().zip(expected.iter()).enumerate() {
+                s.assert_eq(cs.namespace(|| format!("output {}", i)), &Expr::constant(*v)).unwrap();
+            }
+
+            let r1cs = cs.into_r1cs();
+            r1cs.check_satisfied().unwrap();
+            assert_eq!(r1cs.constraints.iter().filter(|c| c.annotation.starts_with("poseidon/")).count(), (8 * 3 + 57) * 3);
+        }
+    }
+
+    #[test]
+    fn test_hash_agrees_with_native() {
+        let rng = &mut thread_rng();
+        let params = PoseidonParams::<Bn256>::new(3, 128, 5).unwrap();
+        let input: Vec<Bn256Fr> = (0..2).map(|_| rng.gen()).collect();
+
+        let mut cs = RecordingSystem::<Bn256>::new_with_witness();
+        let a = Expr::alloc(cs.namespace(|| "a"), || Ok(input[0])).unwrap();
+        let b = Expr::alloc(cs.namespace(|| "b"), || Ok(input[1])).unwrap();
+        let digest = hash(cs.namespace(|| "poseidon"), &params, &[a, b]).unwrap();
+        assert_eq!(digest.get_value(), Some(native::hash(&params, &input)));
+
+        let out = Expr::alloc_input(cs.namespace(|| "out"), || digest.get_value().ok_or(SynthesisError::AssignmentMissing)).unwrap();
+        digest.assert_eq(cs.namespace(|| "digest"), &out).unwrap();
+
+        let mut r1cs = cs.into_r1cs();
+        r1cs.check_satisfied().unwrap();
+
+        // The S-box of the constant capacity element is folded
+        assert_eq!(r1cs.constraints.iter().filter(|c| c.annotation.starts_with("poseidon/")).count(), 240);
+
+        r1cs.input_assignment.as_mut().unwrap()[1] = rng.gen();
+        assert!(r1cs.check_satisfied().is_err());
+
+        // Hashing constants needs no constraints at all
+        let mut cs = RecordingSystem::<Bn256>::new();
+        let constants: Vec<_> = input.iter().map(|&v| Expr::constant(v)).collect();
+        let digest = hash(&mut cs, &params, &constants).unwrap();
+        assert!(digest.is_constant());
+        assert_eq!(digest.get_value(), Some(native::hash(&params, &input)));
+        assert_eq!(cs.into_r1cs().num_constraints(), 0);
+    }
+}
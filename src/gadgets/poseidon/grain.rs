@@ -0,0 +1,120 @@
+use crate::pairing::ff::{
+    PrimeField,
+    PrimeFieldRepr
+};
+
+/// The 80-bit Grain LFSR that the reference implementation of Poseidon
+/// uses to derive its round constants and MDS matrix. The state is seeded
+/// with a description of the instance, so every instance gets its own
+/// stream of bits.
+pub(crate) struct Grain {
+    state: [bool; 80],
+    // Position of the oldest bit in `state`
+    head: usize
+}
+
+impl Grain {
+    /// Seeds the LFSR for a prime field with the S-box `x^alpha`.
+    pub fn new(
+        field_bits: usize,
+        width: usize,
+        full_rounds: usize,
+        partial_rounds: usize
+    ) -> Self
+    {
+        let fields: [(u64, usize); 7] = [
+            // A prime field
+            (1, 2),
+            // The S-box x^alpha, as opposed to x^-1
+            (0, 4),
+            (field_bits as u64, 12),
+            (width as u64, 12),
+            (full_rounds as u64, 10),
+            (partial_rounds as u64, 10),
+            ((1 << 30) - 1, 30)
+        ];
+
+        let mut state = [false; 80];
+        let bits = fields.iter().flat_map(|&(value, len)| {
+            (0..len).rev().map(move |i| (value >> i) & 1 == 1)
+        });
+        for (s, b) in state.iter_mut().zip(bits) {
+            *s = b;
+        }
+
+        let mut grain = Grain {
+            state: state,
+            head: 0
+        };
+
+        // The first 160 bits are discarded
+        for _ in 0..160 {
+            grain.clock();
+        }
+
+        grain
+    }
+
+    fn clock(&mut self) -> bool {
+        let bit = {
+            let s = |i: usize| self.state[(self.head + i) % 80];
+
+            s(62) ^ s(51) ^ s(38) ^ s(23) ^ s(13) ^ s(0)
+        };
+
+        self.state[self.head] = bit;
+        self.head = (self.head + 1) % 80;
+
+        bit
+    }
+
+    /// Bits are produced in pairs, and the second bit is only output
+    /// if the first one is set.
+    fn next_bit(&mut self) -> bool {
+        loop {
+            let keep = self.clock();
+            let bit = self.clock();
+
+            if keep {
+                return bit;
+            }
+        }
+    }
+
+    // The next `NUM_BITS` bits, most significant first
+    fn next_repr<F: PrimeField>(&mut self) -> F::Repr {
+        let num_bits = F::NUM_BITS as usize;
+
+        let mut repr = F::Repr::default();
+        for i in (0..num_bits).rev() {
+            if self.next_bit() {
+                repr.as_mut()[i / 64] |= 1 << (i % 64);
+            }
+        }
+
+        repr
+    }
+
+    /// Samples a field element uniformly, skipping values that are not
+    /// below the modulus.
+    pub fn next_field_element<F: PrimeField>(&mut self) -> F {
+        loop {
+            if let Ok(value) = F::from_repr(self.next_repr::<F>()) {
+                return value;
+            }
+        }
+    }
+
+    /// Samples a field element by reducing `NUM_BITS` bits modulo the
+    /// characteristic, which is how the MDS matrix is sampled.
+    pub fn next_field_element_reduced<F: PrimeField>(&mut self) -> F {
+        let mut repr = self.next_repr::<F>();
+
+        // NUM_BITS bits are below twice the modulus
+        if repr >= F::char() {
+            repr.sub_noborrow(&F::char());
+        }
+
+        F::from_repr(repr).expect("reduced below the modulus")
+    }
+}
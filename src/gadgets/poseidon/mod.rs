@@ -0,0 +1,266 @@
+//! The Poseidon hash function, from "Poseidon: A New Hash Function for
+//! Zero-Knowledge Proof Systems" by Grassi, Khovratovich, Rechberger,
+//! Roy and Schofnegger.
+//!
+//! The permutation alternates rounds in which the S-box `x^alpha` is
+//! applied to the whole state with rounds in which it is only applied to
+//! the first element, with a round constant addition before and an MDS
+//! matrix multiplication after every S-box layer. Only the S-boxes need
+//! constraints, which makes it cheap to use in circuits.
+//!
+//! The parameters are derived with the Grain LFSR in the same way as the
+//! reference implementation does, so they agree with the published test
+//! vectors and with circomlib for the same width. The reference script
+//! additionally checks the MDS matrix against subspace trails and samples
+//! a new one if it fails; that check is not performed here, so widths for
+//! which the first matrix is rejected get different parameters.
+//!
+//! `native` computes the hash outside of circuits, and `circuit` computes
+//! it in a constraint system.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::pairing::ff::{
+    Field,
+    PrimeField
+};
+
+mod grain;
+
+pub mod circuit;
+pub mod native;
+
+use self::grain::Grain;
+
+// Recommended partial rounds for widths 2 to 17, with eight full rounds,
+// the S-box x^5 and 128 bits of security, over fields of about 255 bits
+const PARTIAL_ROUNDS_X5_128: [usize; 16] = [56, 57, 56, 60, 60, 63, 64, 63, 60, 66, 60, 65, 70, 60, 64, 68];
+
+/// Returns the recommended numbers of full and partial rounds for the
+/// given width, security level in bits and S-box exponent, if they are
+/// known. Round numbers are published for 128 bits of security and the
+/// S-box `x^5`, which suits both the BN256 and BLS12-381 scalar fields.
+pub fn recommended_rounds(
+    width: usize,
+    security_level: usize,
+    alpha: u64
+) -> Option<(usize, usize)>
+{
+    if security_level != 128 || alpha != 5 || width < 2 {
+        return None;
+    }
+
+    PARTIAL_ROUNDS_X5_128.get(width - 2).map(|&partial_rounds| (8, partial_rounds))
+}
+
+/// An instance of the Poseidon permutation over the scalar field of `E`.
+#[derive(Clone)]
+pub struct PoseidonParams<E: Engine> {
+    width: usize,
+    alpha: u64,
+    full_rounds: usize,
+    partial_rounds: usize,
+    // Width many constants for each round
+    round_constants: Vec<E::Fr>,
+    mds_matrix: Vec<Vec<E::Fr>>
+}
+
+impl<E: Engine> PoseidonParams<E> {
+    /// Generates parameters with the recommended round numbers, or returns
+    /// `None` if no round numbers are known for the combination.
+    pub fn new(
+        width: usize,
+        security_level: usize,
+        alpha: u64
+    ) -> Option<Self>
+    {
+        let (full_rounds, partial_rounds) = recommended_rounds(width, security_level, alpha)?;
+
+        Some(Self::with_rounds(width, alpha, full_rounds, partial_rounds))
+    }
+
+    /// Generates parameters with the given round numbers. The number of
+    /// full rounds must be even, as they are split evenly around the
+    /// partial rounds, and `x^alpha` must be a permutation of the field.
+    pub fn with_rounds(
+        width: usize,
+        alpha: u64,
+        full_rounds: usize,
+        partial_rounds: usize
+    ) -> Self
+    {
+        assert!(width >= 2, "the state must have room for a capacity and a rate");
+        assert!(full_rounds.is_multiple_of(2), "the full rounds are split evenly");
+        assert!(alpha >= 3 && is_permutation::<E::Fr>(alpha), "x^alpha is not a permutation of the field");
+
+        let mut grain = Grain::new(E::Fr::NUM_BITS as usize, width, full_rounds, partial_rounds);
+
+        let round_constants = (0..(full_rounds + partial_rounds) * width)
+            .map(|_| grain.next_field_element())
+            .collect();
+
+        // A Cauchy matrix 1 / (x_i + y_j) for distinct x_i and y_j, which
+        // is MDS as long as no sum is zero
+        let mds_matrix = loop {
+            let mut elements: Vec<E::Fr> = (0..2 * width).map(|_| grain.next_field_element_reduced()).collect();
+            while has_duplicates(&elements) {
+                elements = (0..2 * width).map(|_| grain.next_field_element_reduced()).collect();
+            }
+
+            let (xs, ys) = elements.split_at(width);
+            let matrix: Option<Vec<Vec<E::Fr>>> = xs.iter().map(|x| {
+                ys.iter().map(|y| {
+                    let mut sum = *x;
+                    sum.add_assign(y);
+
+                    sum.inverse()
+                }).collect()
+            }).collect();
+
+            if let Some(matrix) = matrix {
+                break matrix;
+            }
+        };
+
+        PoseidonParams {
+            width: width,
+            alpha: alpha,
+            full_rounds: full_rounds,
+            partial_rounds: partial_rounds,
+            round_constants: round_constants,
+            mds_matrix: mds_matrix
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The number of elements absorbed per permutation, which is the
+    /// width less one element of capacity.
+    pub fn rate(&self) -> usize {
+        self.width - 1
+    }
+
+    pub fn alpha(&self) -> u64 {
+        self.alpha
+    }
+
+    pub fn full_rounds(&self) -> usize {
+        self.full_rounds
+    }
+
+    pub fn partial_rounds(&self) -> usize {
+        self.partial_rounds
+    }
+
+    /// The constants added to the state in the given round.
+    pub fn round_constants(&self, round: usize) -> &[E::Fr] {
+        &self.round_constants[round * self.width..(round + 1) * self.width]
+    }
+
+    pub fn mds_matrix(&self) -> &[Vec<E::Fr>] {
+        &self.mds_matrix
+    }
+
+    /// Returns whether the S-box is applied to the whole state in the
+    /// given round, rather than to the first element only.
+    pub fn is_full_round(&self, round: usize) -> bool {
+        let half = self.full_rounds / 2;
+
+        round < half || round >= half + self.partial_rounds
+    }
+
+    pub fn num_rounds(&self) -> usize {
+        self.full_rounds + self.partial_rounds
+    }
+}
+
+fn has_duplicates<F: PartialEq>(elements: &[F]) -> bool {
+    elements.iter().enumerate().any(|(i, a)| elements[..i].contains(a))
+}
+
+// x^alpha permutes the field iff alpha is coprime to p - 1
+fn is_permutation<F: PrimeField>(alpha: u64) -> bool {
+    let alpha = alpha as u128;
+
+    let mut remainder = 0u128;
+    for &limb in F::char().as_ref().iter().rev() {
+        remainder = ((remainder << 64) | limb as u128) % alpha;
+    }
+
+    // (p - 1) mod alpha
+    let mut a = alpha;
+    let mut b = (remainder + alpha - 1) % alpha;
+    while b != 0 {
+        let tmp = a % b;
+        a = b;
+        b = tmp;
+    }
+
+    a == 1
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bn256::Bn256;
+    use crate::pairing::bls12_381::Bls12;
+    use crate::pairing::ff::PrimeFieldRepr;
+
+    pub(crate) fn from_hex<F: PrimeField>(hex: &str) -> F {
+        let hex = hex.trim_start_matches("0x");
+        let bytes: Vec<u8> = (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i+2], 16).unwrap()).collect();
+
+        let mut repr = F::Repr::default();
+        repr.read_be(&bytes[..]).unwrap();
+
+        F::from_repr(repr).unwrap()
+    }
+
+    #[test]
+    fn test_round_numbers() {
+        assert_eq!(recommended_rounds(3, 128, 5), Some((8, 57)));
+        assert_eq!(recommended_rounds(5, 128, 5), Some((8, 60)));
+        assert_eq!(recommended_rounds(1, 128, 5), None);
+        assert_eq!(recommended_rounds(18, 128, 5), None);
+        assert_eq!(recommended_rounds(3, 80, 5), None);
+        assert_eq!(recommended_rounds(3, 128, 3), None);
+
+        assert!(PoseidonParams::<Bn256>::new(3, 128, 5).is_some());
+        assert!(PoseidonParams::<Bn256>::new(3, 256, 5).is_none());
+    }
+
+    #[test]
+    fn test_sbox_exponents() {
+        use crate::pairing::bn256::Fr as Bn256Fr;
+        use crate::pairing::bls12_381::Fr as Bls12Fr;
+
+        // 3 divides p - 1 for both fields, 5 and 7 do not
+        assert!(!is_permutation::<Bn256Fr>(3));
+        assert!(is_permutation::<Bn256Fr>(5));
+        assert!(is_permutation::<Bn256Fr>(7));
+        assert!(!is_permutation::<Bls12Fr>(3));
+        assert!(is_permutation::<Bls12Fr>(5));
+    }
+
+    #[test]
+    fn test_grain_parameters() {
+        let params = PoseidonParams::<Bn256>::new(3, 128, 5).unwrap();
+        assert_eq!(params.round_constants.len(), 65 * 3);
+        assert_eq!(params.round_constants(0)[0], from_hex("0x0ee9a592ba9a9518d05986d656f40c2114c4993c11bb29938d21d47304cd8e6e"));
+        assert_eq!(params.mds_matrix()[0][0], from_hex("0x109b7f411ba0e4c9b2b70caf5c36a7b194be7c11ad24378bfedb68592ba8118b"));
+
+        let params = PoseidonParams::<Bls12>::new(3, 128, 5).unwrap();
+        assert_eq!(params.round_constants(0)[0], from_hex("0x6c4ffa723eaf1a7bf74905cc7dae4ca9ff4a2c3bc81d42e09540d1f250910880"));
+        assert_eq!(params.mds_matrix()[0][0], from_hex("0x3d955d6c02fe4d7cb500e12f2b55eff668a7b4386bd27413766713c93f2acfcd"));
+
+        let rounds: Vec<_> = (0..params.num_rounds()).map(|r| params.is_full_round(r)).collect();
+        assert!(rounds[..4].iter().all(|&full| full));
+        assert!(rounds[4..61].iter().all(|&full| !full));
+        assert!(rounds[61..].iter().all(|&full| full));
+    }
+}
@@ -0,0 +1,117 @@
+//! Poseidon over field elements, outside of circuits.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::pairing::ff::{
+    Field
+};
+
+use super::PoseidonParams;
+
+/// Applies the permutation to the state in place.
+pub fn permute<E: Engine>(params: &PoseidonParams<E>, state: &mut [E::Fr]) {
+    assert_eq!(state.len(), params.width());
+
+    for round in 0..params.num_rounds() {
+        for (s, c) in state.iter_mut().zip(params.round_constants(round)) {
+            s.add_assign(c);
+        }
+
+        if params.is_full_round(round) {
+            for s in state.iter_mut() {
+                *s = s.pow([params.alpha()]);
+            }
+        } else {
+            state[0] = state[0].pow([params.alpha()]);
+        }
+
+        let mixed: Vec<E::Fr> = params.mds_matrix().iter().map(|row| {
+            let mut acc = E::Fr::zero();
+            for (m, s) in row.iter().zip(state.iter()) {
+                let mut tmp = *m;
+                tmp.mul_assign(s);
+                acc.add_assign(&tmp);
+            }
+
+            acc
+        }).collect();
+        state.copy_from_slice(&mixed);
+    }
+}
+
+/// Hashes exactly `rate` elements, by placing them after a zero capacity
+/// element and returning the first element of the permuted state. This is
+/// the hash that circomlib computes for the same width.
+pub fn hash<E: Engine>(params: &PoseidonParams<E>, input: &[E::Fr]) -> E::Fr {
+    assert_eq!(input.len(), params.rate());
+
+    let mut state = Vec::with_capacity(params.width());
+    state.push(E::Fr::zero());
+    state.extend_from_slice(input);
+
+    permute(params, &mut state);
+
+    state[0]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use super::super::test::from_hex;
+
+    use crate::pairing::bn256::{Bn256, Fr as Bn256Fr};
+    use crate::pairing::bls12_381::{Bls12, Fr as Bls12Fr};
+    use crate::pairing::ff::PrimeField;
+
+    fn state<F: PrimeField>(width: u64) -> Vec<F> {
+        (0..width).map(|i| F::from_str(&i.to_string()).unwrap()).collect()
+    }
+
+    fn elements<F: PrimeField>(values: &[&str]) -> Vec<F> {
+        values.iter().map(|v| F::from_str(v).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_bn256_vectors() {
+        // poseidonperm_x5_254_3 of the reference implementation
+        let params = PoseidonParams::<Bn256>::new(3, 128, 5).unwrap();
+        let mut s = state::<Bn256Fr>(3);
+        permute(&params, &mut s);
+        assert_eq!(s, vec![
+            from_hex("0x115cc0f5e7d690413df64c6b9662e9cf2a3617f2743245519e19607a4417189a"),
+            from_hex("0x0fca49b798923ab0239de1c9e7a4a9a2210312b6a2f616d18b5a87f9b628ae29"),
+            from_hex("0x0e7ae82e40091e63cbd4f16a6d16310b3729d4b6e138fcf54110e2867045a30c")
+        ]);
+
+        // poseidon([1, 2]) of circomlib is the first element of the above
+        assert_eq!(hash(&params, &elements(&["1", "2"])), s[0]);
+
+        // poseidonperm_x5_254_5
+        let params = PoseidonParams::<Bn256>::new(5, 128, 5).unwrap();
+        let mut s = state::<Bn256Fr>(5);
+        permute(&params, &mut s);
+        assert_eq!(s[0], from_hex("0x299c867db6c1fdd79dcefa40e4510b9837e60ebb1ce0663dbaa525df65250465"));
+
+        // poseidon([1]) and poseidon([1, 2, 3]) of circomlib
+        let params = PoseidonParams::<Bn256>::new(2, 128, 5).unwrap();
+        assert_eq!(hash(&params, &elements(&["1"])), from_hex("0x29176100eaa962bdc1fe6c654d6a3c130e96a4d1168b33848b897dc502820133"));
+        let params = PoseidonParams::<Bn256>::new(4, 128, 5).unwrap();
+        assert_eq!(hash(&params, &elements(&["1", "2", "3"])), from_hex("0x0e7732d89e6939c0ff03d5e58dab6302f3230e269dc5b968f725df34ab36d732"));
+    }
+
+    #[test]
+    fn test_bls12_vectors() {
+        // poseidonperm_x5_255_3 of the reference implementation
+        let params = PoseidonParams::<Bls12>::new(3, 128, 5).unwrap();
+        let mut s = state::<Bls12Fr>(3);
+        permute(&params, &mut s);
+        assert_eq!(s, vec![
+            from_hex("0x28ce19420fc246a05553ad1e8c98f5c9d67166be2c18e9e4cb4b4e317dd2a78a"),
+            from_hex("0x51f3e312c95343a896cfd8945ea82ba956c1118ce9b9859b6ea56637b4b1ddc4"),
+            from_hex("0x3b2b69139b235626a0bfb56c9527ae66a7bf486ad8c11c14d1da0c69bbe0f79a")
+        ]);
+    }
+}
@@ -0,0 +1,472 @@
+//! SHA-256 as specified in FIPS 180-4.
+//!
+//! Inputs and outputs are big-endian bit strings, so the bytes of a message
+//! are fed in one after the other, most significant bit first. The additions
+//! of a round are deferred until their result is needed, so that they are
+//! unpacked together and every round costs two modular additions. With an
+//! allocated input, a block costs 25996 constraints. Constant bits are folded
+//! throughout, including the initial state and the padding.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::boolean::Boolean;
+use super::uint::UInt32;
+
+const ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2
+];
+
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+    0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19
+];
+
+/// The length of a block in bits.
+pub const BLOCK_BITS: usize = 512;
+
+/// Hashes a message of any bit length, padding it as the standard
+/// requires. Returns the 256 bits of the digest.
+pub fn sha256<E, CS>(
+    mut cs: CS,
+    input: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    let mut padded = input.to_vec();
+    let plen = padded.len() as u64;
+
+    // The message is followed by a single set bit, and then by zeros
+    // up to 64 bits short of the end of a block
+    padded.push(Boolean::constant(true));
+    while !(padded.len() + 64).is_multiple_of(BLOCK_BITS) {
+        padded.push(Boolean::constant(false));
+    }
+
+    // The block is completed with the length of the message
+    for b in (0..64).rev().map(|i| (plen >> i) & 1 == 1) {
+        padded.push(Boolean::constant(b));
+    }
+    assert!(padded.len().is_multiple_of(BLOCK_BITS));
+
+    let mut cur = get_sha256_iv();
+    for (i, block) in padded.chunks(BLOCK_BITS).enumerate() {
+        cur = sha256_compression_function(
+            cs.namespace(|| format!("block {}", i)),
+            block,
+            &cur
+        )?;
+    }
+
+    Ok(cur.into_iter().flat_map(|e| e.into_bits_be()).collect())
+}
+
+/// Runs the compression function on a single block from the initial
+/// state, without padding the input. This is not SHA-256 of the input,
+/// but is useful when the padding is part of the data being hashed.
+pub fn sha256_block_no_padding<E, CS>(
+    cs: CS,
+    input: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(input.len(), BLOCK_BITS);
+
+    Ok(sha256_compression_function(cs, input, &get_sha256_iv())?
+        .into_iter()
+        .flat_map(|e| e.into_bits_be())
+        .collect())
+}
+
+/// The initial hash value, as constants.
+pub fn get_sha256_iv() -> Vec<UInt32> {
+    IV.iter().map(|&v| UInt32::constant(v)).collect()
+}
+
+/// Updates the hash state with a block of big-endian input bits.
+pub fn sha256_compression_function<E, CS>(
+    mut cs: CS,
+    input: &[Boolean],
+    current_hash_value: &[UInt32]
+) -> Result<Vec<UInt32>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(input.len(), BLOCK_BITS);
+    assert_eq!(current_hash_value.len(), 8);
+
+    let mut w = input.chunks(32)
+                     .map(UInt32::from_bits_be)
+                     .collect::<Vec<_>>();
+
+    for i in 16..64 {
+        let cs = &mut cs.namespace(|| format!("w extension {}", i));
+
+        // s0 := (w[i-15] rightrotate 7) xor (w[i-15] rightrotate 18) xor (w[i-15] rightshift 3)
+        let mut s0 = w[i-15].rotr(7);
+        s0 = s0.xor(
+            cs.namespace(|| "first xor for s0"),
+            &w[i-15].rotr(18)
+        )?;
+        s0 = s0.xor(
+            cs.namespace(|| "second xor for s0"),
+            &w[i-15].shr(3)
+        )?;
+
+        // s1 := (w[i-2] rightrotate 17) xor (w[i-2] rightrotate 19) xor (w[i-2] rightshift 10)
+        let mut s1 = w[i-2].rotr(17);
+        s1 = s1.xor(
+            cs.namespace(|| "first xor for s1"),
+            &w[i-2].rotr(19)
+        )?;
+        s1 = s1.xor(
+            cs.namespace(|| "second xor for s1"),
+            &w[i-2].shr(10)
+        )?;
+
+        let tmp = UInt32::addmany(
+            cs.namespace(|| "computation of w[i]"),
+            &[w[i-16].clone(), s0, w[i-7].clone(), s1]
+        )?;
+
+        // w[i] := w[i-16] + s0 + w[i-7] + s1
+        w.push(tmp);
+    }
+
+    assert_eq!(w.len(), 64);
+
+    // The operands of a sum whose result is not needed yet
+    enum Maybe {
+        Deferred(Vec<UInt32>),
+        Concrete(UInt32)
+    }
+
+    impl Maybe {
+        fn compute<E, CS>(
+            self,
+            cs: CS,
+            others: &[UInt32]
+        ) -> Result<UInt32, SynthesisError>
+            where E: Engine, CS: ConstraintSystem<E>
+        {
+            let mut operands = match self {
+                Maybe::Concrete(v) => {
+                    if others.is_empty() {
+                        return Ok(v);
+                    }
+
+                    vec![v]
+                },
+                Maybe::Deferred(v) => v
+            };
+            operands.extend(others.iter().cloned());
+
+            UInt32::addmany(cs, &operands)
+        }
+    }
+
+    let mut a = Maybe::Concrete(current_hash_value[0].clone());
+    let mut b = current_hash_value[1].clone();
+    let mut c = current_hash_value[2].clone();
+    let mut d = current_hash_value[3].clone();
+    let mut e = Maybe::Concrete(current_hash_value[4].clone());
+    let mut f = current_hash_value[5].clone();
+    let mut g = current_hash_value[6].clone();
+    let mut h = current_hash_value[7].clone();
+
+    for (i, (w, &k)) in w.iter().zip(ROUND_CONSTANTS.iter()).enumerate() {
+        let cs = &mut cs.namespace(|| format!("compression round {}", i));
+
+        // S1 := (e rightrotate 6) xor (e rightrotate 11) xor (e rightrotate 25)
+        let new_e = e.compute(cs.namespace(|| "deferred e computation"), &[])?;
+        let mut s1 = new_e.rotr(6);
+        s1 = s1.xor(
+            cs.namespace(|| "first xor for s1"),
+            &new_e.rotr(11)
+        )?;
+        s1 = s1.xor(
+            cs.namespace(|| "second xor for s1"),
+            &new_e.rotr(25)
+        )?;
+
+        // ch := (e and f) xor ((not e) and g)
+        let ch = UInt32::ch(
+            cs.namespace(|| "ch"),
+            &new_e,
+            &f,
+            &g
+        )?;
+
+        // temp1 := h + S1 + ch + k[i] + w[i]
+        let temp1 = vec![
+            h.clone(),
+            s1,
+            ch,
+            UInt32::constant(k),
+            w.clone()
+        ];
+
+        // S0 := (a rightrotate 2) xor (a rightrotate 13) xor (a rightrotate 22)
+        let new_a = a.compute(cs.namespace(|| "deferred a computation"), &[])?;
+        let mut s0 = new_a.rotr(2);
+        s0 = s0.xor(
+            cs.namespace(|| "first xor for s0"),
+            &new_a.rotr(13)
+        )?;
+        s0 = s0.xor(
+            cs.namespace(|| "second xor for s0"),
+            &new_a.rotr(22)
+        )?;
+
+        // maj := (a and b) xor (a and c) xor (b and c)
+        let maj = UInt32::maj(
+            cs.namespace(|| "maj"),
+            &new_a,
+            &b,
+            &c
+        )?;
+
+        // temp2 := S0 + maj
+        let temp2 = vec![s0, maj];
+
+        /*
+        h := g
+        g := f
+        f := e
+        e := d + temp1
+        d := c
+        c := b
+        b := a
+        a := temp1 + temp2
+        */
+
+        h = g;
+        g = f;
+        f = new_e;
+        e = Maybe::Deferred(temp1.iter().cloned().chain(Some(d)).collect());
+        d = c;
+        c = b;
+        b = new_a;
+        a = Maybe::Deferred(temp1.into_iter().chain(temp2).collect());
+    }
+
+    /*
+    Add the compressed chunk to the current hash value:
+    h0 := h0 + a
+    h1 := h1 + b
+    h2 := h2 + c
+    h3 := h3 + d
+    h4 := h4 + e
+    h5 := h5 + f
+    h6 := h6 + g
+    h7 := h7 + h
+    */
+
+    let h0 = a.compute(
+        cs.namespace(|| "deferred h0 computation"),
+        &[current_hash_value[0].clone()]
+    )?;
+
+    let h1 = UInt32::addmany(
+        cs.namespace(|| "new h1"),
+        &[current_hash_value[1].clone(), b]
+    )?;
+
+    let h2 = UInt32::addmany(
+        cs.namespace(|| "new h2"),
+        &[current_hash_value[2].clone(), c]
+    )?;
+
+    let h3 = UInt32::addmany(
+        cs.namespace(|| "new h3"),
+        &[current_hash_value[3].clone(), d]
+    )?;
+
+    let h4 = e.compute(
+        cs.namespace(|| "deferred h4 computation"),
+        &[current_hash_value[4].clone()]
+    )?;
+
+    let h5 = UInt32::addmany(
+        cs.namespace(|| "new h5"),
+        &[current_hash_value[5].clone(), f]
+    )?;
+
+    let h6 = UInt32::addmany(
+        cs.namespace(|| "new h6"),
+        &[current_hash_value[6].clone(), g]
+    )?;
+
+    let h7 = UInt32::addmany(
+        cs.namespace(|| "new h7"),
+        &[current_hash_value[7].clone(), h]
+    )?;
+
+    Ok(vec![h0, h1, h2, h3, h4, h5, h6, h7])
+}
+
+/// Applies the compression function to the state natively, for a block
+/// of 512 bits.
+pub fn native_compression(state: &mut [u32; 8], block: &[bool]) {
+    assert_eq!(block.len(), BLOCK_BITS);
+
+    let mut w: Vec<u32> = block.chunks(32).map(|word| word.iter().fold(0, |acc, &b| (acc << 1) | b as u32)).collect();
+    for i in 16..64 {
+        let s0 = w[i-15].rotate_right(7) ^ w[i-15].rotate_right(18) ^ (w[i-15] >> 3);
+        let s1 = w[i-2].rotate_right(17) ^ w[i-2].rotate_right(19) ^ (w[i-2] >> 10);
+        let next = w[i-16].wrapping_add(s0).wrapping_add(w[i-7]).wrapping_add(s1);
+        w.push(next);
+    }
+
+    let mut v = *state;
+    for (w, &k) in w.iter().zip(ROUND_CONSTANTS.iter()) {
+        let s1 = v[4].rotate_right(6) ^ v[4].rotate_right(11) ^ v[4].rotate_right(25);
+        let ch = (v[4] & v[5]) ^ (!v[4] & v[6]);
+        let temp1 = v[7].wrapping_add(s1).wrapping_add(ch).wrapping_add(k).wrapping_add(*w);
+        let s0 = v[0].rotate_right(2) ^ v[0].rotate_right(13) ^ v[0].rotate_right(22);
+        let maj = (v[0] & v[1]) ^ (v[0] & v[2]) ^ (v[1] & v[2]);
+        let temp2 = s0.wrapping_add(maj);
+
+        v = [temp1.wrapping_add(temp2), v[0], v[1], v[2], v[3].wrapping_add(temp1), v[4], v[5], v[6]];
+    }
+
+    for (s, v) in state.iter_mut().zip(v.iter()) {
+        *s = s.wrapping_add(*v);
+    }
+}
+
+/// Computes the same digest as `sha256` natively, for a message of
+/// any bit length.
+pub fn native_sha256(message: &[bool]) -> Vec<bool> {
+    let mut padded = message.to_vec();
+    padded.push(true);
+    while !(padded.len() + 64).is_multiple_of(BLOCK_BITS) {
+        padded.push(false);
+    }
+    padded.extend((0..64).rev().map(|i| ((message.len() as u64) >> i) & 1 == 1));
+
+    let mut state = IV;
+    for block in padded.chunks(BLOCK_BITS) {
+        native_compression(&mut state, block);
+    }
+
+    state.iter().flat_map(|&word| (0..32).rev().map(move |i| (word >> i) & 1 == 1)).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bls12_381::{Bls12, Fr};
+    use crate::pairing::ff::Field;
+    use crate::gadgets::boolean::AllocatedBit;
+    use crate::r1cs::RecordingSystem;
+
+    use rand::{Rng, thread_rng};
+
+    fn bytes_to_bits(bytes: &[u8]) -> Vec<bool> {
+        bytes.iter().flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1 == 1)).collect()
+    }
+
+    fn hex_to_bits(hex: &str) -> Vec<bool> {
+        let bytes: Vec<u8> = (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i+2], 16).unwrap()).collect();
+
+        bytes_to_bits(&bytes)
+    }
+
+    fn alloc_bits<CS: ConstraintSystem<Bls12>>(mut cs: CS, bits: &[bool]) -> Vec<Boolean> {
+        bits.iter().enumerate().map(|(i, &b)| {
+            Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("input bit {}", i)), Some(b)).unwrap())
+        }).collect()
+    }
+
+    fn check_sha256(message: &[bool], expected: &[bool]) {
+        let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+        let input = alloc_bits(cs.namespace(|| "input"), message);
+
+        let digest = sha256(cs.namespace(|| "sha256"), &input).unwrap();
+        let digest: Vec<bool> = digest.iter().map(|b| b.get_value().unwrap()).collect();
+        assert_eq!(digest, expected);
+
+        cs.into_r1cs().check_satisfied().unwrap();
+    }
+
+    #[test]
+    fn test_sha256_test_vectors() {
+        let vectors: [(&[u8], &str); 3] = [
+            (b"abc", "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+            (b"", "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+            (b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq", "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1")
+        ];
+
+        for &(message, digest) in vectors.iter() {
+            let message = bytes_to_bits(message);
+            let expected = hex_to_bits(digest);
+
+            assert_eq!(native_sha256(&message), expected);
+            check_sha256(&message, &expected);
+        }
+    }
+
+    #[test]
+    fn test_sha256_bit_lengths() {
+        let rng = &mut thread_rng();
+
+        let mut lengths = vec![1, 7, 447, 448, 511, 512, 513];
+        lengths.extend((0..3).map(|_| rng.gen_range(0, 1100)));
+
+        for len in lengths {
+            let message: Vec<bool> = (0..len).map(|_| rng.gen()).collect();
+
+            check_sha256(&message, &native_sha256(&message));
+        }
+    }
+
+    #[test]
+    fn test_sha256_constant_input() {
+        let message = bytes_to_bits(b"abc");
+        let input: Vec<_> = message.iter().map(|&b| Boolean::constant(b)).collect();
+
+        let mut cs = RecordingSystem::<Bls12>::new();
+        let digest = sha256(&mut cs, &input).unwrap();
+
+        assert!(digest.iter().all(|b| b.is_constant()));
+        let digest: Vec<bool> = digest.iter().map(|b| b.get_value().unwrap()).collect();
+        assert_eq!(digest, native_sha256(&message));
+
+        assert_eq!(cs.into_r1cs().num_constraints(), 0);
+    }
+
+    #[test]
+    fn test_sha256_block_constraints() {
+        let rng = &mut thread_rng();
+        let message: Vec<bool> = (0..BLOCK_BITS).map(|_| rng.gen()).collect();
+
+        let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+        let input = alloc_bits(cs.namespace(|| "input"), &message);
+        sha256_block_no_padding(cs.namespace(|| "block"), &input).unwrap();
+
+        let mut r1cs = cs.into_r1cs();
+        r1cs.check_satisfied().unwrap();
+        assert_eq!(r1cs.num_constraints() - BLOCK_BITS, 25996);
+
+        // Changing a bit of the input breaks the message schedule
+        let index = r1cs.aux_names.iter().position(|name| name == "input/input bit 0/boolean").unwrap();
+        let assignment = r1cs.aux_assignment.as_mut().unwrap();
+        let flipped = if message[0] { Fr::zero() } else { Fr::one() };
+        assignment[index] = flipped;
+        assert!(r1cs.check_satisfied().is_err());
+    }
+}
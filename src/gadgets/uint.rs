@@ -0,0 +1,766 @@
+//! Fixed-width unsigned integers represented by their bits.
+//!
+//! Bitwise operations, rotations and shifts work bit by bit, so they are
+//! free for constant bits and cost at most one constraint per bit
+//! otherwise. Modular addition of several operands packs all of them into a
+//! single linear combination and unpacks the sum once, including the
+//! carries, so its cost depends on the width and not on the operand count.
+//! All three widths share one implementation.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::pairing::ff::{
+    Field,
+    PrimeField
+};
+
+use crate::{
+    SynthesisError,
+    ConstraintSystem,
+    LinearCombination
+};
+
+use super::Allocate;
+use super::boolean::{
+    AllocatedBit,
+    Boolean
+};
+
+fn field_of_u64<F: PrimeField>(value: u64) -> F {
+    F::from_repr(F::Repr::from(value)).expect("every u64 is in the field")
+}
+
+macro_rules! uint_impl {
+    ($name:ident, $native:ty, $bits:expr, $doc:expr) => {
+        #[doc = $doc]
+        #[derive(Clone)]
+        pub struct $name {
+            // Least significant bit first
+            bits: Vec<Boolean>,
+            value: Option<$native>
+        }
+
+        impl $name {
+            /// Construct a constant from a known value.
+            pub fn constant(value: $native) -> Self {
+                let bits = (0..$bits).map(|i: usize| Boolean::constant((value >> i) & 1 == 1)).collect();
+
+                $name {
+                    bits: bits,
+                    value: Some(value)
+                }
+            }
+
+            /// Allocate a variable for every bit of the value.
+            pub fn alloc<E, CS>(
+                mut cs: CS,
+                value: Option<$native>
+            ) -> Result<Self, SynthesisError>
+                where E: Engine, CS: ConstraintSystem<E>
+            {
+                let bits = (0..$bits).map(|i: usize| {
+                    AllocatedBit::alloc(
+                        cs.namespace(|| format!("allocated bit {}", i)),
+                        value.map(|v| (v >> i) & 1 == 1)
+                    ).map(Boolean::from)
+                }).collect::<Result<Vec<_>, SynthesisError>>()?;
+
+                Ok($name {
+                    bits: bits,
+                    value: value
+                })
+            }
+
+            pub fn get_value(&self) -> Option<$native> {
+                self.value
+            }
+
+            pub fn is_constant(&self) -> bool {
+                self.bits.iter().all(|b| b.is_constant())
+            }
+
+            /// The bits in little-endian order.
+            pub fn into_bits(&self) -> Vec<Boolean> {
+                self.bits.clone()
+            }
+
+            /// The bits in big-endian order.
+            pub fn into_bits_be(&self) -> Vec<Boolean> {
+                self.bits.iter().rev().cloned().collect()
+            }
+
+            /// Turns little-endian bits into an integer.
+            pub fn from_bits(bits: &[Boolean]) -> Self {
+                assert_eq!(bits.len(), $bits);
+
+                let mut value = Some(0 as $native);
+                for b in bits.iter().rev() {
+                    value = match (value, b.get_value()) {
+                        (Some(v), Some(b)) => Some((v << 1) | (b as $native)),
+                        _ => None
+                    };
+                }
+
+                $name {
+                    bits: bits.to_vec(),
+                    value: value
+                }
+            }
+
+            /// Turns big-endian bits into an integer.
+            pub fn from_bits_be(bits: &[Boolean]) -> Self {
+                let bits: Vec<_> = bits.iter().rev().cloned().collect();
+
+                Self::from_bits(&bits)
+            }
+
+            /// The bytes, least significant first.
+            pub fn to_bytes_le(&self) -> Vec<UInt8> {
+                self.bits.chunks(8).map(UInt8::from_bits).collect()
+            }
+
+            /// The bytes, most significant first.
+            pub fn to_bytes_be(&self) -> Vec<UInt8> {
+                let mut bytes = self.to_bytes_le();
+                bytes.reverse();
+
+                bytes
+            }
+
+            pub fn from_bytes_le(bytes: &[UInt8]) -> Self {
+                assert_eq!(bytes.len() * 8, $bits);
+
+                let bits: Vec<_> = bytes.iter().flat_map(|byte| byte.into_bits()).collect();
+
+                Self::from_bits(&bits)
+            }
+
+            pub fn from_bytes_be(bytes: &[UInt8]) -> Self {
+                let bytes: Vec<_> = bytes.iter().rev().cloned().collect();
+
+                Self::from_bytes_le(&bytes)
+            }
+
+            pub fn rotr(&self, by: usize) -> Self {
+                let by = by % $bits;
+
+                let new_bits = self.bits.iter()
+                                        .skip(by)
+                                        .chain(self.bits.iter())
+                                        .take($bits)
+                                        .cloned()
+                                        .collect();
+
+                $name {
+                    bits: new_bits,
+                    value: self.value.map(|v| v.rotate_right(by as u32))
+                }
+            }
+
+            pub fn rotl(&self, by: usize) -> Self {
+                self.rotr($bits - by % $bits)
+            }
+
+            /// Shifts towards the least significant bit, filling in zeros.
+            pub fn shr(&self, by: usize) -> Self {
+                let by = if by > $bits { $bits } else { by };
+
+                let new_bits = self.bits.iter()
+                                        .skip(by)
+                                        .cloned()
+                                        .chain((0..by).map(|_| Boolean::constant(false)))
+                                        .collect();
+
+                $name {
+                    bits: new_bits,
+                    value: self.value.map(|v| v.checked_shr(by as u32).unwrap_or(0))
+                }
+            }
+
+            /// Shifts towards the most significant bit, filling in zeros.
+            pub fn shl(&self, by: usize) -> Self {
+                let by = if by > $bits { $bits } else { by };
+
+                let new_bits = (0..by).map(|_| Boolean::constant(false))
+                                      .chain(self.bits.iter().take($bits - by).cloned())
+                                      .collect();
+
+                $name {
+                    bits: new_bits,
+                    value: self.value.map(|v| v.checked_shl(by as u32).unwrap_or(0))
+                }
+            }
+
+            pub fn not(&self) -> Self {
+                $name {
+                    bits: self.bits.iter().map(|b| b.not()).collect(),
+                    value: self.value.map(|v| !v)
+                }
+            }
+
+            /// XOR this integer with another one.
+            pub fn xor<E, CS>(
+                &self,
+                mut cs: CS,
+                other: &Self
+            ) -> Result<Self, SynthesisError>
+                where E: Engine, CS: ConstraintSystem<E>
+            {
+                let new_value = match (self.value, other.value) {
+                    (Some(a), Some(b)) => Some(a ^ b),
+                    _ => None
+                };
+
+                let bits = self.bits.iter()
+                                    .zip(other.bits.iter())
+                                    .enumerate()
+                                    .map(|(i, (a, b))| {
+                                        Boolean::xor(cs.namespace(|| format!("xor of bit {}", i)), a, b)
+                                    })
+                                    .collect::<Result<_, _>>()?;
+
+                Ok($name {
+                    bits: bits,
+                    value: new_value
+                })
+            }
+
+            /// AND this integer with another one.
+            pub fn and<E, CS>(
+                &self,
+                mut cs: CS,
+                other: &Self
+            ) -> Result<Self, SynthesisError>
+                where E: Engine, CS: ConstraintSystem<E>
+            {
+                let new_value = match (self.value, other.value) {
+                    (Some(a), Some(b)) => Some(a & b),
+                    _ => None
+                };
+
+                let bits = self.bits.iter()
+                                    .zip(other.bits.iter())
+                                    .enumerate()
+                                    .map(|(i, (a, b))| {
+                                        Boolean::and(cs.namespace(|| format!("and of bit {}", i)), a, b)
+                                    })
+                                    .collect::<Result<_, _>>()?;
+
+                Ok($name {
+                    bits: bits,
+                    value: new_value
+                })
+            }
+
+            fn triop<E, CS, F, U>(
+                mut cs: CS,
+                a: &Self,
+                b: &Self,
+                c: &Self,
+                tri_fn: F,
+                circuit_fn: U
+            ) -> Result<Self, SynthesisError>
+                where E: Engine,
+                      CS: ConstraintSystem<E>,
+                      F: Fn($native, $native, $native) -> $native,
+                      U: Fn(&mut CS, usize, &Boolean, &Boolean, &Boolean) -> Result<Boolean, SynthesisError>
+            {
+                let new_value = match (a.value, b.value, c.value) {
+                    (Some(a), Some(b), Some(c)) => Some(tri_fn(a, b, c)),
+                    _ => None
+                };
+
+                let bits = a.bits.iter()
+                                 .zip(b.bits.iter())
+                                 .zip(c.bits.iter())
+                                 .enumerate()
+                                 .map(|(i, ((a, b), c))| circuit_fn(&mut cs, i, a, b, c))
+                                 .collect::<Result<_, _>>()?;
+
+                Ok($name {
+                    bits: bits,
+                    value: new_value
+                })
+            }
+
+            /// Computes the SHA-2 choice function bit by bit, taking the
+            /// bits of `b` where `a` is set and those of `c` elsewhere.
+            pub fn ch<E, CS>(
+                cs: CS,
+                a: &Self,
+                b: &Self,
+                c: &Self
+            ) -> Result<Self, SynthesisError>
+                where E: Engine, CS: ConstraintSystem<E>
+            {
+                Self::triop(cs, a, b, c, |a, b, c| (a & b) ^ (!a & c),
+                    |cs, i, a, b, c| {
+                        Boolean::ch(cs.namespace(|| format!("ch {}", i)), a, b, c)
+                    }
+                )
+            }
+
+            /// Computes the SHA-2 majority function bit by bit.
+            pub fn maj<E, CS>(
+                cs: CS,
+                a: &Self,
+                b: &Self,
+                c: &Self
+            ) -> Result<Self, SynthesisError>
+                where E: Engine, CS: ConstraintSystem<E>
+            {
+                Self::triop(cs, a, b, c, |a, b, c| (a & b) ^ (a & c) ^ (b & c),
+                    |cs, i, a, b, c| {
+                        Boolean::maj(cs.namespace(|| format!("maj {}", i)), a, b, c)
+                    }
+                )
+            }
+
+            /// Adds all operands modulo the width. The sum is unpacked
+            /// with a single constraint, plus a boolean constraint for
+            /// every bit of the sum including the carries. Constant
+            /// operands are folded into the sum for free, and if all of
+            /// them are constant no constraints are needed at all.
+            pub fn addmany<E, CS>(
+                mut cs: CS,
+                operands: &[Self]
+            ) -> Result<Self, SynthesisError>
+                where E: Engine, CS: ConstraintSystem<E>
+            {
+                assert!(!operands.is_empty());
+
+                // Compute the maximum value of the sum so we allocate enough bits for the result
+                let mut max_value = (operands.len() as u128) * (<$native>::max_value() as u128);
+                let result_bits_len = 128 - max_value.leading_zeros();
+                assert!(result_bits_len < E::Fr::CAPACITY, "too many operands for the field");
+
+                // Keep track of the resulting value
+                let mut result_value = Some(0u128);
+
+                // This is a linear combination that we will enforce to equal the output
+                let mut lc = LinearCombination::zero();
+
+                let mut all_constants = true;
+
+                // Iterate over the operands
+                for op in operands {
+                    // Accumulate the value
+                    result_value = match (result_value, op.value) {
+                        (Some(sum), Some(val)) => Some(sum + val as u128),
+                        _ => None
+                    };
+
+                    // Iterate over each bit of the operand and add the operand to
+                    // the linear combination
+                    let mut coeff = E::Fr::one();
+                    for bit in &op.bits {
+                        lc = lc + &bit.lc(CS::one(), coeff);
+
+                        all_constants &= bit.is_constant();
+
+                        coeff.double();
+                    }
+                }
+
+                // The value of the actual result is modulo the width
+                let modular_value = result_value.map(|v| v as $native);
+
+                if all_constants {
+                    if let Some(value) = modular_value {
+                        // We can just return a constant, rather than
+                        // unpacking the result into allocated bits.
+                        return Ok($name::constant(value));
+                    }
+                }
+
+                // Storage area for the resulting bits
+                let mut result_bits = vec![];
+
+                // Linear combination representing the output,
+                // for comparison with the sum of the operands
+                let mut result_lc = LinearCombination::zero();
+
+                // Allocate each bit of the result
+                let mut coeff = E::Fr::one();
+                let mut i = 0;
+                while max_value != 0 {
+                    // Allocate the bit
+                    let b = AllocatedBit::alloc(
+                        cs.namespace(|| format!("result bit {}", i)),
+                        result_value.map(|v| (v >> i) & 1 == 1)
+                    )?;
+
+                    // Add this bit to the result combination
+                    result_lc = result_lc + (coeff, b.get_variable());
+
+                    result_bits.push(b.into());
+
+                    max_value >>= 1;
+                    i += 1;
+                    coeff.double();
+                }
+
+                // Enforce equality between the sum and result
+                cs.enforce(
+                    || "modular addition",
+                    |zero| zero + &lc,
+                    |zero| zero + CS::one(),
+                    |zero| zero + &result_lc
+                );
+
+                // Discard carry bits that we don't care about
+                result_bits.truncate($bits);
+
+                Ok($name {
+                    bits: result_bits,
+                    value: modular_value
+                })
+            }
+
+            /// Returns whether `a < b`. The difference `a - b + 2^width`
+            /// is unpacked into `width + 1` bits, and the top one is set
+            /// iff `a >= b`. This costs `width + 2` constraints, unless
+            /// both integers are constant.
+            pub fn less_than<E, CS>(
+                mut cs: CS,
+                a: &Self,
+                b: &Self
+            ) -> Result<Boolean, SynthesisError>
+                where E: Engine, CS: ConstraintSystem<E>
+            {
+                if let (Some(x), Some(y)) = (a.value, b.value) {
+                    if a.is_constant() && b.is_constant() {
+                        return Ok(Boolean::constant(x < y));
+                    }
+                }
+
+                let difference_value = match (a.value, b.value) {
+                    (Some(x), Some(y)) => Some((x as u128) + (1u128 << $bits) - (y as u128)),
+                    _ => None
+                };
+
+                let mut difference = LinearCombination::zero();
+                let mut coeff = E::Fr::one();
+                for (x, y) in a.bits.iter().zip(b.bits.iter()) {
+                    difference = difference + &x.lc(CS::one(), coeff) - &y.lc(CS::one(), coeff);
+
+                    coeff.double();
+                }
+                difference = difference + (coeff, CS::one());
+
+                let mut unpacked = LinearCombination::zero();
+                let mut coeff = E::Fr::one();
+                let mut top = None;
+                for i in 0..($bits + 1) {
+                    let bit = AllocatedBit::alloc(
+                        cs.namespace(|| format!("difference bit {}", i)),
+                        difference_value.map(|v| (v >> i) & 1 == 1)
+                    )?;
+
+                    unpacked = unpacked + (coeff, bit.get_variable());
+                    top = Some(bit);
+
+                    coeff.double();
+                }
+
+                cs.enforce(
+                    || "difference",
+                    |zero| zero + &difference,
+                    |zero| zero + CS::one(),
+                    |zero| zero + &unpacked
+                );
+
+                Ok(Boolean::from(top.expect("at least one bit")).not())
+            }
+
+            /// Returns whether `a <= b`, for the same cost as `less_than`.
+            pub fn less_than_or_equal<E, CS>(
+                cs: CS,
+                a: &Self,
+                b: &Self
+            ) -> Result<Boolean, SynthesisError>
+                where E: Engine, CS: ConstraintSystem<E>
+            {
+                Ok(Self::less_than(cs, b, a)?.not())
+            }
+        }
+
+        impl<E: Engine> Allocate<E> for $name {
+            type Value = $native;
+
+            fn allocate<CS>(
+                cs: CS,
+                value: Option<&$native>
+            ) -> Result<Self, SynthesisError>
+                where CS: ConstraintSystem<E>
+            {
+                $name::alloc(cs, value.cloned())
+            }
+
+            fn get_value(&self) -> Option<$native> {
+                self.value
+            }
+
+            /// Exposes the integer as a single public input.
+            fn inputize<CS>(
+                &self,
+                mut cs: CS
+            ) -> Result<(), SynthesisError>
+                where CS: ConstraintSystem<E>
+            {
+                let input = cs.alloc_input(|| "input", || {
+                    let value = self.value.ok_or(SynthesisError::AssignmentMissing)?;
+
+                    Ok(field_of_u64(value as u64))
+                })?;
+
+                let mut packed = LinearCombination::zero();
+                let mut coeff = E::Fr::one();
+                for bit in self.bits.iter() {
+                    packed = packed + &bit.lc(CS::one(), coeff);
+
+                    coeff.double();
+                }
+
+                cs.enforce(
+                    || "packing constraint",
+                    |zero| zero + &packed,
+                    |zero| zero + CS::one(),
+                    |zero| zero + input
+                );
+
+                Ok(())
+            }
+        }
+    }
+}
+
+uint_impl!(UInt8, u8, 8, "Represents an interpretation of 8 `Boolean` objects as an unsigned integer.");
+uint_impl!(UInt32, u32, 32, "Represents an interpretation of 32 `Boolean` objects as an unsigned integer.");
+uint_impl!(UInt64, u64, 64, "Represents an interpretation of 64 `Boolean` objects as an unsigned integer.");
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bls12_381::{Bls12, Fr};
+    use crate::r1cs::RecordingSystem;
+
+    use rand::{Rng, thread_rng};
+
+    fn check_bits32(a: &UInt32, expected: u32) {
+        assert_eq!(a.get_value(), Some(expected));
+        for (i, bit) in a.into_bits().iter().enumerate() {
+            assert_eq!(bit.get_value(), Some((expected >> i) & 1 == 1));
+        }
+    }
+
+    #[test]
+    fn test_uint32_from_bits() {
+        let rng = &mut thread_rng();
+
+        for _ in 0..100 {
+            let v: u32 = rng.gen();
+
+            let bits: Vec<_> = (0..32).map(|i| Boolean::constant((v >> i) & 1 == 1)).collect();
+            let from_bits = UInt32::from_bits(&bits);
+            check_bits32(&from_bits, v);
+
+            let from_be = UInt32::from_bits_be(&from_bits.into_bits_be());
+            check_bits32(&from_be, v);
+        }
+    }
+
+    #[test]
+    fn test_uint32_bytes() {
+        let a = UInt32::constant(0x01020304);
+
+        let le: Vec<_> = a.to_bytes_le().iter().map(|b| b.get_value().unwrap()).collect();
+        assert_eq!(le, vec![4, 3, 2, 1]);
+        let be: Vec<_> = a.to_bytes_be().iter().map(|b| b.get_value().unwrap()).collect();
+        assert_eq!(be, vec![1, 2, 3, 4]);
+
+        check_bits32(&UInt32::from_bytes_le(&a.to_bytes_le()), 0x01020304);
+        check_bits32(&UInt32::from_bytes_be(&a.to_bytes_be()), 0x01020304);
+        check_bits32(&UInt32::from_bytes_le(&a.to_bytes_be()), 0x04030201);
+
+        let b = UInt64::constant(0x0102030405060708);
+        let be: Vec<_> = b.to_bytes_be().iter().map(|b| b.get_value().unwrap()).collect();
+        assert_eq!(be, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(UInt64::from_bytes_be(&b.to_bytes_be()).get_value(), Some(0x0102030405060708));
+    }
+
+    #[test]
+    fn test_uint32_bitwise() {
+        let rng = &mut thread_rng();
+
+        for _ in 0..20 {
+            let (a, b, c): (u32, u32, u32) = (rng.gen(), rng.gen(), rng.gen());
+
+            let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+            let a_bit = UInt32::alloc(cs.namespace(|| "a"), Some(a)).unwrap();
+            let b_bit = UInt32::alloc(cs.namespace(|| "b"), Some(b)).unwrap();
+            let c_bit = UInt32::constant(c);
+
+            let xor = a_bit.xor(cs.namespace(|| "xor"), &b_bit).unwrap();
+            check_bits32(&xor, a ^ b);
+            let and = xor.and(cs.namespace(|| "and"), &c_bit).unwrap();
+            check_bits32(&and, (a ^ b) & c);
+            check_bits32(&and.not(), !((a ^ b) & c));
+
+            for i in 0..40 {
+                check_bits32(&a_bit.rotr(i), a.rotate_right(i as u32));
+                check_bits32(&a_bit.rotl(i), a.rotate_left(i as u32));
+                check_bits32(&a_bit.shr(i), a.checked_shr(i as u32).unwrap_or(0));
+                check_bits32(&a_bit.shl(i), a.checked_shl(i as u32).unwrap_or(0));
+            }
+
+            let r1cs = cs.into_r1cs();
+            r1cs.check_satisfied().unwrap();
+
+            // 64 boolean constraints and one per xor'd bit; the
+            // and with a constant is free
+            assert_eq!(r1cs.num_constraints(), 64 + 32);
+        }
+    }
+
+    #[test]
+    fn test_uint32_ch_maj() {
+        let rng = &mut thread_rng();
+
+        for _ in 0..20 {
+            let (a, b, c): (u32, u32, u32) = (rng.gen(), rng.gen(), rng.gen());
+
+            let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+            let a_bit = UInt32::alloc(cs.namespace(|| "a"), Some(a)).unwrap();
+            let b_bit = UInt32::alloc(cs.namespace(|| "b"), Some(b)).unwrap();
+            let c_bit = UInt32::alloc(cs.namespace(|| "c"), Some(c)).unwrap();
+
+            let ch = UInt32::ch(cs.namespace(|| "ch"), &a_bit, &b_bit, &c_bit).unwrap();
+            check_bits32(&ch, (a & b) ^ (!a & c));
+            let maj = UInt32::maj(cs.namespace(|| "maj"), &a_bit, &b_bit, &c_bit).unwrap();
+            check_bits32(&maj, (a & b) ^ (a & c) ^ (b & c));
+
+            // Constant operands are folded bit by bit
+            let folded = UInt32::maj(cs.namespace(|| "folded"), &a_bit, &UInt32::constant(b), &UInt32::constant(c)).unwrap();
+            check_bits32(&folded, (a & b) ^ (a & c) ^ (b & c));
+
+            let r1cs = cs.into_r1cs();
+            r1cs.check_satisfied().unwrap();
+            assert_eq!(r1cs.constraints.iter().filter(|c| c.annotation.starts_with("ch/")).count(), 32);
+            assert_eq!(r1cs.constraints.iter().filter(|c| c.annotation.starts_with("maj/")).count(), 64);
+            assert_eq!(r1cs.constraints.iter().filter(|c| c.annotation.starts_with("folded/")).count(), 0);
+        }
+    }
+
+    #[test]
+    fn test_uint32_addmany() {
+        let rng = &mut thread_rng();
+
+        for _ in 0..20 {
+            let (a, b, c, d): (u32, u32, u32, u32) = (rng.gen(), rng.gen(), rng.gen(), rng.gen());
+
+            let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+            let a_bit = UInt32::alloc(cs.namespace(|| "a"), Some(a)).unwrap();
+            let b_bit = UInt32::alloc(cs.namespace(|| "b"), Some(b)).unwrap();
+            let c_bit = UInt32::constant(c);
+            let d_bit = UInt32::alloc(cs.namespace(|| "d"), Some(d)).unwrap();
+
+            let r = a_bit.xor(cs.namespace(|| "xor"), &b_bit).unwrap();
+            let r = UInt32::addmany(cs.namespace(|| "addition"), &[r, c_bit, d_bit]).unwrap();
+            check_bits32(&r, (a ^ b).wrapping_add(c).wrapping_add(d));
+
+            let mut r1cs = cs.into_r1cs();
+            r1cs.check_satisfied().unwrap();
+
+            // The sum of three operands takes 34 bits
+            let addition = r1cs.constraints.iter().filter(|c| c.annotation.starts_with("addition/")).count();
+            assert_eq!(addition, 34 + 1);
+
+            // Flipping a bit of the result breaks the sum
+            let i = r1cs.aux_names.iter().position(|name| name == "addition/result bit 7/boolean").unwrap();
+            let mut flipped = Fr::one();
+            flipped.sub_assign(&r1cs.aux_assignment.as_ref().unwrap()[i]);
+            r1cs.aux_assignment.as_mut().unwrap()[i] = flipped;
+            assert!(r1cs.check_satisfied().is_err());
+        }
+    }
+
+    #[test]
+    fn test_uint_addmany_constants() {
+        let rng = &mut thread_rng();
+
+        for _ in 0..100 {
+            let (a, b, c): (u64, u64, u64) = (rng.gen(), rng.gen(), rng.gen());
+
+            let mut cs = RecordingSystem::<Bls12>::new();
+            let r = UInt64::addmany(&mut cs, &[UInt64::constant(a), UInt64::constant(b), UInt64::constant(c)]).unwrap();
+
+            assert!(r.is_constant());
+            assert_eq!(r.get_value(), Some(a.wrapping_add(b).wrapping_add(c)));
+            assert_eq!(cs.into_r1cs().num_constraints(), 0);
+        }
+    }
+
+    #[test]
+    fn test_uint64_addmany() {
+        let rng = &mut thread_rng();
+
+        for _ in 0..10 {
+            let values: Vec<u64> = (0..5).map(|_| rng.gen()).collect();
+
+            let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+            let operands: Vec<_> = values.iter().enumerate().map(|(i, &v)| {
+                UInt64::alloc(cs.namespace(|| format!("operand {}", i)), Some(v)).unwrap()
+            }).collect();
+
+            let r = UInt64::addmany(cs.namespace(|| "addition"), &operands).unwrap();
+            let expected = values.iter().fold(0u64, |acc, &v| acc.wrapping_add(v));
+            assert_eq!(r.get_value(), Some(expected));
+
+            cs.into_r1cs().check_satisfied().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_uint_comparison() {
+        let rng = &mut thread_rng();
+        let random: u32 = rng.gen();
+        let pairs = [(0, 0), (0, 1), (1, 0), (u32::MAX, 0), (0, u32::MAX), (random, random), (random, random.wrapping_add(1))];
+
+        for &(a, b) in pairs.iter() {
+            let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+            let a_bit = UInt32::alloc(cs.namespace(|| "a"), Some(a)).unwrap();
+            let b_bit = UInt32::alloc(cs.namespace(|| "b"), Some(b)).unwrap();
+            let b_constant = UInt32::constant(b);
+
+            let lt = UInt32::less_than(cs.namespace(|| "lt"), &a_bit, &b_bit).unwrap();
+            let le = UInt32::less_than_or_equal(cs.namespace(|| "le"), &a_bit, &b_constant).unwrap();
+            assert_eq!(lt.get_value(), Some(a < b));
+            assert_eq!(le.get_value(), Some(a <= b));
+
+            let r1cs = cs.into_r1cs();
+            r1cs.check_satisfied().unwrap();
+            assert_eq!(r1cs.constraints.iter().filter(|c| c.annotation.starts_with("lt/")).count(), 34);
+
+            let folded = UInt32::less_than(RecordingSystem::<Bls12>::new(), &UInt32::constant(a), &b_constant).unwrap();
+            assert!(folded.is_constant());
+            assert_eq!(folded.get_value(), Some(a < b));
+        }
+    }
+
+    #[test]
+    fn test_uint_inputize() {
+        let mut cs = RecordingSystem::<Bls12>::new_with_witness();
+        let a = UInt64::alloc(cs.namespace(|| "a"), Some(u64::MAX)).unwrap();
+        a.inputize(cs.namespace(|| "input")).unwrap();
+
+        let mut r1cs = cs.into_r1cs();
+        r1cs.check_satisfied().unwrap();
+
+        r1cs.input_assignment.as_mut().unwrap()[1] = field_of_u64(u64::MAX - 1);
+        assert!(r1cs.check_satisfied().is_err());
+    }
+}
@@ -0,0 +1,637 @@
+use rand::Rng;
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::pairing::ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr,
+    ScalarEngine
+};
+
+use super::{
+    ParameterSource,
+    Proof,
+    ProvingAssignment,
+    PreparedProver
+};
+
+use crate::{
+    SynthesisError,
+    ErrorLocation,
+    Circuit,
+    ConstraintSystem,
+    LinearCombination,
+    Variable,
+    Index
+};
+
+use crate::domain::{
+    Scalar
+};
+
+use crate::source::{
+    DensityTracker
+};
+
+use crate::worker::{
+    Worker
+};
+
+#[cfg(feature = "witness")]
+use blake2_rfc::blake2s::Blake2s;
+use byteorder::{BigEndian, WriteBytesExt};
+
+/// Sparse matrix in compressed sparse row form. Columns index the
+/// concatenation of the input and auxillary assignments.
+#[derive(Clone)]
+pub struct SparseMatrix<E: Engine> {
+    row_starts: Vec<usize>,
+    columns: Vec<usize>,
+    coeffs: Vec<E::Fr>
+}
+
+impl<E: Engine> SparseMatrix<E> {
+    fn from_rows(rows: Vec<Vec<(Index, E::Fr)>>, num_inputs: usize) -> Self {
+        let num_terms = rows.iter().map(|r| r.len()).sum();
+
+        let mut row_starts = Vec::with_capacity(rows.len() + 1);
+        let mut columns = Vec::with_capacity(num_terms);
+        let mut coeffs = Vec::with_capacity(num_terms);
+
+        row_starts.push(0);
+        for row in rows.into_iter() {
+            for (index, coeff) in row.into_iter() {
+                columns.push(match index {
+                    Index::Input(i) => i,
+                    Index::Aux(i) => num_inputs + i
+                });
+                coeffs.push(coeff);
+            }
+            row_starts.push(columns.len());
+        }
+
+        SparseMatrix {
+            row_starts: row_starts,
+            columns: columns,
+            coeffs: coeffs
+        }
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.row_starts.len() - 1
+    }
+
+    pub fn num_nonzero(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// Returns the columns and coefficients of a single row.
+    pub fn row(&self, row: usize) -> (&[usize], &[E::Fr]) {
+        let range = self.row_starts[row]..self.row_starts[row + 1];
+
+        (&self.columns[range.clone()], &self.coeffs[range])
+    }
+
+    fn mark_density(
+        &self,
+        num_inputs: usize,
+        mut input_density: Option<&mut DensityTracker>,
+        aux_density: &mut DensityTracker
+    )
+    {
+        for &column in self.columns.iter() {
+            if column < num_inputs {
+                if let Some(ref mut v) = input_density {
+                    v.inc(column);
+                }
+            } else {
+                aux_density.inc(column - num_inputs);
+            }
+        }
+    }
+
+    #[cfg(feature = "witness")]
+    fn hash_into(&self, h: &mut Blake2s) {
+        let mut buf = vec![];
+        for &start in self.row_starts.iter() {
+            buf.write_u64::<BigEndian>(start as u64).unwrap();
+        }
+        for &column in self.columns.iter() {
+            buf.write_u64::<BigEndian>(column as u64).unwrap();
+        }
+        for coeff in self.coeffs.iter() {
+            coeff.into_repr().write_be(&mut buf).unwrap();
+        }
+
+        h.update(&buf);
+    }
+
+    /// Multiplies the matrix by the assignment vector in parallel.
+    fn mul_vec(
+        &self,
+        worker: &Worker,
+        input_assignment: &[E::Fr],
+        aux_assignment: &[E::Fr]
+    ) -> Vec<Scalar<E>>
+    {
+        let num_inputs = input_assignment.len();
+        let mut result = vec![Scalar::<E>(E::Fr::zero()); self.num_rows()];
+
+        worker.scope(result.len(), |scope, chunk| {
+            for (i, result) in result.chunks_mut(chunk).enumerate() {
+                scope.spawn(move |_| {
+                    for (j, result) in result.iter_mut().enumerate() {
+                        let (columns, coeffs) = self.row(i * chunk + j);
+
+                        let mut acc = E::Fr::zero();
+                        for (&column, coeff) in columns.iter().zip(coeffs.iter()) {
+                            let mut tmp = if column < num_inputs {
+                                input_assignment[column]
+                            } else {
+                                aux_assignment[column - num_inputs]
+                            };
+
+                            if *coeff != E::Fr::one() {
+                                tmp.mul_assign(coeff);
+                            }
+                            acc.add_assign(&tmp);
+                        }
+
+                        result.0 = acc;
+                    }
+                });
+            }
+        });
+
+        result
+    }
+}
+
+/// A circuit synthesized once into its A, B and C matrices, including the
+/// `input * 0 = 0` constraints the prover appends for every public input.
+/// Proofs for any witness of the same circuit can be created from it
+/// without building a single `LinearCombination`.
+#[derive(Clone)]
+pub struct CompiledCircuit<E: Engine> {
+    num_inputs: usize,
+    num_aux: usize,
+
+    a: SparseMatrix<E>,
+    b: SparseMatrix<E>,
+    c: SparseMatrix<E>,
+
+    // Densities of queries, derived from the matrix structure
+    a_aux_density: DensityTracker,
+    b_input_density: DensityTracker,
+    b_aux_density: DensityTracker
+}
+
+impl<E: Engine> CompiledCircuit<E> {
+    /// Synthesizes the circuit without invoking any of the
+    /// assignment closures and stores its matrices.
+    pub fn compile<C: Circuit<E>>(circuit: C) -> Result<Self, SynthesisError> {
+        let mut assembly = CompilingAssembly {
+            num_inputs: 0,
+            num_aux: 0,
+            a: vec![],
+            b: vec![],
+            c: vec![]
+        };
+
+        assembly.alloc_input(|| "", || Ok(E::Fr::one()))?;
+
+        circuit.synthesize(&mut assembly)?;
+
+        for i in 0..assembly.num_inputs {
+            assembly.enforce(|| "",
+                |lc| lc + Variable(Index::Input(i)),
+                |lc| lc,
+                |lc| lc,
+            );
+        }
+
+        let num_inputs = assembly.num_inputs;
+        let num_aux = assembly.num_aux;
+
+        let a = SparseMatrix::from_rows(assembly.a, num_inputs);
+        let b = SparseMatrix::from_rows(assembly.b, num_inputs);
+        let c = SparseMatrix::from_rows(assembly.c, num_inputs);
+
+        let new_density = |len: usize| {
+            let mut density = DensityTracker::new();
+            for _ in 0..len {
+                density.add_element();
+            }
+
+            density
+        };
+
+        // Inputs have full density in the A query
+        // because of the x * 0 = 0 constraints
+        let mut a_aux_density = new_density(num_aux);
+        a.mark_density(num_inputs, None, &mut a_aux_density);
+
+        let mut b_input_density = new_density(num_inputs);
+        let mut b_aux_density = new_density(num_aux);
+        b.mark_density(num_inputs, Some(&mut b_input_density), &mut b_aux_density);
+
+        Ok(CompiledCircuit {
+            num_inputs: num_inputs,
+            num_aux: num_aux,
+            a: a,
+            b: b,
+            c: c,
+            a_aux_density: a_aux_density,
+            b_input_density: b_input_density,
+            b_aux_density: b_aux_density
+        })
+    }
+
+    /// Number of inputs, including the "one" input.
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    pub fn num_aux(&self) -> usize {
+        self.num_aux
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.a.num_rows()
+    }
+
+    /// BLAKE2s digest of the circuit shape, which changes whenever
+    /// any of the matrices or the number of variables changes.
+    #[cfg(feature = "witness")]
+    pub fn digest(&self) -> [u8; 32] {
+        let mut h = Blake2s::new(32);
+        let mut sizes = vec![];
+        sizes.write_u64::<BigEndian>(self.num_inputs as u64).unwrap();
+        sizes.write_u64::<BigEndian>(self.num_aux as u64).unwrap();
+        h.update(&sizes);
+        self.a.hash_into(&mut h);
+        self.b.hash_into(&mut h);
+        self.c.hash_into(&mut h);
+
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(h.finalize().as_bytes());
+
+        digest
+    }
+
+    pub fn a(&self) -> &SparseMatrix<E> {
+        &self.a
+    }
+
+    pub fn b(&self) -> &SparseMatrix<E> {
+        &self.b
+    }
+
+    pub fn c(&self) -> &SparseMatrix<E> {
+        &self.c
+    }
+
+    /// Evaluates A, B and C on the witness. The input assignment
+    /// starts with the "one" input. Assignments of the wrong length are
+    /// reported as `WitnessMismatch`.
+    pub fn prepare_prover(
+        &self,
+        input_assignment: &[E::Fr],
+        aux_assignment: &[E::Fr]
+    ) -> Result<PreparedProver<E>, SynthesisError>
+    {
+        if input_assignment.len() != self.num_inputs || aux_assignment.len() != self.num_aux {
+            return Err(SynthesisError::WitnessMismatch);
+        }
+
+        let worker = Worker::new();
+
+        let a = self.a.mul_vec(&worker, input_assignment, aux_assignment);
+        let b = self.b.mul_vec(&worker, input_assignment, aux_assignment);
+        let c = self.c.mul_vec(&worker, input_assignment, aux_assignment);
+
+        Ok(PreparedProver {
+            assignment: ProvingAssignment {
+                a_aux_density: self.a_aux_density.clone(),
+                b_input_density: self.b_input_density.clone(),
+                b_aux_density: self.b_aux_density.clone(),
+                a: a,
+                b: b,
+                c: c,
+                input_assignment: input_assignment.to_vec(),
+                aux_assignment: aux_assignment.to_vec(),
+                current_namespace: vec![]
+            }
+        })
+    }
+}
+
+/// Assembly that records the rows of the A, B and C matrices.
+struct CompilingAssembly<E: Engine> {
+    num_inputs: usize,
+    num_aux: usize,
+    a: Vec<Vec<(Index, E::Fr)>>,
+    b: Vec<Vec<(Index, E::Fr)>>,
+    c: Vec<Vec<(Index, E::Fr)>>
+}
+
+impl<E: Engine> ConstraintSystem<E> for CompilingAssembly<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(
+        &mut self,
+        _: A,
+        _: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        // There is no assignment, so we don't even invoke the
+        // function for obtaining one.
+
+        let index = self.num_aux;
+        self.num_aux += 1;
+
+        Ok(Variable(Index::Aux(index)))
+    }
+
+    fn alloc_input<F, A, AR>(
+        &mut self,
+        _: A,
+        _: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        // There is no assignment, so we don't even invoke the
+        // function for obtaining one.
+
+        let index = self.num_inputs;
+        self.num_inputs += 1;
+
+        Ok(Variable(Index::Input(index)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(
+        &mut self,
+        _: A,
+        a: LA,
+        b: LB,
+        c: LC
+    )
+        where A: FnOnce() -> AR, AR: Into<String>,
+              LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>
+    {
+        fn row<E: Engine>(lc: LinearCombination<E>) -> Vec<(Index, E::Fr)> {
+            lc.0.into_iter().map(|(var, coeff)| (var.0, coeff)).collect()
+        }
+
+        self.a.push(row(a(LinearCombination::zero())));
+        self.b.push(row(b(LinearCombination::zero())));
+        self.c.push(row(c(LinearCombination::zero())));
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+        where NR: Into<String>, N: FnOnce() -> NR
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self)
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+/// Assembly that only computes the witness. Constraints are
+/// ignored, so their linear combinations are never built.
+struct WitnessAssembly<E: Engine> {
+    input_assignment: Vec<E::Fr>,
+    aux_assignment: Vec<E::Fr>,
+    current_namespace: Vec<String>
+}
+
+impl<E: Engine> ConstraintSystem<E> for WitnessAssembly<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(
+        &mut self,
+        annotation: A,
+        f: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        let index = Index::Aux(self.aux_assignment.len());
+        let value = f().map_err(|e| {
+            e.at(ErrorLocation::in_namespace(index, &self.current_namespace, annotation().into()))
+        })?;
+        self.aux_assignment.push(value);
+
+        Ok(Variable(Index::Aux(self.aux_assignment.len() - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(
+        &mut self,
+        annotation: A,
+        f: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        let index = Index::Input(self.input_assignment.len());
+        let value = f().map_err(|e| {
+            e.at(ErrorLocation::in_namespace(index, &self.current_namespace, annotation().into()))
+        })?;
+        self.input_assignment.push(value);
+
+        Ok(Variable(Index::Input(self.input_assignment.len() - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(
+        &mut self,
+        _: A,
+        _: LA,
+        _: LB,
+        _: LC
+    )
+        where A: FnOnce() -> AR, AR: Into<String>,
+              LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>
+    {
+        // Do nothing; the constraints are known from compilation.
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+        where NR: Into<String>, N: FnOnce() -> NR
+    {
+        // Namespaces only matter for locating errors
+        self.current_namespace.push(name_fn().into());
+    }
+
+    fn pop_namespace(&mut self)
+    {
+        assert!(self.current_namespace.pop().is_some());
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+// The input and auxillary assignments
+type Assignments<E> = (Vec<<E as ScalarEngine>::Fr>, Vec<<E as ScalarEngine>::Fr>);
+
+/// Runs only the assignment closures of the circuit and returns the
+/// input assignment (starting with the "one" input) and the auxillary
+/// assignment.
+pub fn synthesize_witness<E, C>(
+    circuit: C
+) -> Result<Assignments<E>, SynthesisError>
+    where E: Engine, C: Circuit<E>
+{
+    let mut assembly = WitnessAssembly {
+        input_assignment: vec![],
+        aux_assignment: vec![],
+        current_namespace: vec![]
+    };
+
+    assembly.alloc_input(|| "", || Ok(E::Fr::one()))?;
+
+    circuit.synthesize(&mut assembly)?;
+
+    Ok((assembly.input_assignment, assembly.aux_assignment))
+}
+
+pub fn create_random_proof_from_witness<E, R, P: ParameterSource<E>>(
+    compiled: &CompiledCircuit<E>,
+    input_assignment: &[E::Fr],
+    aux_assignment: &[E::Fr],
+    params: P,
+    rng: &mut R
+) -> Result<Proof<E>, SynthesisError>
+    where E: Engine, R: Rng
+{
+    let r = rng.gen();
+    let s = rng.gen();
+
+    create_proof_from_witness(compiled, input_assignment, aux_assignment, params, r, s)
+}
+
+pub fn create_proof_from_witness<E, P: ParameterSource<E>>(
+    compiled: &CompiledCircuit<E>,
+    input_assignment: &[E::Fr],
+    aux_assignment: &[E::Fr],
+    params: P,
+    r: E::Fr,
+    s: E::Fr
+) -> Result<Proof<E>, SynthesisError>
+    where E: Engine
+{
+    let prover = compiled.prepare_prover(input_assignment, aux_assignment)?;
+
+    prover.create_proof(params, r, s)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bls12_381::{Bls12, Fr};
+    use crate::tests::XORDemo;
+    use super::super::{
+        generate_random_parameters,
+        prepare_verifying_key,
+        create_proof,
+        verify_proof
+    };
+
+    use rand::{Rand, thread_rng};
+    use std::marker::PhantomData;
+
+    #[test]
+    fn test_compiled_proof_matches_circuit_proof() {
+        let rng = &mut thread_rng();
+
+        let circuit = |a, b| XORDemo::<Bls12> {
+            a: a,
+            b: b,
+            _marker: PhantomData
+        };
+
+        let params = generate_random_parameters(circuit(None, None), rng).unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+
+        let compiled = CompiledCircuit::compile(circuit(None, None)).unwrap();
+        assert_eq!(compiled.num_inputs(), 2);
+        assert_eq!(compiled.num_aux(), 2);
+        // 3 circuit constraints and 2 input constraints
+        assert_eq!(compiled.num_constraints(), 5);
+        // a + a in the xor constraint is merged into a single term
+        assert_eq!(compiled.a().num_nonzero(), 7);
+
+        for &(a, b) in [(false, false), (false, true), (true, false), (true, true)].iter() {
+            let (input, aux) = synthesize_witness(circuit(Some(a), Some(b))).unwrap();
+            assert_eq!(input.len(), 2);
+
+            let r = Fr::rand(rng);
+            let s = Fr::rand(rng);
+
+            let expected = create_proof(circuit(Some(a), Some(b)), &params, r, s).unwrap();
+            let proof = create_proof_from_witness(&compiled, &input, &aux, &params, r, s).unwrap();
+
+            assert!(proof == expected);
+            assert!(verify_proof(&pvk, &proof, &input[1..]).unwrap());
+        }
+
+        assert!(compiled.prepare_prover(&[Fr::one()], &[]).err().unwrap() == SynthesisError::WitnessMismatch);
+    }
+
+    // Allocates a variable without a value, and without constraining it
+    struct Missing;
+
+    impl<E: Engine> Circuit<E> for Missing {
+        fn synthesize<CS: ConstraintSystem<E>>(
+            self,
+            cs: &mut CS
+        ) -> Result<(), SynthesisError>
+        {
+            let mut cs = cs.namespace(|| "outer");
+            cs.alloc(|| "value", || Err(SynthesisError::AssignmentMissing))?;
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_errors_are_located() {
+        let rng = &mut thread_rng();
+
+        let err = synthesize_witness::<Bls12, _>(Missing).unwrap_err();
+        assert!(err == SynthesisError::AssignmentMissing);
+        assert_eq!(err.location(), Some(&ErrorLocation::variable(Index::Aux(0)).with_path("outer/value")));
+
+        let err = generate_random_parameters::<Bls12, _, _>(Missing, rng).err().unwrap();
+        assert!(err == SynthesisError::UnconstrainedVariable);
+        assert_eq!(err.location(), Some(&ErrorLocation::variable(Index::Aux(0)).with_path("outer/value")));
+
+        let circuit = |a, b| XORDemo::<Bls12> {
+            a: a,
+            b: b,
+            _marker: PhantomData
+        };
+        let params = generate_random_parameters(circuit(None, None), rng).unwrap();
+        let err = create_proof(circuit(Some(true), None), &params, Fr::rand(rng), Fr::rand(rng)).unwrap_err();
+        match *err.kind() {
+            SynthesisError::AssignmentMissing => {},
+            ref e => panic!("unexpected error {}", e)
+        }
+        assert_eq!(err.location(), Some(&ErrorLocation::variable(Index::Aux(1)).with_path("b")));
+    }
+}
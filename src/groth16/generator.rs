@@ -18,16 +18,19 @@ use crate::pairing::ff::{
 
 use super::{
     Parameters,
-    VerifyingKey
+    VerifyingKey,
+    InputLayout
 };
 
 use crate::{
     SynthesisError,
+    ErrorLocation,
     Circuit,
     ConstraintSystem,
     LinearCombination,
     Variable,
-    Index
+    Index,
+    namespace_path
 };
 
 use crate::domain::{
@@ -67,6 +70,34 @@ pub fn generate_random_parameters<E, C, R>(
     )
 }
 
+/// Same as `generate_random_parameters`, but also returns the
+/// names and order of the public inputs.
+pub fn generate_random_parameters_with_layout<E, C, R>(
+    circuit: C,
+    rng: &mut R
+) -> Result<(Parameters<E>, InputLayout), SynthesisError>
+    where E: Engine, C: Circuit<E>, R: Rng
+{
+    let g1 = rng.gen();
+    let g2 = rng.gen();
+    let alpha = rng.gen();
+    let beta = rng.gen();
+    let gamma = rng.gen();
+    let delta = rng.gen();
+    let tau = rng.gen();
+
+    generate_parameters_with_layout::<E, C>(
+        circuit,
+        g1,
+        g2,
+        alpha,
+        beta,
+        gamma,
+        delta,
+        tau
+    )
+}
+
 /// This is our assembly structure that we'll use to synthesize the
 /// circuit into a QAP.
 struct KeypairAssembly<E: Engine> {
@@ -78,7 +109,10 @@ struct KeypairAssembly<E: Engine> {
     ct_inputs: Vec<Vec<(E::Fr, usize)>>,
     at_aux: Vec<Vec<(E::Fr, usize)>>,
     bt_aux: Vec<Vec<(E::Fr, usize)>>,
-    ct_aux: Vec<Vec<(E::Fr, usize)>>
+    ct_aux: Vec<Vec<(E::Fr, usize)>>,
+    input_names: Vec<String>,
+    aux_names: Vec<String>,
+    current_namespace: Vec<String>
 }
 
 impl<E: Engine> ConstraintSystem<E> for KeypairAssembly<E> {
@@ -86,7 +120,7 @@ impl<E: Engine> ConstraintSystem<E> for KeypairAssembly<E> {
 
     fn alloc<F, A, AR>(
         &mut self,
-        _: A,
+        annotation: A,
         _: F
     ) -> Result<Variable, SynthesisError>
         where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
@@ -101,12 +135,15 @@ impl<E: Engine> ConstraintSystem<E> for KeypairAssembly<E> {
         self.bt_aux.push(vec![]);
         self.ct_aux.push(vec![]);
 
+        // Remember the full path to locate unconstrained variables
+        self.aux_names.push(namespace_path(&self.current_namespace, annotation().into()));
+
         Ok(Variable(Index::Aux(index)))
     }
 
     fn alloc_input<F, A, AR>(
         &mut self,
-        _: A,
+        annotation: A,
         _: F
     ) -> Result<Variable, SynthesisError>
         where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
@@ -121,6 +158,9 @@ impl<E: Engine> ConstraintSystem<E> for KeypairAssembly<E> {
         self.bt_inputs.push(vec![]);
         self.ct_inputs.push(vec![]);
 
+        // Remember the full path of the input for the layout
+        self.input_names.push(namespace_path(&self.current_namespace, annotation().into()));
+
         Ok(Variable(Index::Input(index)))
     }
 
@@ -158,15 +198,16 @@ impl<E: Engine> ConstraintSystem<E> for KeypairAssembly<E> {
         self.num_constraints += 1;
     }
 
-    fn push_namespace<NR, N>(&mut self, _: N)
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
         where NR: Into<String>, N: FnOnce() -> NR
     {
-        // Do nothing; we don't care about namespaces in this context.
+        // Namespaces only matter for naming the variables
+        self.current_namespace.push(name_fn().into());
     }
 
     fn pop_namespace(&mut self)
     {
-        // Do nothing; we don't care about namespaces in this context.
+        assert!(self.current_namespace.pop().is_some());
     }
 
     fn get_root(&mut self) -> &mut Self::Root {
@@ -186,6 +227,33 @@ pub fn generate_parameters<E, C>(
     tau: E::Fr
 ) -> Result<Parameters<E>, SynthesisError>
     where E: Engine, C: Circuit<E>
+{
+    generate_parameters_with_layout::<E, C>(
+        circuit,
+        g1,
+        g2,
+        alpha,
+        beta,
+        gamma,
+        delta,
+        tau
+    ).map(|(params, _)| params)
+}
+
+/// Same as `generate_parameters`, but also returns the
+/// names and order of the public inputs.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_parameters_with_layout<E, C>(
+    circuit: C,
+    g1: E::G1,
+    g2: E::G2,
+    alpha: E::Fr,
+    beta: E::Fr,
+    gamma: E::Fr,
+    delta: E::Fr,
+    tau: E::Fr
+) -> Result<(Parameters<E>, InputLayout), SynthesisError>
+    where E: Engine, C: Circuit<E>
 {
     let mut assembly = KeypairAssembly {
         num_inputs: 0,
@@ -196,7 +264,10 @@ pub fn generate_parameters<E, C>(
         ct_inputs: vec![],
         at_aux: vec![],
         bt_aux: vec![],
-        ct_aux: vec![]
+        ct_aux: vec![],
+        input_names: vec![],
+        aux_names: vec![],
+        current_namespace: vec![]
     };
 
     // Allocate the "one" input variable
@@ -477,9 +548,11 @@ pub fn generate_parameters<E, C>(
 
     // Don't allow any elements be unconstrained, so that
     // the L query is always fully dense.
-    for e in l.iter() {
+    for (i, e) in l.iter().enumerate() {
         if e.is_zero() {
-            return Err(SynthesisError::UnconstrainedVariable);
+            let location = ErrorLocation::variable(Index::Aux(i)).with_path(assembly.aux_names[i].clone());
+
+            return Err(SynthesisError::UnconstrainedVariable.at(location));
         }
     }
 
@@ -498,7 +571,7 @@ pub fn generate_parameters<E, C>(
 
     log!("Has generated {} points", a.len());
 
-    Ok(Parameters {
+    let params = Parameters {
         vk: vk,
         h: Arc::new(h.into_iter().map(|e| e.into_affine()).collect()),
         l: Arc::new(l.into_iter().map(|e| e.into_affine()).collect()),
@@ -507,5 +580,10 @@ pub fn generate_parameters<E, C>(
         a: Arc::new(a.into_iter().filter(|e| !e.is_zero()).map(|e| e.into_affine()).collect()),
         b_g1: Arc::new(b_g1.into_iter().filter(|e| !e.is_zero()).map(|e| e.into_affine()).collect()),
         b_g2: Arc::new(b_g2.into_iter().filter(|e| !e.is_zero()).map(|e| e.into_affine()).collect())
-    })
+    };
+
+    // The first input is the "one" variable, which is not supplied by the verifier
+    let layout = InputLayout::new(assembly.input_names.split_off(1));
+
+    Ok((params, layout))
 }
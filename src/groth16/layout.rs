@@ -0,0 +1,211 @@
+use crate::VerificationError;
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Read, Write};
+use byteorder::{BigEndian, WriteBytesExt, ReadBytesExt};
+
+/// The names of the public inputs of a circuit, in the order the verifier
+/// expects them. Names are the full namespace paths of the `alloc_input`
+/// annotations. The "one" input is not included.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InputLayout {
+    names: Vec<String>
+}
+
+impl InputLayout {
+    pub fn new(names: Vec<String>) -> Self {
+        InputLayout {
+            names: names
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Position of the input among the values passed to `verify_proof`.
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+
+    /// Arranges named values in the order of the layout. Every input of the
+    /// layout must be supplied exactly once, and nothing else.
+    pub fn assemble<F: Clone>(
+        &self,
+        values: &HashMap<String, F>
+    ) -> Result<Vec<F>, VerificationError>
+    {
+        let mut seen = HashSet::new();
+        for name in self.names.iter() {
+            if !seen.insert(name.as_str()) {
+                return Err(VerificationError::AmbiguousInput(name.clone()));
+            }
+        }
+
+        let mut unexpected: Vec<&String> = values.keys().filter(|name| !seen.contains(name.as_str())).collect();
+        unexpected.sort();
+        if let Some(name) = unexpected.first() {
+            return Err(VerificationError::UnexpectedInput((*name).clone()));
+        }
+
+        self.names.iter().map(|name| {
+            values.get(name).cloned().ok_or_else(|| VerificationError::MissingInput(name.clone()))
+        }).collect()
+    }
+
+    pub fn write<W: Write>(
+        &self,
+        mut writer: W
+    ) -> io::Result<()>
+    {
+        writer.write_u32::<BigEndian>(self.names.len() as u32)?;
+        for name in self.names.iter() {
+            writer.write_u32::<BigEndian>(name.len() as u32)?;
+            writer.write_all(name.as_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(
+        mut reader: R
+    ) -> io::Result<Self>
+    {
+        let len = reader.read_u32::<BigEndian>()? as usize;
+
+        let mut names = vec![];
+        for _ in 0..len {
+            // The length is untrusted, so the name grows only with
+            // the data actually read
+            let name_len = reader.read_u32::<BigEndian>()? as u64;
+            let mut name = vec![];
+            (&mut reader).take(name_len).read_to_end(&mut name)?;
+            if name.len() as u64 != name_len {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "input name is truncated"));
+            }
+
+            let name = String::from_utf8(name).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            names.push(name);
+        }
+
+        Ok(InputLayout {
+            names: names
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bls12_381::{Bls12, Fr};
+    use crate::pairing::ff::{Field, PrimeField};
+    use crate::{Circuit, ConstraintSystem, SynthesisError};
+    use super::super::{
+        generate_random_parameters_with_layout,
+        prepare_verifying_key,
+        create_random_proof,
+        verify_proof_named
+    };
+
+    use rand::thread_rng;
+
+    struct Product {
+        x: Option<Fr>,
+        y: Option<Fr>
+    }
+
+    impl Circuit<Bls12> for Product {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS
+        ) -> Result<(), SynthesisError>
+        {
+            let x = cs.alloc_input(|| "x", || self.x.ok_or(SynthesisError::AssignmentMissing))?;
+            let y = cs.namespace(|| "point").alloc_input(|| "y", || self.y.ok_or(SynthesisError::AssignmentMissing))?;
+
+            let z = cs.alloc(|| "z", || {
+                let mut z = self.x.ok_or(SynthesisError::AssignmentMissing)?;
+                z.mul_assign(&self.y.ok_or(SynthesisError::AssignmentMissing)?);
+
+                Ok(z)
+            })?;
+
+            cs.enforce(|| "product", |lc| lc + x, |lc| lc + y, |lc| lc + z);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_verify_with_named_inputs() {
+        let rng = &mut thread_rng();
+
+        let (params, layout) = generate_random_parameters_with_layout(Product { x: None, y: None }, rng).unwrap();
+        assert_eq!(layout.names(), &["x".to_string(), "point/y".to_string()][..]);
+        assert_eq!(layout.position("point/y"), Some(1));
+
+        let mut v = vec![];
+        layout.write(&mut v).unwrap();
+        let layout = InputLayout::read(&v[..]).unwrap();
+
+        let pvk = prepare_verifying_key(&params.vk);
+
+        let two = Fr::from_str("2").unwrap();
+        let three = Fr::from_str("3").unwrap();
+        let proof = create_random_proof(Product { x: Some(two), y: Some(three) }, &params, rng).unwrap();
+
+        let mut inputs = HashMap::new();
+        inputs.insert("point/y".to_string(), three);
+        inputs.insert("x".to_string(), two);
+        assert!(verify_proof_named(&pvk, &layout, &proof, &inputs).unwrap());
+
+        inputs.insert("x".to_string(), three);
+        inputs.insert("point/y".to_string(), two);
+        assert!(!verify_proof_named(&pvk, &layout, &proof, &inputs).unwrap());
+
+        inputs.remove("point/y");
+        assert_eq!(
+            verify_proof_named(&pvk, &layout, &proof, &inputs).unwrap_err(),
+            VerificationError::MissingInput("point/y".into())
+        );
+
+        inputs.insert("y".to_string(), two);
+        assert_eq!(
+            verify_proof_named(&pvk, &layout, &proof, &inputs).unwrap_err(),
+            VerificationError::UnexpectedInput("y".into())
+        );
+
+        let short = InputLayout::new(vec!["x".into()]);
+        let mut inputs = HashMap::new();
+        inputs.insert("x".to_string(), two);
+        assert_eq!(
+            verify_proof_named(&pvk, &short, &proof, &inputs).unwrap_err(),
+            VerificationError::InputCountMismatch { expected: 2, actual: 1 }
+        );
+
+        let ambiguous = InputLayout::new(vec!["x".into(), "x".into()]);
+        assert_eq!(
+            verify_proof_named(&pvk, &ambiguous, &proof, &inputs).unwrap_err(),
+            VerificationError::AmbiguousInput("x".into())
+        );
+    }
+
+    #[test]
+    fn test_read_corrupt_layout() {
+        let mut v = vec![];
+        InputLayout::new(vec!["x".into()]).write(&mut v).unwrap();
+
+        // A corrupt name length is not allocated up front
+        v[4..8].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_eq!(InputLayout::read(&v[..]).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
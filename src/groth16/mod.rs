@@ -19,10 +19,18 @@ mod tests;
 mod generator;
 mod prover;
 mod verifier;
+mod compiled;
+#[cfg(feature = "witness")]
+mod witness;
+mod layout;
 
 pub use self::generator::*;
 pub use self::prover::*;
 pub use self::verifier::*;
+pub use self::compiled::*;
+#[cfg(feature = "witness")]
+pub use self::witness::*;
+pub use self::layout::*;
 
 #[derive(Debug, Clone)]
 pub struct Proof<E: Engine> {
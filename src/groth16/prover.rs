@@ -22,6 +22,7 @@ use super::{
 
 use crate::{
     SynthesisError,
+    ErrorLocation,
     Circuit,
     ConstraintSystem,
     LinearCombination,
@@ -139,13 +140,16 @@ pub struct ProvingAssignment<E: Engine> {
     pub b_aux_density: DensityTracker,
 
     // Evaluations of A, B, C polynomials
-    a: Vec<Scalar<E>>,
-    b: Vec<Scalar<E>>,
-    c: Vec<Scalar<E>>,
+    pub(crate) a: Vec<Scalar<E>>,
+    pub(crate) b: Vec<Scalar<E>>,
+    pub(crate) c: Vec<Scalar<E>>,
 
     // Assignments of variables
-    input_assignment: Vec<E::Fr>,
-    aux_assignment: Vec<E::Fr>
+    pub(crate) input_assignment: Vec<E::Fr>,
+    pub(crate) aux_assignment: Vec<E::Fr>,
+
+    // Namespaces, to locate errors
+    pub(crate) current_namespace: Vec<String>
 }
 
 pub fn prepare_prover<E, C>(
@@ -161,7 +165,8 @@ pub fn prepare_prover<E, C>(
         b: vec![],
         c: vec![],
         input_assignment: vec![],
-        aux_assignment: vec![]
+        aux_assignment: vec![],
+        current_namespace: vec![]
     };
 
     prover.alloc_input(|| "", || Ok(E::Fr::one()))?;
@@ -350,12 +355,16 @@ impl<E: Engine> ConstraintSystem<E> for ProvingAssignment<E> {
 
     fn alloc<F, A, AR>(
         &mut self,
-        _: A,
+        annotation: A,
         f: F
     ) -> Result<Variable, SynthesisError>
         where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
     {
-        self.aux_assignment.push(f()?);
+        let index = Index::Aux(self.aux_assignment.len());
+        let value = f().map_err(|e| {
+            e.at(ErrorLocation::in_namespace(index, &self.current_namespace, annotation().into()))
+        })?;
+        self.aux_assignment.push(value);
         self.a_aux_density.add_element();
         self.b_aux_density.add_element();
 
@@ -364,12 +373,16 @@ impl<E: Engine> ConstraintSystem<E> for ProvingAssignment<E> {
 
     fn alloc_input<F, A, AR>(
         &mut self,
-        _: A,
+        annotation: A,
         f: F
     ) -> Result<Variable, SynthesisError>
         where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
     {
-        self.input_assignment.push(f()?);
+        let index = Index::Input(self.input_assignment.len());
+        let value = f().map_err(|e| {
+            e.at(ErrorLocation::in_namespace(index, &self.current_namespace, annotation().into()))
+        })?;
+        self.input_assignment.push(value);
         self.b_input_density.add_element();
 
         Ok(Variable(Index::Input(self.input_assignment.len() - 1)))
@@ -421,15 +434,16 @@ impl<E: Engine> ConstraintSystem<E> for ProvingAssignment<E> {
         )));
     }
 
-    fn push_namespace<NR, N>(&mut self, _: N)
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
         where NR: Into<String>, N: FnOnce() -> NR
     {
-        // Do nothing; we don't care about namespaces in this context.
+        // Namespaces only matter for locating errors
+        self.current_namespace.push(name_fn().into());
     }
 
     fn pop_namespace(&mut self)
     {
-        // Do nothing; we don't care about namespaces in this context.
+        assert!(self.current_namespace.pop().is_some());
     }
 
     fn get_root(&mut self) -> &mut Self::Root {
@@ -9,13 +9,16 @@ use crate::pairing::ff::{PrimeField};
 use super::{
     Proof,
     VerifyingKey,
-    PreparedVerifyingKey
+    PreparedVerifyingKey,
+    InputLayout
 };
 
 use crate::{
-    SynthesisError
+    VerificationError
 };
 
+use std::collections::HashMap;
+
 pub fn prepare_verifying_key<E: Engine>(
     vk: &VerifyingKey<E>
 ) -> PreparedVerifyingKey<E>
@@ -37,10 +40,17 @@ pub fn verify_proof<'a, E: Engine>(
     pvk: &'a PreparedVerifyingKey<E>,
     proof: &Proof<E>,
     public_inputs: &[E::Fr]
-) -> Result<bool, SynthesisError>
+) -> Result<bool, VerificationError>
 {
+    if pvk.ic.is_empty() {
+        return Err(VerificationError::MalformedVerifyingKey);
+    }
+
     if (public_inputs.len() + 1) != pvk.ic.len() {
-        return Err(SynthesisError::MalformedVerifyingKey);
+        return Err(VerificationError::InputCountMismatch {
+            expected: pvk.ic.len() - 1,
+            actual: public_inputs.len()
+        });
     }
 
     let mut acc = pvk.ic[0].into_projective();
@@ -65,3 +75,18 @@ pub fn verify_proof<'a, E: Engine>(
         ].iter())
     ).unwrap() == pvk.alpha_g1_beta_g2)
 }
+
+/// Verifies a proof with the public inputs given by their names in the
+/// layout. Missing, unknown or ambiguous inputs are reported before any
+/// pairing is computed.
+pub fn verify_proof_named<E: Engine>(
+    pvk: &PreparedVerifyingKey<E>,
+    layout: &InputLayout,
+    proof: &Proof<E>,
+    public_inputs: &HashMap<String, E::Fr>
+) -> Result<bool, VerificationError>
+{
+    let public_inputs = layout.assemble(public_inputs)?;
+
+    verify_proof(pvk, proof, &public_inputs)
+}
@@ -0,0 +1,260 @@
+use crate::pairing::{
+    Engine
+};
+
+use crate::pairing::ff::{
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use super::{
+    CompiledCircuit,
+    ProvingAssignment,
+    PreparedProver,
+    synthesize_witness
+};
+
+use crate::{
+    SynthesisError,
+    Circuit
+};
+
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+use byteorder::{BigEndian, WriteBytesExt, ReadBytesExt};
+
+const MAGIC: &[u8; 4] = b"bwit";
+const VERSION: u32 = 1;
+
+/// A complete assignment of a circuit, stored separately from the circuit so
+/// that proofs can be created later without re-running the closures that
+/// compute it. The input assignment starts with the "one" input.
+#[derive(Clone)]
+pub struct Witness<E: Engine> {
+    pub input_assignment: Vec<E::Fr>,
+    pub aux_assignment: Vec<E::Fr>,
+
+    // Digest of the compiled circuit this witness belongs to
+    pub circuit_digest: [u8; 32]
+}
+
+impl<E: Engine> PartialEq for Witness<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.input_assignment == other.input_assignment &&
+        self.aux_assignment == other.aux_assignment &&
+        self.circuit_digest == other.circuit_digest
+    }
+}
+
+impl<E: Engine> Witness<E> {
+    /// Computes the witness of the circuit, which must have
+    /// the same shape as the compiled one.
+    pub fn synthesize<C: Circuit<E>>(
+        circuit: C,
+        compiled: &CompiledCircuit<E>
+    ) -> Result<Self, SynthesisError>
+    {
+        let (input_assignment, aux_assignment) = synthesize_witness(circuit)?;
+
+        Ok(Witness {
+            input_assignment: input_assignment,
+            aux_assignment: aux_assignment,
+            circuit_digest: compiled.digest()
+        })
+    }
+
+    /// Serializes the witness. The engine is identified by
+    /// the modulus of its scalar field.
+    pub fn write<W: Write>(
+        &self,
+        mut writer: W
+    ) -> io::Result<()>
+    {
+        let num_inputs = u32::try_from(self.input_assignment.len());
+        let num_aux = u32::try_from(self.aux_assignment.len());
+        let (num_inputs, num_aux) = match (num_inputs, num_aux) {
+            (Ok(num_inputs), Ok(num_aux)) => (num_inputs, num_aux),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "too many variables for the format"))
+        };
+
+        writer.write_all(MAGIC)?;
+        writer.write_u32::<BigEndian>(VERSION)?;
+
+        let modulus = E::Fr::char();
+        writer.write_u32::<BigEndian>(modulus.as_ref().len() as u32)?;
+        modulus.write_be(&mut writer)?;
+
+        writer.write_all(&self.circuit_digest)?;
+
+        writer.write_u32::<BigEndian>(num_inputs)?;
+        writer.write_u32::<BigEndian>(num_aux)?;
+        for value in self.input_assignment.iter().chain(self.aux_assignment.iter()) {
+            value.into_repr().write_be(&mut writer)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(
+        mut reader: R
+    ) -> io::Result<Self>
+    {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a witness file"));
+        }
+
+        if reader.read_u32::<BigEndian>()? != VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "unsupported witness version"));
+        }
+
+        let mut modulus = <E::Fr as PrimeField>::Repr::default();
+        if reader.read_u32::<BigEndian>()? as usize != modulus.as_ref().len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "witness was created for a different engine"));
+        }
+        modulus.read_be(&mut reader)?;
+        if modulus != E::Fr::char() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "witness was created for a different engine"));
+        }
+
+        let mut circuit_digest = [0u8; 32];
+        reader.read_exact(&mut circuit_digest)?;
+
+        let num_inputs = reader.read_u32::<BigEndian>()? as usize;
+        let num_aux = reader.read_u32::<BigEndian>()? as usize;
+
+        let mut read_values = |len: usize| -> io::Result<Vec<E::Fr>> {
+            let mut values = vec![];
+            for _ in 0..len {
+                let mut repr = <E::Fr as PrimeField>::Repr::default();
+                repr.read_be(&mut reader)?;
+                values.push(E::Fr::from_repr(repr).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?);
+            }
+
+            Ok(values)
+        };
+
+        let input_assignment = read_values(num_inputs)?;
+        let aux_assignment = read_values(num_aux)?;
+
+        Ok(Witness {
+            input_assignment: input_assignment,
+            aux_assignment: aux_assignment,
+            circuit_digest: circuit_digest
+        })
+    }
+}
+
+impl<E: Engine> ProvingAssignment<E> {
+    /// Extracts the witness, tagged with the digest of
+    /// the compiled circuit it was synthesized from.
+    pub fn witness(
+        &self,
+        circuit_digest: [u8; 32]
+    ) -> Witness<E>
+    {
+        Witness {
+            input_assignment: self.input_assignment.clone(),
+            aux_assignment: self.aux_assignment.clone(),
+            circuit_digest: circuit_digest
+        }
+    }
+
+    pub fn write_witness<W: Write>(
+        &self,
+        circuit_digest: [u8; 32],
+        writer: W
+    ) -> io::Result<()>
+    {
+        self.witness(circuit_digest).write(writer)
+    }
+}
+
+/// Prepares a prover from a stored witness without running any
+/// of the circuit's closures.
+pub fn prepare_prover_from_witness<E: Engine>(
+    compiled: &CompiledCircuit<E>,
+    witness: &Witness<E>
+) -> Result<PreparedProver<E>, SynthesisError>
+{
+    if witness.circuit_digest != compiled.digest() {
+        return Err(SynthesisError::WitnessMismatch);
+    }
+
+    compiled.prepare_prover(&witness.input_assignment, &witness.aux_assignment)
+}
+
+/// Same as `prepare_prover_from_witness`, but takes the circuit itself. Only
+/// the shape of the circuit is synthesized; its closures are never invoked.
+pub fn prepare_prover_from_circuit_witness<E, C>(
+    circuit: C,
+    witness: &Witness<E>
+) -> Result<PreparedProver<E>, SynthesisError>
+    where E: Engine, C: Circuit<E>
+{
+    let compiled = CompiledCircuit::compile(circuit)?;
+
+    prepare_prover_from_witness(&compiled, witness)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bn256::{Bn256, Fr};
+    use crate::pairing::ff::Field;
+    use crate::tests::XORDemo;
+    use super::super::{
+        generate_random_parameters,
+        prepare_prover,
+        prepare_verifying_key,
+        verify_proof
+    };
+
+    use rand::thread_rng;
+    use std::marker::PhantomData;
+
+    #[test]
+    fn test_witness_roundtrip_and_proving() {
+        let rng = &mut thread_rng();
+
+        let circuit = |a, b| XORDemo::<Bn256> {
+            a: a,
+            b: b,
+            _marker: PhantomData
+        };
+
+        let params = generate_random_parameters(circuit(None, None), rng).unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+        let compiled = CompiledCircuit::compile(circuit(None, None)).unwrap();
+
+        let prover = prepare_prover(circuit(Some(true), Some(false))).unwrap();
+        let mut v = vec![];
+        prover.assignment.write_witness(compiled.digest(), &mut v).unwrap();
+
+        let witness = Witness::<Bn256>::read(&v[..]).unwrap();
+        assert!(witness == Witness::synthesize(circuit(Some(true), Some(false)), &compiled).unwrap());
+        assert_eq!(witness.input_assignment, vec![Fr::one(), Fr::one()]);
+
+        // The same witness can be proven repeatedly
+        for _ in 0..2 {
+            let proof = prepare_prover_from_witness(&compiled, &witness).unwrap()
+                        .create_random_proof(&params, rng).unwrap();
+            assert!(verify_proof(&pvk, &proof, &[Fr::one()]).unwrap());
+        }
+
+        let proof = prepare_prover_from_circuit_witness(circuit(None, None), &witness).unwrap()
+                    .create_random_proof(&params, rng).unwrap();
+        assert!(verify_proof(&pvk, &proof, &[Fr::one()]).unwrap());
+
+        let mut wrong = witness.clone();
+        wrong.circuit_digest[0] ^= 1;
+        match prepare_prover_from_witness(&compiled, &wrong) {
+            Err(SynthesisError::WitnessMismatch) => {},
+            _ => panic!("a witness of another circuit must be rejected")
+        }
+
+        assert!(Witness::<crate::pairing::bls12_381::Bls12>::read(&v[..]).is_err());
+    }
+}
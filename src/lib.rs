@@ -12,7 +12,9 @@ extern crate byteorder;
 mod log;
 
 pub mod domain;
+pub mod gadgets;
 pub mod groth16;
+pub mod r1cs;
 
 #[cfg(feature = "gm17")]
 pub mod gm17;
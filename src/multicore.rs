@@ -19,6 +19,8 @@ use self::futures::channel::oneshot::{channel, Sender, Receiver};
 use self::futures::executor::{block_on};
 use self::futures::executor::{ThreadPool};
 
+use crate::SynthesisError;
+
 #[derive(Clone)]
 pub struct Worker {
     cpus: usize,
@@ -32,16 +34,26 @@ impl Worker {
     // CPUs configured.
     
     pub(crate) fn new_with_cpus(cpus: usize) -> Worker {
-        Worker {
+        Self::try_new_with_cpus(cpus).expect("should create a thread pool for futures execution")
+    }
+
+    pub(crate) fn try_new_with_cpus(cpus: usize) -> Result<Worker, SynthesisError> {
+        Ok(Worker {
             cpus: cpus,
-            pool: ThreadPool::builder().pool_size(cpus).create().expect("should create a thread pool for futures execution"),
-        }
+            pool: ThreadPool::builder().pool_size(cpus).create()?,
+        })
     }
 
     pub fn new() -> Worker {
         Self::new_with_cpus(num_cpus::get())
     }
 
+    /// Same as `new`, but returns an error if the thread
+    /// pool can not be created.
+    pub fn try_new() -> Result<Worker, SynthesisError> {
+        Self::try_new_with_cpus(num_cpus::get())
+    }
+
     pub fn log_num_cpus(&self) -> u32 {
         log2_floor(self.cpus)
     }
@@ -77,6 +89,18 @@ impl Worker {
         f: F
     ) -> R
         where F: FnOnce(&Scope<'a>, usize) -> R
+    {
+        self.try_scope(elements, f).expect("must run")
+    }
+
+    /// Same as `scope`, but returns an error instead of panicking
+    /// if any of the spawned threads panicked.
+    pub fn try_scope<'a, F, R>(
+        &self,
+        elements: usize,
+        f: F
+    ) -> Result<R, SynthesisError>
+        where F: FnOnce(&Scope<'a>, usize) -> R
     {
         let chunk_size = if elements < self.cpus {
             1
@@ -86,7 +110,7 @@ impl Worker {
 
         crossbeam::scope(|scope| {
             f(scope, chunk_size)
-        }).expect("must run")
+        }).map_err(|_| SynthesisError::WorkerFailure)
     }
 }
 
@@ -119,6 +143,17 @@ impl<T: Send + 'static, E: Send + 'static> WorkerFuture<T, E> {
     pub fn wait(self) -> <Self as Future>::Output {
         block_on(self)
     }
+
+    /// Same as `wait`, but a computation that was dropped before
+    /// sending its result is reported as an error.
+    pub fn try_wait(self) -> Result<T, E>
+        where E: From<SynthesisError>
+    {
+        match block_on(self.receiver) {
+            Ok(result) => result,
+            Err(_) => Err(SynthesisError::WorkerFailure.into())
+        }
+    }
 }
 
 fn log2_floor(num: usize) -> u32 {
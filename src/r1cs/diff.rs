@@ -0,0 +1,363 @@
+//! Structural comparison of two versions of a circuit.
+//!
+//! Allocations and constraints are aligned by their namespace paths, and
+//! constraints are compared as equations over variable names, so inserting
+//! a variable doesn't make every later constraint look different. Whether
+//! new keys are needed is decided separately on the raw matrices, since any
+//! change to them, including a reordering, changes the parameters.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::pairing::ff::{
+    Field
+};
+
+use crate::{
+    SynthesisError,
+    Circuit,
+    Index
+};
+
+use super::{
+    R1CS,
+    Constraint,
+    format_value
+};
+
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change<T> {
+    Added(T),
+    Removed(T),
+    Modified {
+        old: T,
+        new: T
+    }
+}
+
+/// An allocation, identified by its path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AllocationEntry {
+    pub path: String,
+    pub index: Index
+}
+
+impl fmt::Display for AllocationEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self.index {
+            Index::Input(i) => write!(f, "input {} `{}`", i, self.path),
+            Index::Aux(i) => write!(f, "aux {} `{}`", i, self.path)
+        }
+    }
+}
+
+/// A constraint, identified by its annotation path. The equation
+/// refers to variables by name, with terms sorted by name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConstraintEntry {
+    pub path: String,
+    pub number: usize,
+    pub equation: String
+}
+
+impl fmt::Display for ConstraintEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "constraint {} `{}`: {}", self.number, self.path, self.equation)
+    }
+}
+
+/// The differences between two constraint systems.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct R1CSDiff {
+    pub allocations: Vec<Change<AllocationEntry>>,
+    pub constraints: Vec<Change<ConstraintEntry>>,
+
+    // Whether the matrices differ, so parameters have to be regenerated
+    pub keys_changed: bool
+}
+
+impl R1CSDiff {
+    pub fn is_empty(&self) -> bool {
+        self.allocations.is_empty() && self.constraints.is_empty()
+    }
+}
+
+impl fmt::Display for R1CSDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fn write_change<T: fmt::Display>(f: &mut fmt::Formatter, change: &Change<T>) -> Result<(), fmt::Error> {
+            match *change {
+                Change::Added(ref new) => writeln!(f, "+ {}", new),
+                Change::Removed(ref old) => writeln!(f, "- {}", old),
+                Change::Modified { ref old, ref new } => {
+                    writeln!(f, "~ {}", old)?;
+                    writeln!(f, "  becomes {}", new)
+                }
+            }
+        }
+
+        for change in self.allocations.iter() {
+            write_change(f, change)?;
+        }
+        for change in self.constraints.iter() {
+            write_change(f, change)?;
+        }
+
+        if self.keys_changed {
+            writeln!(f, "parameters must be regenerated")
+        } else {
+            writeln!(f, "parameters are unchanged")
+        }
+    }
+}
+
+/// Synthesizes the shapes of both circuits and compares them.
+pub fn diff<E, C1, C2>(
+    old: C1,
+    new: C2
+) -> Result<R1CSDiff, SynthesisError>
+    where E: Engine, C1: Circuit<E>, C2: Circuit<E>
+{
+    let old = R1CS::record(old)?;
+    let new = R1CS::record(new)?;
+
+    Ok(diff_r1cs(&old, &new))
+}
+
+pub fn diff_r1cs<E: Engine>(old: &R1CS<E>, new: &R1CS<E>) -> R1CSDiff {
+    let allocations = align(
+        allocations(old),
+        allocations(new),
+        |entry| entry.path.clone(),
+        |old, new| {
+            // Moving aux variables around doesn't matter to anyone, but
+            // the position of an input is part of the statement
+            match (old.index, new.index) {
+                (Index::Aux(_), Index::Aux(_)) => false,
+                (a, b) => a != b
+            }
+        }
+    );
+
+    let constraints = align(
+        constraints(old),
+        constraints(new),
+        |entry| entry.path.clone(),
+        |old, new| old.equation != new.equation
+    );
+
+    let keys_changed = old.num_inputs() != new.num_inputs() ||
+                       old.num_aux() != new.num_aux() ||
+                       old.num_constraints() != new.num_constraints() ||
+                       old.constraints.iter().zip(new.constraints.iter()).any(|(a, b)| {
+                           a.a != b.a || a.b != b.b || a.c != b.c
+                       });
+
+    R1CSDiff {
+        allocations: allocations,
+        constraints: constraints,
+        keys_changed: keys_changed
+    }
+}
+
+fn allocations<E: Engine>(r1cs: &R1CS<E>) -> Vec<AllocationEntry> {
+    let inputs = r1cs.input_names.iter().enumerate().skip(1).map(|(i, path)| {
+        AllocationEntry {
+            path: path.clone(),
+            index: Index::Input(i)
+        }
+    });
+    let aux = r1cs.aux_names.iter().enumerate().map(|(i, path)| {
+        AllocationEntry {
+            path: path.clone(),
+            index: Index::Aux(i)
+        }
+    });
+
+    inputs.chain(aux).collect()
+}
+
+fn constraints<E: Engine>(r1cs: &R1CS<E>) -> Vec<ConstraintEntry> {
+    r1cs.constraints.iter().enumerate().map(|(i, constraint)| {
+        ConstraintEntry {
+            path: constraint.annotation.clone(),
+            number: i,
+            equation: equation(r1cs, constraint)
+        }
+    }).collect()
+}
+
+/// Matches entries by key, pairing up repeated keys in order of appearance.
+/// Removed and modified entries come first in old order, then additions
+/// in new order.
+fn align<T, K, M>(
+    old: Vec<T>,
+    new: Vec<T>,
+    key: K,
+    modified: M
+) -> Vec<Change<T>>
+    where K: Fn(&T) -> String, M: Fn(&T, &T) -> bool
+{
+    fn keyed<T, K: Fn(&T) -> String>(entries: &[T], key: &K) -> Vec<(String, usize)> {
+        let mut seen = HashMap::new();
+        entries.iter().map(|entry| {
+            let k = key(entry);
+            let occurrence = seen.entry(k.clone()).or_insert(0);
+            *occurrence += 1;
+
+            (k, *occurrence)
+        }).collect()
+    }
+
+    let old_keys = keyed(&old, &key);
+    let new_keys = keyed(&new, &key);
+
+    let new_positions: HashMap<&(String, usize), usize> = new_keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+    let mut matched = vec![false; new.len()];
+
+    let mut changes = vec![];
+    let mut new: Vec<Option<T>> = new.into_iter().map(Some).collect();
+    for (entry, k) in old.into_iter().zip(old_keys.iter()) {
+        match new_positions.get(k) {
+            Some(&i) => {
+                matched[i] = true;
+                if modified(&entry, new[i].as_ref().unwrap()) {
+                    changes.push(Change::Modified {
+                        old: entry,
+                        new: new[i].take().unwrap()
+                    });
+                }
+            },
+            None => changes.push(Change::Removed(entry))
+        }
+    }
+
+    for (i, entry) in new.into_iter().enumerate() {
+        if !matched[i] {
+            changes.push(Change::Added(entry.unwrap()));
+        }
+    }
+
+    changes
+}
+
+fn equation<E: Engine>(r1cs: &R1CS<E>, constraint: &Constraint<E>) -> String {
+    let lc = |terms: &[(Index, E::Fr)]| {
+        let mut rendered: Vec<(&str, String)> = terms.iter().map(|&(index, ref coeff)| {
+            if index == Index::Input(0) {
+                ("", format_value(coeff))
+            } else {
+                let name = r1cs.name_of(index);
+                let mut minus_one = E::Fr::one();
+                minus_one.negate();
+
+                let term = if *coeff == E::Fr::one() {
+                    format!("`{}`", name)
+                } else if *coeff == minus_one {
+                    format!("-`{}`", name)
+                } else {
+                    format!("{} `{}`", format_value(coeff), name)
+                };
+
+                (name, term)
+            }
+        }).collect();
+        rendered.sort();
+
+        if rendered.is_empty() {
+            "0".to_string()
+        } else {
+            rendered.into_iter().map(|(_, term)| term).collect::<Vec<_>>().join(" + ")
+        }
+    };
+
+    format!("({}) * ({}) = ({})", lc(&constraint.a), lc(&constraint.b), lc(&constraint.c))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bls12_381::Bls12;
+    use crate::ConstraintSystem;
+
+    #[derive(Clone, Copy)]
+    struct Versioned {
+        version: u32
+    }
+
+    impl Circuit<Bls12> for Versioned {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS
+        ) -> Result<(), SynthesisError>
+        {
+            let x = cs.alloc_input(|| "x", || Err(SynthesisError::AssignmentMissing))?;
+            let a = cs.alloc(|| "a", || Err(SynthesisError::AssignmentMissing))?;
+
+            {
+                let mut cs = cs.namespace(|| "square");
+                cs.enforce(|| "sq", |lc| lc + a, |lc| lc + a, |lc| lc + x);
+            }
+
+            if self.version == 1 {
+                let b = cs.alloc(|| "b", || Err(SynthesisError::AssignmentMissing))?;
+                cs.enforce(|| "double", |lc| lc + a + a, |lc| lc + CS::one(), |lc| lc + b);
+                cs.enforce(|| "shift", |lc| lc + b - CS::one(), |lc| lc + CS::one(), |lc| lc + x);
+            } else {
+                let b = cs.alloc_input(|| "b", || Err(SynthesisError::AssignmentMissing))?;
+                cs.enforce(|| "double", |lc| lc + a + a, |lc| lc + CS::one(), |lc| lc + b);
+                cs.enforce(|| "shift", |lc| lc + b + CS::one(), |lc| lc + CS::one(), |lc| lc + x);
+
+                let c = cs.alloc(|| "c", || Err(SynthesisError::AssignmentMissing))?;
+                cs.enforce(|| "c", |lc| lc + c, |lc| lc + c, |lc| lc + b);
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_diff_versions() {
+        let v1 = Versioned { version: 1 };
+        let v2 = Versioned { version: 2 };
+
+        let same = diff(v1, v1).unwrap();
+        assert!(same.is_empty());
+        assert!(!same.keys_changed);
+
+        let changes = diff(v1, v2).unwrap();
+        assert!(changes.keys_changed);
+
+        assert_eq!(changes.allocations, vec![
+            Change::Modified {
+                old: AllocationEntry { path: "b".into(), index: Index::Aux(1) },
+                new: AllocationEntry { path: "b".into(), index: Index::Input(2) }
+            },
+            Change::Added(AllocationEntry { path: "c".into(), index: Index::Aux(1) })
+        ]);
+
+        // "double" is unchanged even though `b` has a different index now
+        let paths: Vec<_> = changes.constraints.iter().map(|c| match *c {
+            Change::Added(ref e) => ("+", e.path.as_str()),
+            Change::Removed(ref e) => ("-", e.path.as_str()),
+            Change::Modified { ref new, .. } => ("~", new.path.as_str())
+        }).collect();
+        assert_eq!(paths, vec![("~", "shift"), ("+", "c")]);
+
+        match changes.constraints[0] {
+            Change::Modified { ref old, ref new } => {
+                assert_eq!(old.equation, "(-1 + `b`) * (1) = (`x`)");
+                assert_eq!(new.equation, "(1 + `b`) * (1) = (`x`)");
+            },
+            _ => unreachable!()
+        }
+
+        let text = changes.to_string();
+        assert!(text.contains("+ aux 1 `c`"));
+        assert!(text.ends_with("parameters must be regenerated\n"));
+    }
+}
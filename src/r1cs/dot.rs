@@ -0,0 +1,315 @@
+//! Export of the structure of a circuit as a Graphviz graph.
+//!
+//! Namespaces become nested clusters and variables become nodes. Every
+//! constraint is drawn as a point with edges from the variables of `A` and
+//! `B` into it and edges out of it to the variables of `C`, so the graph
+//! roughly follows the flow of the computation. Namespaces nested deeper
+//! than the cutoff are collapsed into a single node, which keeps the graph
+//! of a large circuit readable at the level of its gadgets. The constant
+//! "one" is left out, since nearly every constraint refers to it.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::{
+    SynthesisError,
+    Circuit,
+    Index
+};
+
+use super::R1CS;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+
+/// Synthesizes the shape of the circuit and writes its graph. With
+/// `max_depth` set, only that many levels of namespaces are drawn as
+/// clusters, and the namespaces below are collapsed.
+pub fn write_circuit_dot<E, C, W>(
+    circuit: C,
+    max_depth: Option<usize>,
+    writer: W
+) -> Result<(), SynthesisError>
+    where E: Engine, C: Circuit<E>, W: Write
+{
+    let r1cs = R1CS::record(circuit)?;
+    write_dot(&r1cs, max_depth, writer)?;
+
+    Ok(())
+}
+
+pub fn write_dot<E: Engine, W: Write>(
+    r1cs: &R1CS<E>,
+    max_depth: Option<usize>,
+    mut writer: W
+) -> io::Result<()>
+{
+    let mut graph = Graph::new(max_depth);
+
+    // Variable nodes
+    let mut variable_nodes = BTreeMap::new();
+    for i in 1..r1cs.num_inputs() {
+        let node = graph.add(&r1cs.input_names[i], format!("i{}", i), NodeKind::Input);
+        variable_nodes.insert(Index::Input(i), node);
+    }
+    for i in 0..r1cs.num_aux() {
+        let node = graph.add(&r1cs.aux_names[i], format!("a{}", i), NodeKind::Aux);
+        variable_nodes.insert(Index::Aux(i), node);
+    }
+
+    // Constraint hyperedges
+    let mut edges = BTreeSet::new();
+    for (i, constraint) in r1cs.constraints.iter().enumerate() {
+        let node = graph.add(&constraint.annotation, format!("c{}", i), NodeKind::Constraint);
+
+        for &(index, _) in constraint.a.iter().chain(constraint.b.iter()) {
+            if let Some(var) = variable_nodes.get(&index) {
+                if *var != node {
+                    edges.insert((var.clone(), node.clone()));
+                }
+            }
+        }
+        for &(index, _) in constraint.c.iter() {
+            if let Some(var) = variable_nodes.get(&index) {
+                if *var != node {
+                    edges.insert((node.clone(), var.clone()));
+                }
+            }
+        }
+    }
+
+    writeln!(writer, "digraph circuit {{")?;
+    writeln!(writer, "  node [fontsize=10];")?;
+    graph.write_namespace(&mut writer, "", 1)?;
+    for (from, to) in edges.iter() {
+        writeln!(writer, "  {} -> {};", from, to)?;
+    }
+    writeln!(writer, "}}")
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Splits a path into its namespace and its own name.
+fn split(path: &str) -> (&str, &str) {
+    match path.rfind('/') {
+        Some(i) => (&path[..i], &path[i + 1..]),
+        None => ("", path)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum NodeKind {
+    Input,
+    Aux,
+    Constraint
+}
+
+struct Collapsed {
+    id: String,
+    variables: usize,
+    constraints: usize
+}
+
+struct Graph {
+    max_depth: Option<usize>,
+
+    // Node lines and child namespaces of every drawn namespace
+    nodes: BTreeMap<String, Vec<String>>,
+    children: BTreeMap<String, BTreeSet<String>>,
+
+    // Namespaces collapsed into a single node, by path
+    collapsed: BTreeMap<String, Collapsed>
+}
+
+impl Graph {
+    fn new(max_depth: Option<usize>) -> Self {
+        Graph {
+            max_depth: max_depth,
+            nodes: BTreeMap::new(),
+            children: BTreeMap::new(),
+            collapsed: BTreeMap::new()
+        }
+    }
+
+    /// Makes sure the namespace and all of its parents are drawn.
+    fn register(&mut self, namespace: &str) {
+        let mut current = namespace.to_string();
+        while !current.is_empty() {
+            let (parent, _) = split(&current);
+            let parent = parent.to_string();
+
+            self.children.entry(parent.clone()).or_default().insert(current.clone());
+            current = parent;
+        }
+    }
+
+    /// Adds a variable or constraint and returns the id of the node
+    /// representing it, which is a collapsed namespace if it's too deep.
+    fn add(&mut self, path: &str, id: String, kind: NodeKind) -> String {
+        let (namespace, name) = split(path);
+        let components: Vec<&str> = if namespace.is_empty() {
+            vec![]
+        } else {
+            namespace.split('/').collect()
+        };
+
+        if let Some(depth) = self.max_depth {
+            if components.len() > depth {
+                let collapsed_path = components[..depth + 1].join("/");
+
+                if !self.collapsed.contains_key(&collapsed_path) {
+                    let id = format!("n{}", self.collapsed.len());
+                    self.register(split(&collapsed_path).0);
+                    self.collapsed.insert(collapsed_path.clone(), Collapsed {
+                        id: id,
+                        variables: 0,
+                        constraints: 0
+                    });
+                }
+
+                let collapsed = self.collapsed.get_mut(&collapsed_path).unwrap();
+                if kind == NodeKind::Constraint {
+                    collapsed.constraints += 1;
+                } else {
+                    collapsed.variables += 1;
+                }
+
+                return collapsed.id.clone();
+            }
+        }
+
+        let attributes = match kind {
+            NodeKind::Input => "shape=doublecircle",
+            NodeKind::Aux => "shape=ellipse",
+            NodeKind::Constraint => "shape=point"
+        };
+
+        self.register(namespace);
+        let line = format!("{} [{}, label=\"{}\", tooltip=\"{}\"];", id, attributes, escape(name), escape(path));
+        self.nodes.entry(namespace.to_string()).or_default().push(line);
+
+        id
+    }
+
+    fn write_namespace<W: Write>(
+        &self,
+        writer: &mut W,
+        namespace: &str,
+        indent: usize
+    ) -> io::Result<()>
+    {
+        let pad = "  ".repeat(indent);
+
+        if let Some(nodes) = self.nodes.get(namespace) {
+            for line in nodes.iter() {
+                writeln!(writer, "{}{}", pad, line)?;
+            }
+        }
+
+        for (path, collapsed) in self.collapsed.iter() {
+            let (parent, name) = split(path);
+            if parent == namespace {
+                writeln!(
+                    writer,
+                    "{}{} [shape=box3d, label=\"{}\\n({} variables, {} constraints)\", tooltip=\"{}\"];",
+                    pad,
+                    collapsed.id,
+                    escape(name),
+                    collapsed.variables,
+                    collapsed.constraints,
+                    escape(path)
+                )?;
+            }
+        }
+
+        if let Some(children) = self.children.get(namespace) {
+            for child in children.iter() {
+                // Collapsed namespaces were drawn as nodes above
+                if self.collapsed.contains_key(child) {
+                    continue;
+                }
+
+                writeln!(writer, "{}subgraph \"cluster_{}\" {{", pad, escape(child))?;
+                writeln!(writer, "{}  label=\"{}\";", pad, escape(split(child).1))?;
+                self.write_namespace(writer, child, indent + 1)?;
+                writeln!(writer, "{}}}", pad)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bls12_381::Bls12;
+    use crate::ConstraintSystem;
+
+    struct Rounds;
+
+    impl Circuit<Bls12> for Rounds {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS
+        ) -> Result<(), SynthesisError>
+        {
+            let x = cs.alloc_input(|| "x", || Err(SynthesisError::AssignmentMissing))?;
+
+            let mut last = x;
+            {
+                let mut cs = cs.namespace(|| "hash");
+                for i in 0..2 {
+                    let mut cs = cs.namespace(|| format!("round {}", i));
+                    let t = cs.alloc(|| "t", || Err(SynthesisError::AssignmentMissing))?;
+                    cs.enforce(|| "square", |lc| lc + last, |lc| lc + last, |lc| lc + t);
+                    last = t;
+                }
+            }
+
+            let out = cs.alloc(|| "out", || Err(SynthesisError::AssignmentMissing))?;
+            cs.enforce(|| "copy", |lc| lc + last, |lc| lc + CS::one(), |lc| lc + out);
+
+            Ok(())
+        }
+    }
+
+    fn dot(max_depth: Option<usize>) -> String {
+        let mut v = vec![];
+        write_circuit_dot(Rounds, max_depth, &mut v).unwrap();
+
+        String::from_utf8(v).unwrap()
+    }
+
+    #[test]
+    fn test_dot_export() {
+        let full = dot(None);
+        assert!(full.starts_with("digraph circuit {"));
+        assert!(full.contains("subgraph \"cluster_hash\" {"));
+        assert!(full.contains("subgraph \"cluster_hash/round 1\" {"));
+        assert!(full.contains("a0 [shape=ellipse, label=\"t\", tooltip=\"hash/round 0/t\"];"));
+        assert!(full.contains("i1 -> c0;"));
+        assert!(full.contains("c0 -> a0;"));
+        assert!(full.contains("a1 -> c2;"));
+
+        // Only the rounds are collapsed
+        let gadgets = dot(Some(1));
+        assert!(gadgets.contains("subgraph \"cluster_hash\" {"));
+        assert!(!gadgets.contains("cluster_hash/round 0"));
+        assert!(gadgets.contains("n0 [shape=box3d, label=\"round 0\\n(1 variables, 1 constraints)\""));
+        assert!(gadgets.contains("i1 -> n0;"));
+        assert!(gadgets.contains("n1 -> c2;"));
+
+        // The whole hash is a single node
+        let top = dot(Some(0));
+        assert!(!top.contains("cluster_"));
+        assert!(top.contains("n0 [shape=box3d, label=\"hash\\n(2 variables, 2 constraints)\""));
+        assert!(top.contains("i1 -> n0;"));
+        assert!(top.contains("n0 -> c2;"));
+        assert!(!top.contains("n0 -> n0;"));
+    }
+}
@@ -0,0 +1,463 @@
+//! Reading and writing of the iden3 binary `.r1cs` format, as produced
+//! by circom and consumed by snarkjs and other tooling.
+//!
+//! Wire 0 is the constant "one", followed by the public inputs and then
+//! by the auxillary variables. Field elements are stored little-endian in
+//! their canonical (non-Montgomery) form. Annotations are not part of the
+//! format itself; every wire gets a label equal to its index, and the names
+//! behind the labels can be exported separately in the circom `.sym` format.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::pairing::ff::{
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use crate::Index;
+
+use super::{
+    R1CS,
+    Constraint
+};
+
+use std::convert::TryFrom;
+use std::io::{self, Read, Write, BufRead};
+use byteorder::{LittleEndian, WriteBytesExt, ReadBytesExt};
+
+const MAGIC: &[u8; 4] = b"r1cs";
+const VERSION: u32 = 1;
+
+const HEADER_SECTION: u32 = 1;
+const CONSTRAINTS_SECTION: u32 = 2;
+const WIRE_TO_LABEL_SECTION: u32 = 3;
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
+
+fn field_size<E: Engine>() -> usize {
+    <E::Fr as PrimeField>::Repr::default().as_ref().len() * 8
+}
+
+fn wire_of(num_inputs: usize, index: Index) -> u32 {
+    match index {
+        Index::Input(i) => i as u32,
+        Index::Aux(i) => (num_inputs + i) as u32
+    }
+}
+
+fn index_of(num_inputs: usize, wire: u32) -> Index {
+    let wire = wire as usize;
+    if wire < num_inputs {
+        Index::Input(wire)
+    } else {
+        Index::Aux(wire - num_inputs)
+    }
+}
+
+/// Writes the constraint system in the iden3 `.r1cs` format.
+pub fn write<E: Engine, W: Write>(
+    r1cs: &R1CS<E>,
+    mut writer: W
+) -> io::Result<()>
+{
+    let n8 = field_size::<E>();
+    let num_inputs = r1cs.num_inputs();
+    let num_wires = num_inputs + r1cs.num_aux();
+    if u32::try_from(num_wires).is_err() || u32::try_from(r1cs.num_constraints()).is_err() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "too many wires or constraints for the format"));
+    }
+
+    let mut header = vec![];
+    header.write_u32::<LittleEndian>(n8 as u32)?;
+    E::Fr::char().write_le(&mut header)?;
+    header.write_u32::<LittleEndian>(num_wires as u32)?;
+    // Outputs are not distinguished from inputs
+    header.write_u32::<LittleEndian>(0)?;
+    header.write_u32::<LittleEndian>((num_inputs - 1) as u32)?;
+    // Private inputs are not distinguished from the rest of the witness
+    header.write_u32::<LittleEndian>(0)?;
+    header.write_u64::<LittleEndian>(num_wires as u64)?;
+    header.write_u32::<LittleEndian>(r1cs.num_constraints() as u32)?;
+
+    let mut constraints = vec![];
+    for constraint in r1cs.constraints.iter() {
+        for terms in [&constraint.a, &constraint.b, &constraint.c].iter() {
+            constraints.write_u32::<LittleEndian>(terms.len() as u32)?;
+            for &(index, coeff) in terms.iter() {
+                constraints.write_u32::<LittleEndian>(wire_of(num_inputs, index))?;
+                coeff.into_repr().write_le(&mut constraints)?;
+            }
+        }
+    }
+
+    let mut wire_to_label = vec![];
+    for wire in 0..num_wires {
+        wire_to_label.write_u64::<LittleEndian>(wire as u64)?;
+    }
+
+    writer.write_all(MAGIC)?;
+    writer.write_u32::<LittleEndian>(VERSION)?;
+    writer.write_u32::<LittleEndian>(3)?;
+
+    for &(section_type, ref section) in [
+        (HEADER_SECTION, header),
+        (CONSTRAINTS_SECTION, constraints),
+        (WIRE_TO_LABEL_SECTION, wire_to_label)
+    ].iter() {
+        writer.write_u32::<LittleEndian>(section_type)?;
+        writer.write_u64::<LittleEndian>(section.len() as u64)?;
+        writer.write_all(section)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the names of all wires in the circom `.sym` format, one
+/// `label,wire,component,name` line per wire. Namespaces do not map to
+/// circom components, so the component is always 0.
+pub fn write_symbols<E: Engine, W: Write>(
+    r1cs: &R1CS<E>,
+    mut writer: W
+) -> io::Result<()>
+{
+    let names = r1cs.input_names.iter().chain(r1cs.aux_names.iter());
+    for (wire, name) in names.enumerate() {
+        writeln!(writer, "{},{},0,{}", wire, wire, name)?;
+    }
+
+    Ok(())
+}
+
+/// Reads a constraint system in the iden3 `.r1cs` format. All public outputs
+/// and public inputs become inputs, everything else becomes auxillary. The
+/// result carries no witness and names every variable after its label.
+pub fn read<E: Engine, R: Read>(
+    mut reader: R
+) -> io::Result<R1CS<E>>
+{
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("not an r1cs file"));
+    }
+
+    let version = reader.read_u32::<LittleEndian>()?;
+    if version != VERSION {
+        return Err(invalid_data("unsupported r1cs version"));
+    }
+
+    // Sections may come in any order, so collect them first
+    let num_sections = reader.read_u32::<LittleEndian>()?;
+    let mut header = None;
+    let mut constraints = None;
+    let mut wire_to_label = None;
+    for _ in 0..num_sections {
+        let section_type = reader.read_u32::<LittleEndian>()?;
+        let size = reader.read_u64::<LittleEndian>()?;
+
+        // The size is untrusted, so the section grows only with
+        // the data actually read
+        let mut section = vec![];
+        (&mut reader).take(size).read_to_end(&mut section)?;
+        if section.len() as u64 != size {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "section is truncated"));
+        }
+
+        match section_type {
+            HEADER_SECTION => header = Some(section),
+            CONSTRAINTS_SECTION => constraints = Some(section),
+            WIRE_TO_LABEL_SECTION => wire_to_label = Some(section),
+            // Custom gates and other extensions are ignored
+            _ => {}
+        }
+    }
+
+    let header = header.ok_or_else(|| invalid_data("missing header section"))?;
+    let constraints = constraints.ok_or_else(|| invalid_data("missing constraints section"))?;
+
+    let n8 = field_size::<E>();
+    let mut header = &header[..];
+    if header.read_u32::<LittleEndian>()? as usize != n8 {
+        return Err(invalid_data("field size does not match the engine"));
+    }
+    let mut prime = <E::Fr as PrimeField>::Repr::default();
+    prime.read_le(&mut header)?;
+    if prime != E::Fr::char() {
+        return Err(invalid_data("prime does not match the engine"));
+    }
+    let num_wires = header.read_u32::<LittleEndian>()? as usize;
+    let num_public_outputs = header.read_u32::<LittleEndian>()? as usize;
+    let num_public_inputs = header.read_u32::<LittleEndian>()? as usize;
+    let _num_private_inputs = header.read_u32::<LittleEndian>()?;
+    let _num_labels = header.read_u64::<LittleEndian>()?;
+    let num_constraints = header.read_u32::<LittleEndian>()? as usize;
+
+    let num_inputs = 1 + num_public_outputs + num_public_inputs;
+    if num_inputs > num_wires {
+        return Err(invalid_data("more public wires than wires"));
+    }
+
+    let labels = match wire_to_label {
+        Some(section) => {
+            if section.len() / 8 < num_wires {
+                return Err(invalid_data("wire to label section is truncated"));
+            }
+
+            let mut section = &section[..];
+            let mut labels = Vec::with_capacity(num_wires);
+            for _ in 0..num_wires {
+                labels.push(section.read_u64::<LittleEndian>()?);
+            }
+
+            Some(labels)
+        },
+        None => {
+            // Without labels nothing else bounds the wire count, so
+            // every wire but "one" must fit a term of the constraints
+            if constraints.len() / (4 + n8) < num_wires - 1 {
+                return Err(invalid_data("more wires than the constraints section can reference"));
+            }
+
+            None
+        }
+    };
+
+    let mut section = &constraints[..];
+    let read_terms = |section: &mut &[u8]| -> io::Result<Vec<(Index, E::Fr)>> {
+        let num_terms = section.read_u32::<LittleEndian>()? as usize;
+        if section.len() / (4 + n8) < num_terms {
+            return Err(invalid_data("constraints section is truncated"));
+        }
+
+        let mut terms = Vec::with_capacity(num_terms);
+        for _ in 0..num_terms {
+            let wire = section.read_u32::<LittleEndian>()?;
+            if wire as usize >= num_wires {
+                return Err(invalid_data("wire index is out of range"));
+            }
+
+            let mut repr = <E::Fr as PrimeField>::Repr::default();
+            repr.read_le(&mut *section)?;
+            let coeff = E::Fr::from_repr(repr).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+            terms.push((index_of(num_inputs, wire), coeff));
+        }
+
+        Ok(terms)
+    };
+
+    // Every constraint takes at least three term counts
+    if constraints.len() / 12 < num_constraints {
+        return Err(invalid_data("constraints section is truncated"));
+    }
+
+    let mut recorded = Vec::with_capacity(num_constraints);
+    for i in 0..num_constraints {
+        let a = read_terms(&mut section)?;
+        let b = read_terms(&mut section)?;
+        let c = read_terms(&mut section)?;
+
+        recorded.push(Constraint {
+            a: a,
+            b: b,
+            c: c,
+            annotation: format!("constraint {}", i)
+        });
+    }
+
+    // Wires are their own labels when there are none
+    let label = |wire: usize| match labels {
+        Some(ref labels) => format!("label {}", labels[wire]),
+        None => format!("label {}", wire)
+    };
+
+    let mut input_names = vec!["ONE".to_owned()];
+    input_names.extend((1..num_inputs).map(&label));
+    let aux_names = (num_inputs..num_wires).map(&label).collect();
+
+    Ok(R1CS {
+        input_names: input_names,
+        aux_names: aux_names,
+        constraints: recorded,
+        input_assignment: None,
+        aux_assignment: None
+    })
+}
+
+/// Restores variable names from a circom `.sym` file.
+pub fn read_symbols<E: Engine, R: BufRead>(
+    r1cs: &mut R1CS<E>,
+    reader: R
+) -> io::Result<()>
+{
+    let num_inputs = r1cs.num_inputs();
+
+    for line in reader.lines() {
+        let line = line?;
+        let fields: Vec<&str> = line.splitn(4, ',').collect();
+        if fields.len() != 4 {
+            return Err(invalid_data("malformed symbol line"));
+        }
+
+        // Signals optimized away by circom have a negative wire
+        let wire: i64 = fields[1].parse().map_err(|_| invalid_data("malformed wire index"))?;
+        if wire < 0 {
+            continue;
+        }
+        let wire = u32::try_from(wire).map_err(|_| invalid_data("wire index is out of range"))?;
+
+        let name = fields[3].to_owned();
+        match index_of(num_inputs, wire) {
+            Index::Input(i) if i < num_inputs => r1cs.input_names[i] = name,
+            Index::Aux(i) if i < r1cs.num_aux() => r1cs.aux_names[i] = name,
+            _ => return Err(invalid_data("wire index is out of range"))
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bn256::{Bn256, Fr};
+    use crate::pairing::ff::Field;
+    use crate::tests::XORDemo;
+    use crate::groth16::{
+        generate_random_parameters,
+        prepare_verifying_key,
+        create_random_proof,
+        verify_proof
+    };
+
+    use rand::thread_rng;
+    use std::marker::PhantomData;
+
+    #[test]
+    fn test_iden3_roundtrip() {
+        let rng = &mut thread_rng();
+
+        let recorded = R1CS::<Bn256>::record_with_witness(XORDemo {
+            a: Some(false),
+            b: Some(true),
+            _marker: PhantomData
+        }).unwrap();
+
+        let mut v = vec![];
+        write(&recorded, &mut v).unwrap();
+        assert_eq!(&v[0..4], b"r1cs");
+
+        let mut symbols = vec![];
+        write_symbols(&recorded, &mut symbols).unwrap();
+        assert_eq!(String::from_utf8(symbols.clone()).unwrap(), "0,0,0,ONE\n1,1,0,c\n2,2,0,a\n3,3,0,b\n");
+
+        let mut read_back = read::<Bn256, _>(&v[..]).unwrap();
+        assert_eq!(read_back.num_inputs(), 2);
+        assert_eq!(read_back.num_aux(), 2);
+        assert_eq!(read_back.aux_names[1], "label 3");
+
+        read_symbols(&mut read_back, &symbols[..]).unwrap();
+        assert_eq!(read_back.input_names, recorded.input_names);
+        assert_eq!(read_back.aux_names, recorded.aux_names);
+
+        for (expected, actual) in recorded.constraints.iter().zip(read_back.constraints.iter()) {
+            assert_eq!(expected.a, actual.a);
+            assert_eq!(expected.b, actual.b);
+            assert_eq!(expected.c, actual.c);
+        }
+
+        // The file must be usable for both key generation and proving
+        let params = generate_random_parameters(&read_back, rng).unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+
+        read_back.set_witness(
+            recorded.input_assignment.clone().unwrap(),
+            recorded.aux_assignment.clone().unwrap()
+        );
+        let proof = create_random_proof(&read_back, &params, rng).unwrap();
+
+        assert!(verify_proof(&pvk, &proof, &[Fr::one()]).unwrap());
+    }
+
+    #[test]
+    fn test_iden3_rejects_wrong_field() {
+        let recorded = R1CS::<Bn256>::record(XORDemo {
+            a: None,
+            b: None,
+            _marker: PhantomData
+        }).unwrap();
+
+        let mut v = vec![];
+        write(&recorded, &mut v).unwrap();
+
+        assert!(read::<crate::pairing::bls12_381::Bls12, _>(&v[..]).is_err());
+    }
+
+    #[test]
+    fn test_iden3_rejects_malformed_sizes() {
+        let recorded = R1CS::<Bn256>::record(XORDemo {
+            a: None,
+            b: None,
+            _marker: PhantomData
+        }).unwrap();
+
+        let mut v = vec![];
+        write(&recorded, &mut v).unwrap();
+
+        // A header section claiming to be enormous must not be allocated
+        let mut huge = v[..12].to_vec();
+        huge.extend_from_slice(&HEADER_SECTION.to_le_bytes());
+        huge.extend_from_slice(&(1u64 << 40).to_le_bytes());
+        huge.extend_from_slice(&v[24..]);
+        assert_eq!(read::<Bn256, _>(&huge[..]).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+
+        // So must a constraint count beyond the constraints section
+        let mut r1cs = read::<Bn256, _>(&v[..]).unwrap();
+        let header_len = 4 + 32 + 4 * 4 + 8 + 4;
+        let count_offset = 12 + 12 + header_len - 4;
+        let mut many = v.clone();
+        many[count_offset..count_offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(read::<Bn256, _>(&many[..]).is_err());
+
+        // Wires beyond 32 bits are not truncated onto valid ones
+        let symbols = format!("1,{},0,overflow\n", (1u64 << 32) + 1);
+        assert!(read_symbols(&mut r1cs, symbols.as_bytes()).is_err());
+        assert_eq!(r1cs.input_names[1], "label 1");
+    }
+
+    #[test]
+    fn test_iden3_without_labels() {
+        let recorded = R1CS::<Bn256>::record(XORDemo {
+            a: None,
+            b: None,
+            _marker: PhantomData
+        }).unwrap();
+
+        let mut v = vec![];
+        write(&recorded, &mut v).unwrap();
+
+        // Keep the header and the constraints, which come first
+        let section_end = |start: usize| {
+            let mut size = [0u8; 8];
+            size.copy_from_slice(&v[start + 4..start + 12]);
+
+            start + 12 + u64::from_le_bytes(size) as usize
+        };
+        let mut unlabeled = v[..section_end(section_end(12))].to_vec();
+        unlabeled[8..12].copy_from_slice(&2u32.to_le_bytes());
+
+        let r1cs = read::<Bn256, _>(&unlabeled[..]).unwrap();
+        assert_eq!(r1cs.aux_names, vec!["label 2", "label 3"]);
+
+        // The wire count of the header is all there is to go by, so it
+        // must not exceed what the constraints can reference
+        let num_wires_offset = 12 + 12 + 4 + 32;
+        unlabeled[num_wires_offset..num_wires_offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(read::<Bn256, _>(&unlabeled[..]).is_err());
+    }
+}
@@ -0,0 +1,357 @@
+//! Static checks for common mistakes in circuits.
+//!
+//! The circuit is synthesized without a witness and the recorded constraint
+//! system is inspected for constraints that don't constrain anything, public
+//! inputs that are never bound, and allocations that are never used in a
+//! meaningful way. The findings are plain data, so that they can be compared
+//! against an accepted baseline to reject only newly introduced problems.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::pairing::ff::{
+    Field
+};
+
+use crate::{
+    SynthesisError,
+    ErrorLocation,
+    Circuit,
+    Index
+};
+
+use super::{
+    R1CS,
+    Constraint,
+    constant_of
+};
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LintKind {
+    /// `A`, `B` and `C` are all empty
+    EmptyConstraint,
+    /// `A` or `B` is empty, so the constraint only says `C = 0`
+    EmptyFactor,
+    /// `A * B = C` holds for every assignment
+    TriviallySatisfied,
+    /// `A * B = C` holds for no assignment
+    NeverSatisfied,
+    /// A public input that appears in no constraint, and is therefore
+    /// not bound to the proof
+    UnusedInput,
+    /// An auxillary variable that appears in no constraint
+    UnusedAux,
+    /// An auxillary variable that appears only in linear constraints,
+    /// so it could be substituted away
+    LinearOnlyAux,
+    /// A namespace with allocations but without any constraints
+    UnconstrainedNamespace
+}
+
+impl LintKind {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            LintKind::EmptyConstraint => "empty-constraint",
+            LintKind::EmptyFactor => "empty-factor",
+            LintKind::TriviallySatisfied => "trivially-satisfied",
+            LintKind::NeverSatisfied => "never-satisfied",
+            LintKind::UnusedInput => "unused-input",
+            LintKind::UnusedAux => "unused-aux",
+            LintKind::LinearOnlyAux => "linear-only-aux",
+            LintKind::UnconstrainedNamespace => "unconstrained-namespace"
+        }
+    }
+}
+
+/// A single finding. The location always carries the namespace path,
+/// and the constraint number or variable index where applicable.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lint {
+    pub kind: LintKind,
+    pub location: ErrorLocation
+}
+
+impl Lint {
+    pub fn path(&self) -> &str {
+        self.location.path.as_deref().unwrap_or("")
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}: {}", self.kind.name(), self.location)
+    }
+}
+
+/// All findings for a circuit, ordered by constraint, then
+/// by variable, then by namespace.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LintReport {
+    pub lints: Vec<Lint>
+}
+
+impl LintReport {
+    pub fn is_empty(&self) -> bool {
+        self.lints.is_empty()
+    }
+
+    /// Findings that are not in the baseline. Lints are matched by kind
+    /// and path only, since numbering shifts whenever the circuit changes.
+    pub fn new_since(&self, baseline: &LintReport) -> Vec<&Lint> {
+        let known: BTreeSet<(LintKind, &str)> = baseline.lints.iter().map(|l| (l.kind, l.path())).collect();
+
+        self.lints.iter().filter(|l| !known.contains(&(l.kind, l.path()))).collect()
+    }
+}
+
+impl fmt::Display for LintReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        for lint in self.lints.iter() {
+            writeln!(f, "{}", lint)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Synthesizes the shape of the circuit and lints it.
+pub fn lint<E: Engine, C: Circuit<E>>(circuit: C) -> Result<LintReport, SynthesisError> {
+    let r1cs = R1CS::record(circuit)?;
+
+    Ok(lint_r1cs(&r1cs))
+}
+
+pub fn lint_r1cs<E: Engine>(r1cs: &R1CS<E>) -> LintReport {
+    let mut lints = vec![];
+
+    let mut input_used = vec![false; r1cs.num_inputs()];
+    let mut aux_used = vec![false; r1cs.num_aux()];
+    let mut aux_nonlinear = vec![false; r1cs.num_aux()];
+
+    for (i, constraint) in r1cs.constraints.iter().enumerate() {
+        if let Some(kind) = check_constraint(constraint) {
+            lints.push(Lint {
+                kind: kind,
+                location: ErrorLocation::constraint(i).with_path(constraint.annotation.clone())
+            });
+        }
+
+        let linear = constant_of::<E>(&constraint.a).is_some() || constant_of::<E>(&constraint.b).is_some();
+        for &(index, _) in constraint.a.iter().chain(constraint.b.iter()).chain(constraint.c.iter()) {
+            match index {
+                Index::Input(j) => input_used[j] = true,
+                Index::Aux(j) => {
+                    aux_used[j] = true;
+                    aux_nonlinear[j] |= !linear;
+                }
+            }
+        }
+    }
+
+    // The first input is the constant one
+    for (j, &used) in input_used.iter().enumerate().skip(1) {
+        if !used {
+            lints.push(Lint {
+                kind: LintKind::UnusedInput,
+                location: ErrorLocation::variable(Index::Input(j)).with_path(r1cs.input_names[j].clone())
+            });
+        }
+    }
+
+    for j in 0..r1cs.num_aux() {
+        let kind = if !aux_used[j] {
+            LintKind::UnusedAux
+        } else if !aux_nonlinear[j] {
+            LintKind::LinearOnlyAux
+        } else {
+            continue;
+        };
+
+        lints.push(Lint {
+            kind: kind,
+            location: ErrorLocation::variable(Index::Aux(j)).with_path(r1cs.aux_names[j].clone())
+        });
+    }
+
+    for namespace in unconstrained_namespaces(r1cs) {
+        lints.push(Lint {
+            kind: LintKind::UnconstrainedNamespace,
+            location: ErrorLocation::path(namespace)
+        });
+    }
+
+    LintReport {
+        lints: lints
+    }
+}
+
+fn check_constraint<E: Engine>(constraint: &Constraint<E>) -> Option<LintKind> {
+    if constraint.a.is_empty() && constraint.b.is_empty() && constraint.c.is_empty() {
+        return Some(LintKind::EmptyConstraint);
+    }
+
+    // With a constant factor `k` the constraint is `k * L - C = 0`
+    // for the other factor `L`, which we can decide symbolically.
+    let remainder = if let Some(k) = constant_of::<E>(&constraint.a) {
+        Some(residual::<E>(k, &constraint.b, &constraint.c))
+    } else if let Some(k) = constant_of::<E>(&constraint.b) {
+        Some(residual::<E>(k, &constraint.a, &constraint.c))
+    } else {
+        None
+    };
+
+    match remainder {
+        Some(ref r) if r.is_empty() => return Some(LintKind::TriviallySatisfied),
+        Some(ref r) if constant_of::<E>(r).is_some() => return Some(LintKind::NeverSatisfied),
+        _ => {}
+    }
+
+    if constraint.a.is_empty() || constraint.b.is_empty() {
+        return Some(LintKind::EmptyFactor);
+    }
+
+    None
+}
+
+/// Computes `k * l - c` with merged terms, omitting zero coefficients.
+fn residual<E: Engine>(
+    k: E::Fr,
+    l: &[(Index, E::Fr)],
+    c: &[(Index, E::Fr)]
+) -> Vec<(Index, E::Fr)>
+{
+    let mut terms: Vec<(Index, E::Fr)> = vec![];
+    let mut add = |index: Index, coeff: E::Fr| {
+        match terms.iter_mut().find(|t| t.0 == index) {
+            Some(t) => t.1.add_assign(&coeff),
+            None => terms.push((index, coeff))
+        }
+    };
+
+    for &(index, coeff) in l.iter() {
+        let mut tmp = coeff;
+        tmp.mul_assign(&k);
+        add(index, tmp);
+    }
+    for &(index, coeff) in c.iter() {
+        let mut tmp = coeff;
+        tmp.negate();
+        add(index, tmp);
+    }
+
+    terms.retain(|t| !t.1.is_zero());
+
+    terms
+}
+
+/// Namespaces that contain allocations but no constraints. Only the
+/// outermost such namespace is reported, not every namespace inside it.
+fn unconstrained_namespaces<E: Engine>(r1cs: &R1CS<E>) -> Vec<String> {
+    fn parent(path: &str) -> Option<&str> {
+        path.rfind('/').map(|i| &path[..i])
+    }
+
+    let mut namespaces = BTreeSet::new();
+    for name in r1cs.input_names.iter().skip(1).chain(r1cs.aux_names.iter()) {
+        let mut current = parent(name);
+        while let Some(namespace) = current {
+            namespaces.insert(namespace);
+            current = parent(namespace);
+        }
+    }
+
+    let constrained = |namespace: &str| {
+        r1cs.constraints.iter().any(|c| {
+            c.annotation.len() > namespace.len() &&
+            c.annotation.starts_with(namespace) &&
+            c.annotation.as_bytes()[namespace.len()] == b'/'
+        })
+    };
+
+    namespaces.iter()
+              .filter(|n| !constrained(n))
+              .filter(|n| parent(n).map(&constrained).unwrap_or(true))
+              .map(|n| n.to_string())
+              .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bls12_381::Bls12;
+    use crate::tests::XORDemo;
+    use crate::ConstraintSystem;
+
+    use std::marker::PhantomData;
+
+    struct SloppyCircuit;
+
+    impl Circuit<Bls12> for SloppyCircuit {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS
+        ) -> Result<(), SynthesisError>
+        {
+            cs.alloc_input(|| "x", || Err(SynthesisError::AssignmentMissing))?;
+            let a = cs.alloc(|| "a", || Err(SynthesisError::AssignmentMissing))?;
+
+            cs.enforce(|| "empty", |lc| lc, |lc| lc, |lc| lc);
+            cs.enforce(|| "zero_factor", |lc| lc, |lc| lc + a, |lc| lc + a);
+            cs.enforce(|| "trivial", |lc| lc + CS::one(), |lc| lc + a, |lc| lc + a);
+            cs.enforce(|| "square", |lc| lc + a, |lc| lc + a, |lc| lc + a);
+
+            let b = cs.alloc(|| "b", || Err(SynthesisError::AssignmentMissing))?;
+            cs.enforce(|| "linear", |lc| lc + b, |lc| lc + CS::one(), |lc| lc + a);
+
+            {
+                let mut cs = cs.namespace(|| "gadget");
+                cs.alloc(|| "t", || Err(SynthesisError::AssignmentMissing))?;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_lint_sloppy_circuit() {
+        let report = lint::<Bls12, _>(SloppyCircuit).unwrap();
+
+        let found: Vec<_> = report.lints.iter().map(|l| (l.kind, l.path())).collect();
+        assert_eq!(found, vec![
+            (LintKind::EmptyConstraint, "empty"),
+            (LintKind::EmptyFactor, "zero_factor"),
+            (LintKind::TriviallySatisfied, "trivial"),
+            (LintKind::UnusedInput, "x"),
+            (LintKind::LinearOnlyAux, "b"),
+            (LintKind::UnusedAux, "gadget/t"),
+            (LintKind::UnconstrainedNamespace, "gadget")
+        ]);
+
+        assert_eq!(report.lints[1].location.constraint, Some(1));
+        assert_eq!(report.lints[3].location.variable, Some(Index::Input(1)));
+        assert_eq!(report.lints[2].to_string(), "trivially-satisfied: `trivial`, constraint 2");
+
+        let mut baseline = report.clone();
+        baseline.lints.retain(|l| l.kind != LintKind::UnusedInput);
+        let new = report.new_since(&baseline);
+        assert_eq!(new.len(), 1);
+        assert_eq!(new[0].kind, LintKind::UnusedInput);
+        assert!(report.new_since(&report).is_empty());
+    }
+
+    #[test]
+    fn test_lint_xordemo() {
+        let report = lint(XORDemo::<Bls12> {
+            a: None,
+            b: None,
+            _marker: PhantomData
+        }).unwrap();
+
+        assert!(report.is_empty());
+    }
+}
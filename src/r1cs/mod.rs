@@ -0,0 +1,591 @@
+//! Tools for working with the rank-1 constraint system of a circuit directly,
+//! independently of any proving system. A circuit is synthesized into an
+//! `R1CS` by a recording constraint system, after which its matrices can be
+//! inspected, exported or replayed as a `Circuit` again.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::pairing::ff::{
+    Field,
+    PrimeField
+};
+
+use crate::{
+    SynthesisError,
+    ErrorLocation,
+    Circuit,
+    ConstraintSystem,
+    LinearCombination,
+    Variable,
+    Index,
+    namespace_path
+};
+
+pub mod diff;
+pub mod dot;
+pub mod iden3;
+pub mod lint;
+pub mod optimizer;
+pub mod trace;
+pub mod underconstrained;
+
+/// A single constraint of the form `A * B = C`, where every linear combination
+/// is stored as a list of `(index, coefficient)` terms.
+#[derive(Clone, Debug)]
+pub struct Constraint<E: Engine> {
+    pub a: Vec<(Index, E::Fr)>,
+    pub b: Vec<(Index, E::Fr)>,
+    pub c: Vec<(Index, E::Fr)>,
+
+    // Full namespace path of the constraint annotation
+    pub annotation: String
+}
+
+impl<E: Engine> Constraint<E> {
+    pub fn is_satisfied(
+        &self,
+        input_assignment: &[E::Fr],
+        aux_assignment: &[E::Fr]
+    ) -> bool
+    {
+        let mut ab = evaluate::<E>(&self.a, input_assignment, aux_assignment);
+        ab.mul_assign(&evaluate::<E>(&self.b, input_assignment, aux_assignment));
+
+        ab == evaluate::<E>(&self.c, input_assignment, aux_assignment)
+    }
+}
+
+/// Evaluates a linear combination at the given assignment.
+pub fn evaluate<E: Engine>(
+    terms: &[(Index, E::Fr)],
+    input_assignment: &[E::Fr],
+    aux_assignment: &[E::Fr]
+) -> E::Fr
+{
+    let mut acc = E::Fr::zero();
+    for &(index, ref coeff) in terms.iter() {
+        let mut tmp = match index {
+            Index::Input(i) => input_assignment[i],
+            Index::Aux(i) => aux_assignment[i]
+        };
+        tmp.mul_assign(coeff);
+        acc.add_assign(&tmp);
+    }
+
+    acc
+}
+
+/// Returns the value of a linear combination that involves no variable
+/// other than the constant one. The terms need not be canonical.
+pub(crate) fn constant_of<E: Engine>(terms: &[(Index, E::Fr)]) -> Option<E::Fr> {
+    let mut acc = E::Fr::zero();
+    for &(index, ref coeff) in terms.iter() {
+        if index == Index::Input(0) {
+            acc.add_assign(coeff);
+        } else if !coeff.is_zero() {
+            return None;
+        }
+    }
+
+    Some(acc)
+}
+
+/// Formats small values and their negations in decimal, and
+/// anything else as the hexadecimal representation.
+pub(crate) fn format_value<F: PrimeField>(value: &F) -> String {
+    fn small<F: PrimeField>(value: &F) -> Option<u64> {
+        let repr = value.into_repr();
+        let limbs = repr.as_ref();
+        if limbs[1..].iter().all(|&limb| limb == 0) {
+            Some(limbs[0])
+        } else {
+            None
+        }
+    }
+
+    if let Some(v) = small(value) {
+        return v.to_string();
+    }
+
+    let mut negated = *value;
+    negated.negate();
+    if let Some(v) = small(&negated) {
+        return format!("-{}", v);
+    }
+
+    format!("{}", value.into_repr())
+}
+
+/// A recorded rank-1 constraint system. Input variable 0 is always
+/// the constant "one", exactly as in the proving systems.
+#[derive(Clone, Debug)]
+pub struct R1CS<E: Engine> {
+    // Full namespace paths of the allocation annotations
+    pub input_names: Vec<String>,
+    pub aux_names: Vec<String>,
+
+    pub constraints: Vec<Constraint<E>>,
+
+    // Assignments, present only if the circuit was recorded
+    // with a witness or one was supplied afterwards
+    pub input_assignment: Option<Vec<E::Fr>>,
+    pub aux_assignment: Option<Vec<E::Fr>>
+}
+
+impl<E: Engine> R1CS<E> {
+    /// Synthesizes the circuit without invoking any of the
+    /// assignment closures.
+    pub fn record<C: Circuit<E>>(circuit: C) -> Result<Self, SynthesisError> {
+        let mut cs = RecordingSystem::new();
+        circuit.synthesize(&mut cs)?;
+
+        Ok(cs.into_r1cs())
+    }
+
+    /// Synthesizes the circuit together with its witness.
+    pub fn record_with_witness<C: Circuit<E>>(circuit: C) -> Result<Self, SynthesisError> {
+        let mut cs = RecordingSystem::new_with_witness();
+        circuit.synthesize(&mut cs)?;
+
+        Ok(cs.into_r1cs())
+    }
+
+    /// Synthesizes the circuit and records its witness if every assignment
+    /// is known. Missing assignments are not an error, so the same call
+    /// works during both key generation and proving.
+    pub fn record_with_optional_witness<C: Circuit<E>>(circuit: C) -> Result<Self, SynthesisError> {
+        let mut cs = RecordingSystem::new_with_optional_witness();
+        circuit.synthesize(&mut cs)?;
+
+        Ok(cs.into_r1cs())
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.input_names.len()
+    }
+
+    pub fn num_aux(&self) -> usize {
+        self.aux_names.len()
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.constraints.len()
+    }
+
+    /// Attaches a witness to the constraint system, so that it can be replayed
+    /// for proving. The input assignment includes the "one" variable.
+    pub fn set_witness(
+        &mut self,
+        input_assignment: Vec<E::Fr>,
+        aux_assignment: Vec<E::Fr>
+    )
+    {
+        assert_eq!(input_assignment.len(), self.num_inputs());
+        assert_eq!(aux_assignment.len(), self.num_aux());
+
+        self.input_assignment = Some(input_assignment);
+        self.aux_assignment = Some(aux_assignment);
+    }
+
+    /// Checks every constraint against the attached witness. The first
+    /// violated constraint is reported with its number and path.
+    pub fn check_satisfied(&self) -> Result<(), SynthesisError> {
+        let (input_assignment, aux_assignment) = match (&self.input_assignment, &self.aux_assignment) {
+            (Some(input), Some(aux)) => (input, aux),
+            _ => return Err(SynthesisError::AssignmentMissing)
+        };
+
+        for (i, constraint) in self.constraints.iter().enumerate() {
+            if !constraint.is_satisfied(input_assignment, aux_assignment) {
+                let location = ErrorLocation::constraint(i).with_path(constraint.annotation.clone());
+
+                return Err(SynthesisError::Unsatisfiable.at(location));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the name of the variable behind the index.
+    pub fn name_of(&self, index: Index) -> &str {
+        match index {
+            Index::Input(i) => &self.input_names[i],
+            Index::Aux(i) => &self.aux_names[i]
+        }
+    }
+
+    /// Returns the index of the variable with the given path, if any.
+    pub fn index_of(&self, path: &str) -> Option<Index> {
+        if let Some(i) = self.input_names.iter().position(|name| name == path) {
+            return Some(Index::Input(i));
+        }
+
+        self.aux_names.iter().position(|name| name == path).map(Index::Aux)
+    }
+}
+
+/// Replays the recorded constraint system. Assignments are taken
+/// from the attached witness, if any.
+impl<E: Engine> Circuit<E> for &R1CS<E> {
+    fn synthesize<CS: ConstraintSystem<E>>(
+        self,
+        cs: &mut CS
+    ) -> Result<(), SynthesisError>
+    {
+        fn value<E: Engine>(
+            assignment: &Option<Vec<E::Fr>>,
+            i: usize
+        ) -> Result<E::Fr, SynthesisError>
+        {
+            assignment.as_ref().map(|v| v[i]).ok_or(SynthesisError::AssignmentMissing)
+        }
+
+        let mut inputs = Vec::with_capacity(self.num_inputs());
+        inputs.push(CS::one());
+        for i in 1..self.num_inputs() {
+            let var = cs.alloc_input(
+                || self.input_names[i].clone(),
+                || value::<E>(&self.input_assignment, i)
+            )?;
+            inputs.push(var);
+        }
+
+        let mut aux = Vec::with_capacity(self.num_aux());
+        for i in 0..self.num_aux() {
+            let var = cs.alloc(
+                || self.aux_names[i].clone(),
+                || value::<E>(&self.aux_assignment, i)
+            )?;
+            aux.push(var);
+        }
+
+        let remap = |terms: &[(Index, E::Fr)], mut lc: LinearCombination<E>| {
+            for &(index, coeff) in terms.iter() {
+                let var = match index {
+                    Index::Input(i) => inputs[i],
+                    Index::Aux(i) => aux[i]
+                };
+                lc = lc + (coeff, var);
+            }
+
+            lc
+        };
+
+        for constraint in self.constraints.iter() {
+            cs.enforce(
+                || constraint.annotation.clone(),
+                |lc| remap(&constraint.a, lc),
+                |lc| remap(&constraint.b, lc),
+                |lc| remap(&constraint.c, lc)
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl<E: Engine> Circuit<E> for R1CS<E> {
+    fn synthesize<CS: ConstraintSystem<E>>(
+        self,
+        cs: &mut CS
+    ) -> Result<(), SynthesisError>
+    {
+        (&self).synthesize(cs)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum WitnessMode {
+    Ignore,
+    Require,
+    Attempt
+}
+
+/// A constraint system that records every allocation and constraint
+/// together with its full namespace path.
+pub struct RecordingSystem<E: Engine> {
+    r1cs: R1CS<E>,
+    current_namespace: Vec<String>,
+    witness_mode: WitnessMode,
+    missing_witness: bool
+}
+
+impl<E: Engine> RecordingSystem<E> {
+    /// Records the shape of the circuit only.
+    pub fn new() -> Self {
+        Self::new_inner(WitnessMode::Ignore)
+    }
+
+    /// Records the shape of the circuit and invokes the
+    /// assignment closures to record the witness.
+    pub fn new_with_witness() -> Self {
+        Self::new_inner(WitnessMode::Require)
+    }
+
+    /// Same as `new_with_witness`, but tolerates missing assignments,
+    /// in which case no witness is recorded at all.
+    pub fn new_with_optional_witness() -> Self {
+        Self::new_inner(WitnessMode::Attempt)
+    }
+
+    fn new_inner(witness_mode: WitnessMode) -> Self {
+        let (input_assignment, aux_assignment) = if witness_mode != WitnessMode::Ignore {
+            (Some(vec![E::Fr::one()]), Some(vec![]))
+        } else {
+            (None, None)
+        };
+
+        RecordingSystem {
+            r1cs: R1CS {
+                input_names: vec!["ONE".into()],
+                aux_names: vec![],
+                constraints: vec![],
+                input_assignment: input_assignment,
+                aux_assignment: aux_assignment
+            },
+            current_namespace: vec![],
+            witness_mode: witness_mode,
+            missing_witness: false
+        }
+    }
+
+    pub fn into_r1cs(mut self) -> R1CS<E> {
+        if self.missing_witness {
+            self.r1cs.input_assignment = None;
+            self.r1cs.aux_assignment = None;
+        }
+
+        self.r1cs
+    }
+
+    fn assign<F>(&mut self, f: F) -> Result<Option<E::Fr>, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>
+    {
+        match self.witness_mode {
+            WitnessMode::Ignore => Ok(None),
+            WitnessMode::Require => f().map(Some),
+            WitnessMode::Attempt => {
+                match f() {
+                    Ok(value) => Ok(Some(value)),
+                    Err(e) => {
+                        if let SynthesisError::AssignmentMissing = *e.kind() {
+                            self.missing_witness = true;
+
+                            Ok(Some(E::Fr::zero()))
+                        } else {
+                            Err(e)
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn compute_path(&self, name: String) -> String {
+        namespace_path(&self.current_namespace, name)
+    }
+}
+
+impl<E: Engine> Default for RecordingSystem<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Engine> ConstraintSystem<E> for RecordingSystem<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(
+        &mut self,
+        annotation: A,
+        f: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        let path = self.compute_path(annotation().into());
+        let index = Index::Aux(self.r1cs.aux_names.len());
+
+        match self.assign(f) {
+            Ok(Some(value)) => self.r1cs.aux_assignment.as_mut().unwrap().push(value),
+            Ok(None) => {},
+            Err(e) => return Err(e.at(ErrorLocation::variable(index).with_path(path)))
+        }
+
+        self.r1cs.aux_names.push(path);
+
+        Ok(Variable(index))
+    }
+
+    fn alloc_input<F, A, AR>(
+        &mut self,
+        annotation: A,
+        f: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        let path = self.compute_path(annotation().into());
+        let index = Index::Input(self.r1cs.input_names.len());
+
+        match self.assign(f) {
+            Ok(Some(value)) => self.r1cs.input_assignment.as_mut().unwrap().push(value),
+            Ok(None) => {},
+            Err(e) => return Err(e.at(ErrorLocation::variable(index).with_path(path)))
+        }
+
+        self.r1cs.input_names.push(path);
+
+        Ok(Variable(index))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(
+        &mut self,
+        annotation: A,
+        a: LA,
+        b: LB,
+        c: LC
+    )
+        where A: FnOnce() -> AR, AR: Into<String>,
+              LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>
+    {
+        fn terms<E: Engine>(lc: LinearCombination<E>) -> Vec<(Index, E::Fr)> {
+            lc.0.into_iter().map(|(var, coeff)| (var.0, coeff)).collect()
+        }
+
+        let path = self.compute_path(annotation().into());
+
+        self.r1cs.constraints.push(Constraint {
+            a: terms(a(LinearCombination::zero())),
+            b: terms(b(LinearCombination::zero())),
+            c: terms(c(LinearCombination::zero())),
+            annotation: path
+        });
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+        where NR: Into<String>, N: FnOnce() -> NR
+    {
+        self.current_namespace.push(name_fn().into());
+    }
+
+    fn pop_namespace(&mut self)
+    {
+        assert!(self.current_namespace.pop().is_some());
+    }
+
+    fn try_pop_namespace(&mut self) -> Result<(), SynthesisError>
+    {
+        self.current_namespace.pop().map(|_| ()).ok_or(SynthesisError::NamespaceMisuse)
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bls12_381::{Bls12, Fr};
+    use crate::pairing::ff::PrimeField;
+    use crate::tests::XORDemo;
+    use crate::groth16::{
+        generate_random_parameters,
+        prepare_verifying_key,
+        create_random_proof,
+        verify_proof
+    };
+    use crate::VerificationError;
+
+    use rand::thread_rng;
+    use std::marker::PhantomData;
+
+    #[test]
+    fn test_record_xordemo() {
+        let r1cs = R1CS::<Bls12>::record(XORDemo {
+            a: None,
+            b: None,
+            _marker: PhantomData
+        }).unwrap();
+
+        assert_eq!(r1cs.num_inputs(), 2);
+        assert_eq!(r1cs.num_aux(), 2);
+        assert_eq!(r1cs.num_constraints(), 3);
+        assert_eq!(r1cs.input_names, vec!["ONE", "c"]);
+        assert_eq!(r1cs.constraints[2].annotation, "c_xor_constraint");
+        assert!(r1cs.input_assignment.is_none());
+
+        let r1cs = R1CS::<Bls12>::record_with_witness(XORDemo {
+            a: Some(true),
+            b: Some(false),
+            _marker: PhantomData
+        }).unwrap();
+
+        assert_eq!(r1cs.input_assignment.unwrap(), vec![Fr::one(), Fr::one()]);
+        assert_eq!(r1cs.aux_assignment.unwrap(), vec![Fr::one(), Fr::zero()]);
+    }
+
+    #[test]
+    fn test_replay_recorded_circuit() {
+        let rng = &mut thread_rng();
+
+        let shape = R1CS::<Bls12>::record(XORDemo {
+            a: None,
+            b: None,
+            _marker: PhantomData
+        }).unwrap();
+
+        let params = generate_random_parameters(&shape, rng).unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+
+        let witness = R1CS::<Bls12>::record_with_witness(XORDemo {
+            a: Some(true),
+            b: Some(true),
+            _marker: PhantomData
+        }).unwrap();
+
+        let proof = create_random_proof(&witness, &params, rng).unwrap();
+
+        assert!(verify_proof(&pvk, &proof, &[Fr::zero()]).unwrap());
+        assert!(!verify_proof(&pvk, &proof, &[Fr::from_str("1").unwrap()]).unwrap());
+        assert_eq!(
+            verify_proof(&pvk, &proof, &[]).unwrap_err(),
+            VerificationError::InputCountMismatch { expected: 1, actual: 0 }
+        );
+    }
+
+    #[test]
+    fn test_error_locations() {
+        let err = R1CS::<Bls12>::record_with_witness(XORDemo {
+            a: Some(true),
+            b: None,
+            _marker: PhantomData
+        }).unwrap_err();
+
+        match *err.kind() {
+            SynthesisError::AssignmentMissing => {},
+            _ => panic!("unexpected error")
+        }
+        assert_eq!(err.location(), Some(&ErrorLocation::variable(Index::Aux(1)).with_path("b")));
+
+        let mut r1cs = R1CS::<Bls12>::record_with_witness(XORDemo {
+            a: Some(true),
+            b: Some(false),
+            _marker: PhantomData
+        }).unwrap();
+        assert!(r1cs.check_satisfied().is_ok());
+
+        r1cs.input_assignment.as_mut().unwrap()[1] = Fr::zero();
+        let err = r1cs.check_satisfied().unwrap_err();
+
+        match *err.kind() {
+            SynthesisError::Unsatisfiable => {},
+            _ => panic!("unexpected error")
+        }
+        assert_eq!(err.location(), Some(&ErrorLocation::constraint(2).with_path("c_xor_constraint")));
+    }
+}
@@ -0,0 +1,435 @@
+//! An optimization pass over recorded constraint systems.
+//!
+//! Constraints where either `A` or `B` is a constant are linear. Such a
+//! constraint is solved for one of its auxillary variables, which is then
+//! substituted everywhere else, and the constraint is dropped. Afterwards
+//! identical constraints are deduplicated and auxillary variables that no
+//! longer appear in any constraint are removed. Public inputs are never
+//! touched, so the reduced system has exactly the same verifier.
+//!
+//! The pass depends only on the shape of the circuit, so key generation and
+//! proving always see the same reduced system.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::pairing::ff::{
+    Field,
+    PrimeField
+};
+
+use crate::{
+    SynthesisError,
+    Circuit,
+    ConstraintSystem,
+    Index
+};
+
+use super::{
+    R1CS,
+    Constraint,
+    constant_of
+};
+
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashSet, VecDeque};
+
+fn order(index: Index) -> (usize, usize) {
+    match index {
+        Index::Input(i) => (0, i),
+        Index::Aux(i) => (1, i)
+    }
+}
+
+/// Sorts the terms, merges duplicate variables and drops zero coefficients.
+fn canonicalize<E: Engine>(mut terms: Vec<(Index, E::Fr)>) -> Vec<(Index, E::Fr)> {
+    terms.sort_by_key(|&(index, _)| index);
+
+    let mut result: Vec<(Index, E::Fr)> = Vec::with_capacity(terms.len());
+    for (index, coeff) in terms.into_iter() {
+        if let Some(last) = result.last_mut() {
+            if last.0 == index {
+                last.1.add_assign(&coeff);
+                continue;
+            }
+        }
+        result.push((index, coeff));
+    }
+    result.retain(|&(_, coeff)| !coeff.is_zero());
+
+    result
+}
+
+fn scaled<E: Engine>(terms: &[(Index, E::Fr)], by: &E::Fr) -> Vec<(Index, E::Fr)> {
+    terms.iter().map(|&(index, mut coeff)| {
+        coeff.mul_assign(by);
+        (index, coeff)
+    }).collect()
+}
+
+/// If the constraint is linear, returns `L` such that
+/// the constraint is equivalent to `L = 0`.
+fn linear_form<E: Engine>(constraint: &Constraint<E>) -> Option<Vec<(Index, E::Fr)>> {
+    let (k, other) = if let Some(k) = constant_of::<E>(&constraint.b) {
+        (k, &constraint.a)
+    } else if let Some(k) = constant_of::<E>(&constraint.a) {
+        (k, &constraint.b)
+    } else {
+        return None;
+    };
+
+    let mut minus_one = E::Fr::one();
+    minus_one.negate();
+
+    let mut terms = scaled::<E>(other, &k);
+    terms.extend(scaled::<E>(&constraint.c, &minus_one));
+
+    Some(canonicalize::<E>(terms))
+}
+
+fn aux_variables<E: Engine>(constraint: &Constraint<E>) -> BTreeSet<usize> {
+    constraint.a.iter().chain(constraint.b.iter()).chain(constraint.c.iter())
+        .filter_map(|&(index, _)| match index {
+            Index::Aux(i) => Some(i),
+            Index::Input(_) => None
+        })
+        .collect()
+}
+
+/// Replaces the auxillary variable `var` with `expr` in the terms.
+fn substitute<E: Engine>(
+    terms: &mut Vec<(Index, E::Fr)>,
+    var: usize,
+    expr: &[(Index, E::Fr)]
+)
+{
+    if let Some(pos) = terms.iter().position(|&(index, _)| index == Index::Aux(var)) {
+        let (_, coeff) = terms.remove(pos);
+        let mut result = terms.split_off(0);
+        result.extend(scaled::<E>(expr, &coeff));
+        *terms = canonicalize::<E>(result);
+    }
+}
+
+fn encode<E: Engine>(terms: &[(Index, E::Fr)]) -> Vec<u64> {
+    let mut encoding = vec![];
+    for &(index, coeff) in terms.iter() {
+        let (tag, i) = order(index);
+        encoding.push(tag as u64);
+        encoding.push(i as u64);
+        encoding.extend_from_slice(coeff.into_repr().as_ref());
+    }
+
+    encoding
+}
+
+/// Result of the optimization pass.
+pub struct Optimization<E: Engine> {
+    pub r1cs: R1CS<E>,
+
+    // New index of every original auxillary variable,
+    // or `None` if the variable was removed
+    pub aux_map: Vec<Option<usize>>,
+
+    pub substituted_constraints: usize,
+    pub duplicate_constraints: usize,
+    pub trivial_constraints: usize,
+    pub removed_variables: usize
+}
+
+/// Optimizes the constraint system. The witness, if attached,
+/// is carried over to the remaining variables.
+pub fn optimize<E: Engine>(r1cs: &R1CS<E>) -> Optimization<E> {
+    let mut constraints: Vec<Constraint<E>> = r1cs.constraints.iter().map(|c| Constraint {
+        a: canonicalize::<E>(c.a.clone()),
+        b: canonicalize::<E>(c.b.clone()),
+        c: canonicalize::<E>(c.c.clone()),
+        annotation: c.annotation.clone()
+    }).collect();
+    let mut deleted = vec![false; constraints.len()];
+
+    // Constraints every auxillary variable appears in
+    let mut occurrences = vec![BTreeSet::new(); r1cs.num_aux()];
+    for (i, constraint) in constraints.iter().enumerate() {
+        for var in aux_variables(constraint) {
+            occurrences[var].insert(i);
+        }
+    }
+
+    let mut substituted_constraints = 0;
+    let mut trivial_constraints = 0;
+
+    let mut queue: VecDeque<usize> = (0..constraints.len()).collect();
+    let mut queued = vec![true; constraints.len()];
+    while let Some(i) = queue.pop_front() {
+        queued[i] = false;
+
+        if deleted[i] {
+            continue;
+        }
+
+        let l = match linear_form(&constraints[i]) {
+            Some(l) => l,
+            None => continue
+        };
+
+        // Prefer the variable that appears in the fewest
+        // constraints to keep the fill-in small
+        let candidate = l.iter()
+            .filter_map(|&(index, coeff)| match index {
+                Index::Aux(var) => Some((var, coeff)),
+                Index::Input(_) => None
+            })
+            .min_by_key(|&(var, _)| (occurrences[var].len(), Reverse(var)));
+
+        let (var, coeff) = match candidate {
+            Some(candidate) => candidate,
+            None => {
+                // Only constraints that hold for any assignment are dropped,
+                // those on the public inputs must be kept.
+                if l.is_empty() {
+                    for var in aux_variables(&constraints[i]) {
+                        occurrences[var].remove(&i);
+                    }
+                    deleted[i] = true;
+                    trivial_constraints += 1;
+                }
+
+                continue;
+            }
+        };
+
+        // var = -(L - coeff * var) / coeff
+        let mut factor = coeff.inverse().expect("coefficients are never zero");
+        factor.negate();
+        let expr: Vec<_> = l.iter()
+            .filter(|&&(index, _)| index != Index::Aux(var))
+            .map(|&(index, mut c)| {
+                c.mul_assign(&factor);
+                (index, c)
+            })
+            .collect();
+
+        for other in aux_variables(&constraints[i]) {
+            occurrences[other].remove(&i);
+        }
+        deleted[i] = true;
+        substituted_constraints += 1;
+
+        let affected: Vec<usize> = occurrences[var].iter().cloned().collect();
+        for j in affected {
+            for other in aux_variables(&constraints[j]) {
+                occurrences[other].remove(&j);
+            }
+
+            {
+                let constraint = &mut constraints[j];
+                substitute::<E>(&mut constraint.a, var, &expr);
+                substitute::<E>(&mut constraint.b, var, &expr);
+                substitute::<E>(&mut constraint.c, var, &expr);
+            }
+
+            for other in aux_variables(&constraints[j]) {
+                occurrences[other].insert(j);
+            }
+
+            if !queued[j] {
+                queue.push_back(j);
+                queued[j] = true;
+            }
+        }
+    }
+
+    // Deduplicate, treating A * B = C and B * A = C as identical
+    let mut duplicate_constraints = 0;
+    let mut seen = HashSet::new();
+    for (i, constraint) in constraints.iter().enumerate() {
+        if deleted[i] {
+            continue;
+        }
+
+        let mut a = encode::<E>(&constraint.a);
+        let mut b = encode::<E>(&constraint.b);
+        if a > b {
+            ::std::mem::swap(&mut a, &mut b);
+        }
+
+        if !seen.insert((a, b, encode::<E>(&constraint.c))) {
+            deleted[i] = true;
+            duplicate_constraints += 1;
+        }
+    }
+
+    let constraints: Vec<_> = constraints.into_iter()
+        .zip(deleted)
+        .filter(|&(_, deleted)| !deleted)
+        .map(|(constraint, _)| constraint)
+        .collect();
+
+    // Renumber the auxillary variables that are still used
+    let mut used = vec![false; r1cs.num_aux()];
+    for constraint in constraints.iter() {
+        for var in aux_variables(constraint) {
+            used[var] = true;
+        }
+    }
+
+    let mut aux_map = Vec::with_capacity(used.len());
+    let mut num_aux = 0;
+    for &used in used.iter() {
+        if used {
+            aux_map.push(Some(num_aux));
+            num_aux += 1;
+        } else {
+            aux_map.push(None);
+        }
+    }
+
+    let remap = |terms: Vec<(Index, E::Fr)>| -> Vec<(Index, E::Fr)> {
+        terms.into_iter().map(|(index, coeff)| match index {
+            Index::Aux(i) => (Index::Aux(aux_map[i].unwrap()), coeff),
+            index => (index, coeff)
+        }).collect()
+    };
+
+    let constraints = constraints.into_iter().map(|constraint| Constraint {
+        a: remap(constraint.a),
+        b: remap(constraint.b),
+        c: remap(constraint.c),
+        annotation: constraint.annotation
+    }).collect();
+
+    let keep = |values: &[String]| -> Vec<String> {
+        values.iter().zip(used.iter()).filter(|&(_, &used)| used).map(|(v, _)| v.clone()).collect()
+    };
+    let aux_names = keep(&r1cs.aux_names);
+    let aux_assignment = r1cs.aux_assignment.as_ref().map(|values| {
+        values.iter().zip(used.iter()).filter(|&(_, &used)| used).map(|(v, _)| *v).collect()
+    });
+
+    Optimization {
+        r1cs: R1CS {
+            input_names: r1cs.input_names.clone(),
+            aux_names: aux_names,
+            constraints: constraints,
+            input_assignment: r1cs.input_assignment.clone(),
+            aux_assignment: aux_assignment
+        },
+        aux_map: aux_map,
+        substituted_constraints: substituted_constraints,
+        duplicate_constraints: duplicate_constraints,
+        trivial_constraints: trivial_constraints,
+        removed_variables: r1cs.num_aux() - num_aux
+    }
+}
+
+/// Wraps a circuit so that it is recorded, optimized and replayed into
+/// whatever constraint system synthesizes it. Use the wrapper both for key
+/// generation and for proving; the circuit itself needs no changes.
+pub struct OptimizedCircuit<C>(pub C);
+
+impl<E: Engine, C: Circuit<E>> Circuit<E> for OptimizedCircuit<C> {
+    fn synthesize<CS: ConstraintSystem<E>>(
+        self,
+        cs: &mut CS
+    ) -> Result<(), SynthesisError>
+    {
+        let recorded = R1CS::record_with_optional_witness(self.0)?;
+
+        optimize(&recorded).r1cs.synthesize(cs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bls12_381::{Bls12, Fr};
+    use crate::groth16::{
+        generate_random_parameters,
+        prepare_verifying_key,
+        create_random_proof,
+        verify_proof
+    };
+
+    use rand::thread_rng;
+
+    struct AliasingCircuit {
+        x: Option<Fr>
+    }
+
+    impl Circuit<Bls12> for AliasingCircuit {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS
+        ) -> Result<(), SynthesisError>
+        {
+            let square = self.x.map(|mut x| {
+                x.square();
+                x
+            });
+
+            let x = cs.alloc(|| "x", || self.x.ok_or(SynthesisError::AssignmentMissing))?;
+            let y = cs.alloc(|| "y", || square.ok_or(SynthesisError::AssignmentMissing))?;
+            cs.enforce(|| "x * x = y", |lc| lc + x, |lc| lc + x, |lc| lc + y);
+
+            let z = cs.alloc(|| "z", || square.ok_or(SynthesisError::AssignmentMissing))?;
+            cs.enforce(|| "y * 1 = z", |lc| lc + y, |lc| lc + CS::one(), |lc| lc + z);
+
+            // Same as the first constraint
+            cs.enforce(|| "x * x = y again", |lc| lc + x, |lc| lc + x, |lc| lc + y);
+
+            let out = cs.alloc_input(|| "out", || square.ok_or(SynthesisError::AssignmentMissing))?;
+            cs.enforce(|| "z * 1 = out", |lc| lc + z, |lc| lc + CS::one(), |lc| lc + out);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_optimize_aliases_and_duplicates() {
+        let recorded = R1CS::<Bls12>::record(AliasingCircuit { x: None }).unwrap();
+        assert_eq!(recorded.num_constraints(), 4);
+        assert_eq!(recorded.num_aux(), 3);
+
+        let optimized = optimize(&recorded);
+        assert_eq!(optimized.substituted_constraints, 2);
+        assert_eq!(optimized.duplicate_constraints, 1);
+        assert_eq!(optimized.removed_variables, 2);
+        assert_eq!(optimized.aux_map, vec![Some(0), None, None]);
+
+        assert_eq!(optimized.r1cs.num_constraints(), 1);
+        assert_eq!(optimized.r1cs.aux_names, vec!["x"]);
+        assert_eq!(optimized.r1cs.constraints[0].c, vec![(Index::Input(1), Fr::one())]);
+    }
+
+    #[test]
+    fn test_optimize_split_constant_factor() {
+        // Rows read from files may repeat the constant one
+        let mut recorded = R1CS::<Bls12>::record(AliasingCircuit { x: None }).unwrap();
+        let half = Fr::from_str("2").unwrap().inverse().unwrap();
+        recorded.constraints[1].b = vec![(Index::Input(0), half), (Index::Input(0), half)];
+
+        let optimized = optimize(&recorded);
+        assert_eq!(optimized.substituted_constraints, 2);
+        assert_eq!(optimized.r1cs.num_constraints(), 1);
+    }
+
+    #[test]
+    fn test_optimized_circuit_proves() {
+        let rng = &mut thread_rng();
+
+        let params = generate_random_parameters(OptimizedCircuit(AliasingCircuit { x: None }), rng).unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+
+        // A single aux variable remains in the L query
+        assert_eq!(params.l.len(), 1);
+
+        let x = Fr::from_str("3").unwrap();
+        let proof = create_random_proof(OptimizedCircuit(AliasingCircuit { x: Some(x) }), &params, rng).unwrap();
+
+        assert!(verify_proof(&pvk, &proof, &[Fr::from_str("9").unwrap()]).unwrap());
+        assert!(!verify_proof(&pvk, &proof, &[Fr::from_str("8").unwrap()]).unwrap());
+    }
+}
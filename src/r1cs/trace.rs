@@ -0,0 +1,629 @@
+//! Tracing of circuit synthesis for offline debugging.
+//!
+//! `TracingSystem` wraps another constraint system and forwards everything to
+//! it, while writing every allocation together with its value, and every
+//! constraint together with its evaluated `A`, `B` and `C`, to a compact
+//! binary trace. `TracedCircuit` does the same for a circuit that is handed
+//! to a prover, so that exactly what happened during proving can be replayed
+//! later. The trace is read back with `Trace::read`, which can locate the
+//! first violated constraint.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::pairing::ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use crate::{
+    SynthesisError,
+    Circuit,
+    ConstraintSystem,
+    LinearCombination,
+    Variable,
+    Index
+};
+
+use super::format_value;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read, Write};
+use byteorder::{BigEndian, WriteBytesExt, ReadBytesExt};
+
+const MAGIC: &[u8; 4] = b"btrc";
+const VERSION: u32 = 1;
+
+const TAG_END: u8 = 0;
+const TAG_PUSH_NAMESPACE: u8 = 1;
+const TAG_POP_NAMESPACE: u8 = 2;
+const TAG_ALLOC_INPUT: u8 = 3;
+const TAG_ALLOC_AUX: u8 = 4;
+const TAG_ENFORCE: u8 = 5;
+
+// Number of preceding events shown around a violated constraint
+const CONTEXT_EVENTS: usize = 16;
+
+fn write_string<W: Write>(writer: &mut W, s: &str) -> io::Result<()> {
+    writer.write_u32::<BigEndian>(s.len() as u32)?;
+    writer.write_all(s.as_bytes())
+}
+
+fn read_string<R: Read>(reader: &mut R) -> io::Result<String> {
+    // The length is untrusted, so the string grows only with
+    // the data actually read
+    let len = reader.read_u32::<BigEndian>()? as u64;
+    let mut bytes = vec![];
+    reader.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "string is truncated"));
+    }
+
+    String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_values<F: PrimeField, W: Write>(writer: &mut W, values: Option<&[F]>) -> io::Result<()> {
+    match values {
+        Some(values) => {
+            writer.write_u8(1)?;
+            for value in values.iter() {
+                value.into_repr().write_be(&mut *writer)?;
+            }
+        },
+        None => writer.write_u8(0)?
+    }
+
+    Ok(())
+}
+
+fn read_values<F: PrimeField, R: Read>(reader: &mut R, len: usize) -> io::Result<Option<Vec<F>>> {
+    if reader.read_u8()? == 0 {
+        return Ok(None);
+    }
+
+    let mut values = vec![];
+    for _ in 0..len {
+        let mut repr = F::Repr::default();
+        repr.read_be(&mut *reader)?;
+        values.push(F::from_repr(repr).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?);
+    }
+
+    Ok(Some(values))
+}
+
+/// A constraint system that writes a trace of everything
+/// passing through it to the inner constraint system.
+pub struct TracingSystem<E: Engine, CS: ConstraintSystem<E>, W: Write> {
+    inner: CS,
+    writer: W,
+
+    // Values of the variables seen so far, if known
+    values: HashMap<Index, E::Fr>,
+    num_constraints: usize,
+
+    // The first I/O error; nothing is written after it
+    error: Option<io::Error>
+}
+
+impl<E: Engine, CS: ConstraintSystem<E>, W: Write> TracingSystem<E, CS, W> {
+    pub fn new(inner: CS, mut writer: W) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        writer.write_u32::<BigEndian>(VERSION)?;
+
+        let modulus = E::Fr::char();
+        writer.write_u32::<BigEndian>(modulus.as_ref().len() as u32)?;
+        modulus.write_be(&mut writer)?;
+
+        let mut values = HashMap::new();
+        values.insert(Index::Input(0), E::Fr::one());
+
+        Ok(TracingSystem {
+            inner: inner,
+            writer: writer,
+            values: values,
+            num_constraints: 0,
+            error: None
+        })
+    }
+
+    /// Terminates the trace and returns the inner constraint
+    /// system, or the first error that occurred while writing.
+    pub fn finish(mut self) -> io::Result<CS> {
+        self.record(|w| w.write_u8(TAG_END));
+        self.record(|w| w.flush());
+
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.inner)
+        }
+    }
+
+    fn record<F>(&mut self, f: F)
+        where F: FnOnce(&mut W) -> io::Result<()>
+    {
+        if self.error.is_none() {
+            if let Err(e) = f(&mut self.writer) {
+                self.error = Some(e);
+            }
+        }
+    }
+
+    fn evaluate(&self, lc: &LinearCombination<E>) -> Option<E::Fr> {
+        let mut acc = E::Fr::zero();
+        for &(var, ref coeff) in lc.as_ref().iter() {
+            let mut tmp = *self.values.get(&var.get_unchecked())?;
+            tmp.mul_assign(coeff);
+            acc.add_assign(&tmp);
+        }
+
+        Some(acc)
+    }
+
+    fn record_alloc(&mut self, var: Variable, name: &str, value: Option<E::Fr>) {
+        let (tag, index) = match var.get_unchecked() {
+            Index::Input(i) => (TAG_ALLOC_INPUT, i),
+            Index::Aux(i) => (TAG_ALLOC_AUX, i)
+        };
+
+        if let Some(value) = value {
+            self.values.insert(var.get_unchecked(), value);
+        }
+
+        self.record(|w| {
+            w.write_u8(tag)?;
+            w.write_u64::<BigEndian>(index as u64)?;
+            write_string(w, name)?;
+            write_values(w, value.as_ref().map(::std::slice::from_ref))
+        });
+    }
+}
+
+impl<E: Engine, CS: ConstraintSystem<E>, W: Write> ConstraintSystem<E> for TracingSystem<E, CS, W> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(
+        &mut self,
+        annotation: A,
+        f: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        let name: String = annotation().into();
+        let mut value = None;
+        let var = self.inner.alloc(|| name.clone(), || {
+            let result = f();
+            if let Ok(v) = result {
+                value = Some(v);
+            }
+
+            result
+        })?;
+
+        self.record_alloc(var, &name, value);
+
+        Ok(var)
+    }
+
+    fn alloc_input<F, A, AR>(
+        &mut self,
+        annotation: A,
+        f: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        let name: String = annotation().into();
+        let mut value = None;
+        let var = self.inner.alloc_input(|| name.clone(), || {
+            let result = f();
+            if let Ok(v) = result {
+                value = Some(v);
+            }
+
+            result
+        })?;
+
+        self.record_alloc(var, &name, value);
+
+        Ok(var)
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(
+        &mut self,
+        annotation: A,
+        a: LA,
+        b: LB,
+        c: LC
+    )
+        where A: FnOnce() -> AR, AR: Into<String>,
+              LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>
+    {
+        let name: String = annotation().into();
+        let a = a(LinearCombination::zero());
+        let b = b(LinearCombination::zero());
+        let c = c(LinearCombination::zero());
+
+        let values = match (self.evaluate(&a), self.evaluate(&b), self.evaluate(&c)) {
+            (Some(a), Some(b), Some(c)) => Some([a, b, c]),
+            _ => None
+        };
+
+        let number = self.num_constraints as u64;
+        self.num_constraints += 1;
+        self.record(|w| {
+            w.write_u8(TAG_ENFORCE)?;
+            w.write_u64::<BigEndian>(number)?;
+            write_string(w, &name)?;
+            write_values(w, values.as_ref().map(|v| &v[..]))
+        });
+
+        self.inner.enforce(|| name, |lc| lc + &a, |lc| lc + &b, |lc| lc + &c);
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+        where NR: Into<String>, N: FnOnce() -> NR
+    {
+        let name: String = name_fn().into();
+        self.record(|w| {
+            w.write_u8(TAG_PUSH_NAMESPACE)?;
+            write_string(w, &name)
+        });
+
+        self.inner.get_root().push_namespace(|| name);
+    }
+
+    fn pop_namespace(&mut self)
+    {
+        self.record(|w| w.write_u8(TAG_POP_NAMESPACE));
+
+        self.inner.get_root().pop_namespace();
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+/// A circuit whose synthesis is traced, to be used in place of the
+/// circuit itself, for example when proving.
+pub struct TracedCircuit<C, W: Write> {
+    circuit: C,
+    writer: W
+}
+
+impl<C, W: Write> TracedCircuit<C, W> {
+    pub fn new(circuit: C, writer: W) -> Self {
+        TracedCircuit {
+            circuit: circuit,
+            writer: writer
+        }
+    }
+}
+
+impl<E: Engine, C: Circuit<E>, W: Write> Circuit<E> for TracedCircuit<C, W> {
+    fn synthesize<CS: ConstraintSystem<E>>(
+        self,
+        cs: &mut CS
+    ) -> Result<(), SynthesisError>
+    {
+        let mut tracer = TracingSystem::new(cs, self.writer)?;
+        let result = self.circuit.synthesize(&mut tracer);
+        tracer.finish()?;
+
+        result
+    }
+}
+
+/// A single step of a trace. Paths are full namespace paths.
+#[derive(Clone, Debug)]
+pub enum TraceEvent<E: Engine> {
+    PushNamespace(String),
+    PopNamespace,
+    Alloc {
+        variable: Index,
+        path: String,
+        value: Option<E::Fr>
+    },
+    Enforce {
+        number: usize,
+        path: String,
+        // Evaluated A, B and C, if every assignment was known
+        values: Option<(E::Fr, E::Fr, E::Fr)>
+    }
+}
+
+impl<E: Engine> TraceEvent<E> {
+    pub fn is_violated(&self) -> bool {
+        match *self {
+            TraceEvent::Enforce { values: Some((a, b, c)), .. } => {
+                let mut ab = a;
+                ab.mul_assign(&b);
+
+                ab != c
+            },
+            _ => false
+        }
+    }
+}
+
+impl<E: Engine> fmt::Display for TraceEvent<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            TraceEvent::PushNamespace(ref path) => write!(f, "enter `{}`", path),
+            TraceEvent::PopNamespace => write!(f, "leave namespace"),
+            TraceEvent::Alloc { variable, ref path, ref value } => {
+                match variable {
+                    Index::Input(i) => write!(f, "input {} `{}`", i, path)?,
+                    Index::Aux(i) => write!(f, "aux {} `{}`", i, path)?
+                }
+                match *value {
+                    Some(ref value) => write!(f, " = {}", format_value(value)),
+                    None => write!(f, " = ?")
+                }
+            },
+            TraceEvent::Enforce { number, ref path, ref values } => {
+                write!(f, "constraint {} `{}`", number, path)?;
+                match *values {
+                    Some((ref a, ref b, ref c)) => {
+                        write!(f, ": {} * {} = {}", format_value(a), format_value(b), format_value(c))?;
+                        if self.is_violated() {
+                            write!(f, " VIOLATED")?;
+                        }
+
+                        Ok(())
+                    },
+                    None => write!(f, ": not evaluated")
+                }
+            }
+        }
+    }
+}
+
+/// A violated constraint together with what happened
+/// before it in the same namespace.
+pub struct Violation<'a, E: Engine> {
+    pub event: &'a TraceEvent<E>,
+    // Namespace the constraint was enforced in
+    pub namespace: String,
+    // Preceding events of the same namespace, oldest first
+    pub context: Vec<&'a TraceEvent<E>>
+}
+
+impl<'a, E: Engine> fmt::Display for Violation<'a, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        writeln!(f, "in `{}`:", self.namespace)?;
+        for event in self.context.iter() {
+            writeln!(f, "    {}", event)?;
+        }
+        writeln!(f, "  > {}", self.event)
+    }
+}
+
+/// Reads the next record, or `None` at the end of the trace.
+fn read_event<E: Engine, R: Read>(
+    reader: &mut R,
+    current_namespace: &mut Vec<String>
+) -> io::Result<Option<TraceEvent<E>>>
+{
+    let path = |current_namespace: &[String], name: String| {
+        let mut path = current_namespace.join("/");
+        if !path.is_empty() {
+            path.push('/');
+        }
+        path.push_str(&name);
+
+        path
+    };
+
+    let tag = reader.read_u8()?;
+    let event = match tag {
+        TAG_END => return Ok(None),
+        TAG_PUSH_NAMESPACE => {
+            let name = read_string(reader)?;
+            let event = TraceEvent::PushNamespace(path(current_namespace, name.clone()));
+            current_namespace.push(name);
+
+            event
+        },
+        TAG_POP_NAMESPACE => {
+            current_namespace.pop();
+
+            TraceEvent::PopNamespace
+        },
+        TAG_ALLOC_INPUT | TAG_ALLOC_AUX => {
+            let index = reader.read_u64::<BigEndian>()? as usize;
+            let name = read_string(reader)?;
+            let value = read_values::<E::Fr, _>(reader, 1)?.map(|v| v[0]);
+
+            TraceEvent::Alloc {
+                variable: if tag == TAG_ALLOC_INPUT { Index::Input(index) } else { Index::Aux(index) },
+                path: path(current_namespace, name),
+                value: value
+            }
+        },
+        TAG_ENFORCE => {
+            let number = reader.read_u64::<BigEndian>()? as usize;
+            let name = read_string(reader)?;
+            let values = read_values::<E::Fr, _>(reader, 3)?.map(|v| (v[0], v[1], v[2]));
+
+            TraceEvent::Enforce {
+                number: number,
+                path: path(current_namespace, name),
+                values: values
+            }
+        },
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "unknown trace record"))
+    };
+
+    Ok(Some(event))
+}
+
+/// A trace, as read back from a file.
+pub struct Trace<E: Engine> {
+    pub events: Vec<TraceEvent<E>>,
+
+    // Namespace of every event
+    namespaces: Vec<String>
+}
+
+impl<E: Engine> Trace<E> {
+    /// Reads a trace. A trace that ends abruptly, e.g. because the
+    /// process crashed while writing it, is read up to that point.
+    pub fn read<R: Read>(
+        mut reader: R
+    ) -> io::Result<Self>
+    {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a trace file"));
+        }
+
+        if reader.read_u32::<BigEndian>()? != VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "unsupported trace version"));
+        }
+
+        let mut modulus = <E::Fr as PrimeField>::Repr::default();
+        if reader.read_u32::<BigEndian>()? as usize != modulus.as_ref().len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "trace was created for a different engine"));
+        }
+        modulus.read_be(&mut reader)?;
+        if modulus != E::Fr::char() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "trace was created for a different engine"));
+        }
+
+        let mut events = vec![];
+        let mut namespaces = vec![];
+        let mut current_namespace = vec![];
+
+        loop {
+            let event = match read_event::<E, _>(&mut reader, &mut current_namespace) {
+                Ok(Some(event)) => event,
+                Ok(None) => break,
+                Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e)
+            };
+
+            events.push(event);
+            namespaces.push(current_namespace.join("/"));
+        }
+
+        Ok(Trace {
+            events: events,
+            namespaces: namespaces
+        })
+    }
+
+    /// Finds the first constraint that doesn't hold.
+    pub fn first_violation(&self) -> Option<Violation<'_, E>> {
+        let position = self.events.iter().position(|e| e.is_violated())?;
+        let namespace = &self.namespaces[position];
+
+        let mut context: Vec<_> = (0..position).rev()
+                                               .filter(|&i| &self.namespaces[i] == namespace)
+                                               .filter(|&i| matches!(self.events[i], TraceEvent::Alloc { .. } | TraceEvent::Enforce { .. }))
+                                               .take(CONTEXT_EVENTS)
+                                               .map(|i| &self.events[i])
+                                               .collect();
+        context.reverse();
+
+        Some(Violation {
+            event: &self.events[position],
+            namespace: namespace.clone(),
+            context: context
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bls12_381::{Bls12, Fr};
+    use crate::groth16::{
+        generate_random_parameters,
+        create_random_proof
+    };
+
+    use rand::thread_rng;
+
+    struct Multiplication {
+        a: Option<Fr>,
+        b: Option<Fr>,
+        c: Option<Fr>
+    }
+
+    impl Circuit<Bls12> for Multiplication {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS
+        ) -> Result<(), SynthesisError>
+        {
+            let mut cs = cs.namespace(|| "gadget");
+
+            let a = cs.alloc(|| "a", || self.a.ok_or(SynthesisError::AssignmentMissing))?;
+            let b = cs.alloc(|| "b", || self.b.ok_or(SynthesisError::AssignmentMissing))?;
+            let c = cs.alloc_input(|| "c", || self.c.ok_or(SynthesisError::AssignmentMissing))?;
+
+            cs.enforce(|| "copy", |lc| lc + a, |lc| lc + CS::one(), |lc| lc + a);
+            cs.enforce(|| "mul", |lc| lc + a, |lc| lc + b, |lc| lc + c);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_trace_finds_violation() {
+        let rng = &mut thread_rng();
+
+        let params = generate_random_parameters::<Bls12, _, _>(Multiplication { a: None, b: None, c: None }, rng).unwrap();
+
+        // The prover doesn't notice the wrong product, the trace does
+        let circuit = Multiplication {
+            a: Some(Fr::from_str("2").unwrap()),
+            b: Some(Fr::from_str("3").unwrap()),
+            c: Some(Fr::from_str("7").unwrap())
+        };
+
+        let mut v = vec![];
+        create_random_proof(TracedCircuit::new(circuit, &mut v), &params, rng).unwrap();
+
+        let trace = Trace::<Bls12>::read(&v[..]).unwrap();
+        assert_eq!(trace.events.len(), 7);
+
+        let violation = trace.first_violation().unwrap();
+        assert_eq!(violation.namespace, "gadget");
+        assert_eq!(violation.context.len(), 4);
+        match *violation.event {
+            TraceEvent::Enforce { number, ref path, .. } => {
+                assert_eq!(number, 1);
+                assert_eq!(path, "gadget/mul");
+            },
+            _ => unreachable!()
+        }
+
+        let text = violation.to_string();
+        assert!(text.contains("aux 0 `gadget/a` = 2"));
+        assert!(text.contains("> constraint 1 `gadget/mul`: 2 * 3 = 7 VIOLATED"));
+
+        // A trace cut short is read up to the last complete record
+        let trace = Trace::<Bls12>::read(&v[..v.len() - 1]).unwrap();
+        assert_eq!(trace.events.len(), 7);
+        let trace = Trace::<Bls12>::read(&v[..v.len() - 2]).unwrap();
+        assert_eq!(trace.events.len(), 6);
+
+        assert!(Trace::<crate::pairing::bn256::Bn256>::read(&v[..]).is_err());
+
+        // A corrupt length is not allocated up front, and only ends the trace
+        let header_len = 4 + 4 + 4 + 32;
+        let mut corrupt = v[..header_len].to_vec();
+        corrupt.push(TAG_PUSH_NAMESPACE);
+        corrupt.extend_from_slice(&u32::MAX.to_be_bytes());
+        corrupt.extend_from_slice(b"gadget");
+        assert_eq!(Trace::<Bls12>::read(&corrupt[..]).unwrap().events.len(), 0);
+    }
+}
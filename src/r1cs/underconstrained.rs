@@ -0,0 +1,458 @@
+//! Detection of auxillary variables that the constraints don't determine.
+//!
+//! Starting from a satisfying witness, every auxillary variable is checked for
+//! alternative assignments that keep all constraints satisfied while the public
+//! inputs stay fixed. Such a variable means the circuit accepts several
+//! witnesses for the same statement, which is usually a soundness bug.
+//!
+//! Two checks are combined. First the constraints are linearised around the
+//! witness; if the unit vector of a variable is not in the row space of the
+//! Jacobian, the variable can move at first order, and a random step along
+//! such a direction is tried on the actual constraints. Variables pinned at
+//! first order are then checked for a second root of the quadratic
+//! constraints they appear in, which catches square roots of unknown sign
+//! and bits whose only constraint is booleanity.
+
+use crate::pairing::{
+    Engine
+};
+
+use crate::pairing::ff::{
+    Field,
+    ScalarEngine
+};
+
+use crate::{
+    SynthesisError,
+    Circuit,
+    Index
+};
+
+use super::{
+    R1CS,
+    Constraint,
+    evaluate
+};
+
+use rand::{Rng, Rand};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Why a variable is suspected to be under-constrained.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Evidence {
+    /// Changing only this variable keeps every constraint satisfied
+    AlternativeValue,
+    /// Changing this variable together with others keeps every constraint satisfied
+    AlternativeWitness,
+    /// The linearised constraints don't determine the variable, but
+    /// no alternative witness was found
+    RankDeficient
+}
+
+/// An auxillary variable that is not uniquely determined by the
+/// public inputs.
+#[derive(Clone)]
+pub struct Suspect<E: Engine> {
+    pub index: usize,
+
+    // Full namespace path of the variable
+    pub path: String,
+
+    pub evidence: Evidence,
+
+    // Auxillary assignment of an alternative satisfying witness, if one was found
+    pub alternative: Option<Vec<E::Fr>>
+}
+
+/// Checks every auxillary variable of the recorded constraint system, which
+/// must carry a satisfying witness. Suspects are returned in variable order.
+pub fn find_underconstrained<E: Engine, R: Rng>(
+    r1cs: &R1CS<E>,
+    rng: &mut R
+) -> Result<Vec<Suspect<E>>, SynthesisError>
+{
+    r1cs.check_satisfied()?;
+
+    let input_assignment = r1cs.input_assignment.as_ref().unwrap();
+    let aux_assignment = r1cs.aux_assignment.as_ref().unwrap();
+
+    // Constraints in which each auxillary variable appears
+    let mut touching = vec![vec![]; r1cs.num_aux()];
+    for (i, constraint) in r1cs.constraints.iter().enumerate() {
+        for &(index, _) in constraint.a.iter().chain(constraint.b.iter()).chain(constraint.c.iter()) {
+            if let Index::Aux(j) = index {
+                if touching[j].last() != Some(&i) {
+                    touching[j].push(i);
+                }
+            }
+        }
+    }
+
+    let mut basis = Basis::<E>::new();
+    for constraint in r1cs.constraints.iter() {
+        basis.insert(linearize(constraint, input_assignment, aux_assignment));
+    }
+
+    let mut suspects = vec![];
+    for v in 0..r1cs.num_aux() {
+        let found = match basis.null_direction(v) {
+            Some(direction) => {
+                let evidence = if direction.len() == 1 {
+                    Evidence::AlternativeValue
+                } else {
+                    Evidence::AlternativeWitness
+                };
+
+                match perturb(r1cs, &touching, &direction, rng) {
+                    Some(alternative) => Some((evidence, Some(alternative))),
+                    None => Some((Evidence::RankDeficient, None))
+                }
+            },
+            None => {
+                other_root(r1cs, &touching[v], v).map(|alternative| (Evidence::AlternativeValue, Some(alternative)))
+            }
+        };
+
+        if let Some((evidence, alternative)) = found {
+            suspects.push(Suspect {
+                index: v,
+                path: r1cs.aux_names[v].clone(),
+                evidence: evidence,
+                alternative: alternative
+            });
+        }
+    }
+
+    Ok(suspects)
+}
+
+/// Synthesizes the circuit together with its witness and
+/// checks it with `find_underconstrained`.
+pub fn find_underconstrained_in_circuit<E, C, R>(
+    circuit: C,
+    rng: &mut R
+) -> Result<Vec<Suspect<E>>, SynthesisError>
+    where E: Engine, C: Circuit<E>, R: Rng
+{
+    let r1cs = R1CS::record_with_witness(circuit)?;
+
+    find_underconstrained(&r1cs, rng)
+}
+
+/// A sparse row, indexed by auxillary variable.
+type Row<E> = BTreeMap<usize, <E as ScalarEngine>::Fr>;
+
+/// Subtracts `coeff * other` from `row`, dropping cancelled entries.
+fn sub_scaled<E: Engine>(row: &mut Row<E>, other: &Row<E>, coeff: &E::Fr) {
+    for (&column, value) in other.iter() {
+        let mut tmp = *value;
+        tmp.mul_assign(coeff);
+
+        let cancelled = {
+            let entry = row.entry(column).or_insert(E::Fr::zero());
+            entry.sub_assign(&tmp);
+            entry.is_zero()
+        };
+        if cancelled {
+            row.remove(&column);
+        }
+    }
+}
+
+/// The gradient of `A * B - C` with respect to the auxillary variables,
+/// evaluated at the witness. Public inputs are fixed, so they don't appear.
+fn linearize<E: Engine>(
+    constraint: &Constraint<E>,
+    input_assignment: &[E::Fr],
+    aux_assignment: &[E::Fr]
+) -> Row<E>
+{
+    let a = evaluate::<E>(&constraint.a, input_assignment, aux_assignment);
+    let b = evaluate::<E>(&constraint.b, input_assignment, aux_assignment);
+
+    let mut minus_one = E::Fr::one();
+    minus_one.negate();
+
+    let mut row = Row::<E>::new();
+    for (terms, scale) in [(&constraint.a, b), (&constraint.b, a), (&constraint.c, minus_one)].iter() {
+        for &(index, coeff) in terms.iter() {
+            if let Index::Aux(j) = index {
+                let mut tmp = coeff;
+                tmp.mul_assign(scale);
+
+                let cancelled = {
+                    let entry = row.entry(j).or_insert(E::Fr::zero());
+                    entry.add_assign(&tmp);
+                    entry.is_zero()
+                };
+                if cancelled {
+                    row.remove(&j);
+                }
+            }
+        }
+    }
+
+    row
+}
+
+/// Row space of the Jacobian in reduced row echelon form.
+struct Basis<E: Engine> {
+    // Rows by pivot column. Every row has a one at its pivot
+    // and no entries in the pivot columns of other rows.
+    rows: BTreeMap<usize, Row<E>>
+}
+
+impl<E: Engine> Basis<E> {
+    fn new() -> Self {
+        Basis {
+            rows: BTreeMap::new()
+        }
+    }
+
+    fn insert(&mut self, mut row: Row<E>) {
+        let pivots: Vec<usize> = row.keys().filter(|c| self.rows.contains_key(c)).cloned().collect();
+        for pivot in pivots {
+            let coeff = row[&pivot];
+            sub_scaled::<E>(&mut row, &self.rows[&pivot], &coeff);
+        }
+
+        let (pivot, coeff) = match row.iter().next() {
+            Some((&pivot, &coeff)) => (pivot, coeff),
+            None => return
+        };
+
+        let inverse = coeff.inverse().unwrap();
+        for value in row.values_mut() {
+            value.mul_assign(&inverse);
+        }
+
+        for other in self.rows.values_mut() {
+            if let Some(coeff) = other.get(&pivot).cloned() {
+                sub_scaled::<E>(other, &row, &coeff);
+            }
+        }
+
+        self.rows.insert(pivot, row);
+    }
+
+    /// A vector in the null space that moves `v`, or `None` if the
+    /// unit vector of `v` is in the row space.
+    fn null_direction(&self, v: usize) -> Option<Row<E>> {
+        let free = match self.rows.get(&v) {
+            None => v,
+            Some(row) => match row.keys().find(|&&column| column != v) {
+                Some(&column) => column,
+                None => return None
+            }
+        };
+
+        let mut direction = Row::<E>::new();
+        direction.insert(free, E::Fr::one());
+        for (&pivot, row) in self.rows.iter() {
+            if let Some(coeff) = row.get(&free) {
+                let mut coeff = *coeff;
+                coeff.negate();
+                direction.insert(pivot, coeff);
+            }
+        }
+
+        Some(direction)
+    }
+}
+
+/// Takes a random step along the direction and returns the new auxillary
+/// assignment if it still satisfies every constraint.
+fn perturb<E: Engine, R: Rng>(
+    r1cs: &R1CS<E>,
+    touching: &[Vec<usize>],
+    direction: &Row<E>,
+    rng: &mut R
+) -> Option<Vec<E::Fr>>
+{
+    let input_assignment = r1cs.input_assignment.as_ref().unwrap();
+    let mut aux_assignment = r1cs.aux_assignment.clone().unwrap();
+
+    let mut step = E::Fr::rand(rng);
+    while step.is_zero() {
+        step = E::Fr::rand(rng);
+    }
+
+    let mut affected = BTreeSet::new();
+    for (&j, coeff) in direction.iter() {
+        let mut tmp = *coeff;
+        tmp.mul_assign(&step);
+        aux_assignment[j].add_assign(&tmp);
+
+        affected.extend(touching[j].iter().cloned());
+    }
+
+    if affected.iter().all(|&i| r1cs.constraints[i].is_satisfied(input_assignment, &aux_assignment)) {
+        Some(aux_assignment)
+    } else {
+        None
+    }
+}
+
+/// Looks for a second value of `v` alone that satisfies the constraints.
+/// With everything else fixed each constraint is a polynomial of degree
+/// at most two in `v`, so the other root follows from the known one.
+fn other_root<E: Engine>(
+    r1cs: &R1CS<E>,
+    touching: &[usize],
+    v: usize
+) -> Option<Vec<E::Fr>>
+{
+    let input_assignment = r1cs.input_assignment.as_ref().unwrap();
+    let aux_assignment = r1cs.aux_assignment.as_ref().unwrap();
+    let value = aux_assignment[v];
+
+    // Splits a linear combination into `x0 + x1 * v`
+    let split = |terms: &[(Index, E::Fr)]| {
+        let mut x1 = E::Fr::zero();
+        for &(index, ref coeff) in terms.iter() {
+            if index == Index::Aux(v) {
+                x1.add_assign(coeff);
+            }
+        }
+
+        let mut x0 = evaluate::<E>(terms, input_assignment, aux_assignment);
+        let mut tmp = x1;
+        tmp.mul_assign(&value);
+        x0.sub_assign(&tmp);
+
+        (x0, x1)
+    };
+
+    let mut candidate = None;
+    for &i in touching.iter() {
+        let constraint = &r1cs.constraints[i];
+        let (a0, a1) = split(&constraint.a);
+        let (b0, b1) = split(&constraint.b);
+        let (_, c1) = split(&constraint.c);
+
+        // a1 * b1 * v^2 + (a0 * b1 + a1 * b0 - c1) * v + ... = 0
+        let mut p2 = a1;
+        p2.mul_assign(&b1);
+
+        let mut p1 = a0;
+        p1.mul_assign(&b1);
+        let mut tmp = a1;
+        tmp.mul_assign(&b0);
+        p1.add_assign(&tmp);
+        p1.sub_assign(&c1);
+
+        if p2.is_zero() {
+            if p1.is_zero() {
+                continue;
+            }
+
+            // Linear in `v`, so the known root is the only one
+            return None;
+        }
+
+        if candidate.is_none() {
+            // The roots sum up to -p1 / p2
+            let mut root = p1;
+            root.mul_assign(&p2.inverse().unwrap());
+            root.negate();
+            root.sub_assign(&value);
+
+            if root == value {
+                return None;
+            }
+
+            candidate = Some(root);
+        }
+    }
+
+    let mut alternative = aux_assignment.clone();
+    alternative[v] = candidate?;
+
+    if touching.iter().all(|&i| r1cs.constraints[i].is_satisfied(input_assignment, &alternative)) {
+        Some(alternative)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pairing::bls12_381::{Bls12, Fr};
+    use crate::pairing::ff::PrimeField;
+    use crate::ConstraintSystem;
+
+    use rand::thread_rng;
+
+    struct LeakyCircuit;
+
+    impl Circuit<Bls12> for LeakyCircuit {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS
+        ) -> Result<(), SynthesisError>
+        {
+            let c = cs.alloc_input(|| "c", || Ok(Fr::from_str("9").unwrap()))?;
+
+            // The sign of the square root is not fixed
+            {
+                let mut cs = cs.namespace(|| "sqrt");
+                let a = cs.alloc(|| "a", || Ok(Fr::from_str("3").unwrap()))?;
+                cs.enforce(|| "square", |lc| lc + a, |lc| lc + a, |lc| lc + c);
+            }
+
+            let b = cs.alloc(|| "b", || Ok(Fr::from_str("9").unwrap()))?;
+            cs.enforce(|| "copy", |lc| lc + b, |lc| lc + CS::one(), |lc| lc + c);
+
+            // Only the sum is constrained
+            let u = cs.alloc(|| "u", || Ok(Fr::from_str("4").unwrap()))?;
+            let w = cs.alloc(|| "w", || Ok(Fr::from_str("5").unwrap()))?;
+            cs.enforce(|| "sum", |lc| lc + u + w, |lc| lc + CS::one(), |lc| lc + c);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_find_underconstrained() {
+        let rng = &mut thread_rng();
+
+        let suspects = find_underconstrained_in_circuit(LeakyCircuit, rng).unwrap();
+
+        let found: Vec<_> = suspects.iter().map(|s| (s.path.as_str(), s.evidence)).collect();
+        assert_eq!(found, vec![
+            ("sqrt/a", Evidence::AlternativeValue),
+            ("u", Evidence::AlternativeWitness),
+            ("w", Evidence::AlternativeWitness)
+        ]);
+
+        let mut minus_three = Fr::from_str("3").unwrap();
+        minus_three.negate();
+        assert_eq!(suspects[0].alternative.as_ref().unwrap()[0], minus_three);
+
+        let r1cs = R1CS::<Bls12>::record_with_witness(LeakyCircuit).unwrap();
+        for suspect in suspects.iter() {
+            let mut alternative = r1cs.clone();
+            alternative.aux_assignment = suspect.alternative.clone();
+            assert!(alternative.check_satisfied().is_ok());
+            assert!(alternative.aux_assignment != r1cs.aux_assignment);
+        }
+    }
+
+    #[test]
+    fn test_xordemo_is_fully_constrained() {
+        use crate::tests::XORDemo;
+        use std::marker::PhantomData;
+
+        let rng = &mut thread_rng();
+
+        let suspects = find_underconstrained_in_circuit(XORDemo::<Bls12> {
+            a: Some(true),
+            b: Some(false),
+            _marker: PhantomData
+        }, rng).unwrap();
+
+        assert!(suspects.is_empty());
+    }
+}
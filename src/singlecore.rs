@@ -11,6 +11,8 @@ use std::pin::{Pin};
 use self::futures::channel::oneshot::{channel, Sender, Receiver};
 use self::futures::executor::{block_on};
 
+use crate::SynthesisError;
+
 #[derive(Clone)]
 pub struct Worker {
     cpus: usize,
@@ -30,6 +32,10 @@ impl Worker {
         Self::new_with_cpus(1)
     }
 
+    pub fn try_new() -> Result<Worker, SynthesisError> {
+        Ok(Self::new())
+    }
+
     pub fn log_num_cpus(&self) -> u32 {
         0u32
     }
@@ -68,6 +74,16 @@ impl Worker {
 
         f(&scope, chunk_size)
     }
+
+    pub fn try_scope<'a, F, R>(
+        &self,
+        elements: usize,
+        f: F
+    ) -> Result<R, SynthesisError>
+        where F: FnOnce(&Scope<'a>, usize) -> R
+    {
+        Ok(self.scope(elements, f))
+    }
 }
 #[derive(Clone)]
 pub struct Scope<'a> {
@@ -114,6 +130,15 @@ impl<T: Send + 'static, E: Send + 'static> WorkerFuture<T, E> {
     pub fn wait(self) -> <Self as Future>::Output {
         block_on(self)
     }
+
+    pub fn try_wait(self) -> Result<T, E>
+        where E: From<SynthesisError>
+    {
+        match block_on(self.receiver) {
+            Ok(result) => result,
+            Err(_) => Err(SynthesisError::WorkerFailure.into())
+        }
+    }
 }
 
 
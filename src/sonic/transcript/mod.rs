@@ -2,6 +2,8 @@ use crate::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
 use crate::pairing::{CurveAffine, CurveProjective, Engine};
 use std::io;
 
+use crate::SynthesisError;
+
 mod hasher;
 
 use self::hasher::{Hasher, Keccak256Hasher, BlakeHasher};
@@ -28,8 +30,8 @@ impl TranscriptProtocol for Transcript {
         self.transcriptor.commit_scalar(scalar);
     }
 
-    fn get_challenge_scalar<F: PrimeField>(&mut self) -> F {
-        self.transcriptor.get_challenge_scalar()
+    fn try_get_challenge_scalar<F: PrimeField>(&mut self) -> Result<F, SynthesisError> {
+        self.transcriptor.try_get_challenge_scalar()
     }
 }
 
@@ -81,7 +83,11 @@ impl<H: Hasher> RollingHashTranscript<H> {
 pub trait TranscriptProtocol {
     fn commit_point<G: CurveAffine>(&mut self, point: &G);
     fn commit_scalar<F: PrimeField>(&mut self, scalar: &F);
-    fn get_challenge_scalar<F: PrimeField>(&mut self) -> F;
+    fn try_get_challenge_scalar<F: PrimeField>(&mut self) -> Result<F, SynthesisError>;
+
+    fn get_challenge_scalar<F: PrimeField>(&mut self) -> F {
+        self.try_get_challenge_scalar().expect("can not make challenge scalar")
+    }
 }
 
 impl<H:Hasher> TranscriptProtocol for RollingHashTranscript<H> {
@@ -100,7 +106,7 @@ impl<H:Hasher> TranscriptProtocol for RollingHashTranscript<H> {
         self.repeated_request_nonce = 0u32;
     }
 
-    fn get_challenge_scalar<F: PrimeField>(&mut self) -> F {
+    fn try_get_challenge_scalar<F: PrimeField>(&mut self) -> Result<F, SynthesisError> {
         use byteorder::ByteOrder;
         let mut nonce = self.repeated_request_nonce;
         loop {
@@ -108,15 +114,15 @@ impl<H:Hasher> TranscriptProtocol for RollingHashTranscript<H> {
             byteorder::BigEndian::write_u32(&mut nonce_bytes, nonce);
             let mut repr: F::Repr = Default::default();
             let challenge_bytes = self.get_challenge_bytes(&nonce_bytes);
-            repr.read_be(&challenge_bytes[..]).unwrap();
+            repr.read_be(&challenge_bytes[..])?;
 
             if let Ok(result) = F::from_repr(repr) {
                 // println!("Got a challenge {} for nonce = {}", result, nonce);
-                self.repeated_request_nonce = nonce + 1u32;
-                return result;
+                self.repeated_request_nonce = nonce.wrapping_add(1u32);
+                return Ok(result);
             }
             if nonce == (0xffffffff as u32) {
-                panic!("can not make challenge scalar");
+                return Err(SynthesisError::ChallengeFailure);
             }
             nonce += 1;
         }
@@ -91,3 +91,46 @@ impl<E: Engine> Circuit<E> for XORDemo<E> {
     }
 }
 
+#[test]
+fn test_linear_combination_is_canonical() {
+    use crate::{LinearCombination, Variable, Index};
+
+    let a = Variable::new_unchecked(Index::Aux(0));
+    let b = Variable::new_unchecked(Index::Aux(1));
+    let one = Variable::new_unchecked(Index::Input(0));
+
+    let two = Fr::from_str("2").unwrap();
+    let three = Fr::from_str("3").unwrap();
+
+    // Terms are merged and sorted with inputs first
+    let lc = LinearCombination::<DummyEngine>::zero() + b + a + (two, b) + one;
+    assert_eq!(lc.as_ref(), &[(one, Fr::one()), (a, Fr::one()), (b, three)][..]);
+
+    // Cancelling terms disappear
+    let lc = lc - a - (three, b);
+    assert_eq!(lc.as_ref(), &[(one, Fr::one())][..]);
+
+    let lc = LinearCombination::<DummyEngine>::zero() + a - b;
+    let mut minus_two = two;
+    minus_two.negate();
+    assert_eq!((lc.clone() * two).as_ref(), &[(a, two), (b, minus_two)][..]);
+    assert!((lc.clone() * Fr::zero()).is_empty());
+
+    let mut minus_one = Fr::one();
+    minus_one.negate();
+    assert_eq!((-lc.clone()).as_ref(), &[(a, minus_one), (b, Fr::one())][..]);
+
+    let lc = lc.clone() - &lc;
+    assert!(lc.is_empty());
+
+    // Combinations with interleaved variables are merged in order
+    let vars: Vec<Variable> = (0..6).map(|i| Variable::new_unchecked(Index::Aux(i))).collect();
+    let evens = vars.iter().step_by(2).fold(LinearCombination::<DummyEngine>::zero() + one, |lc, &v| lc + v);
+    let odds = vars.iter().skip(1).step_by(2).fold(LinearCombination::<DummyEngine>::zero(), |lc, &v| lc + v);
+    let all = evens.clone() + (two, &odds) - &odds;
+    let expected: Vec<_> = Some(one).into_iter().chain(vars.iter().cloned()).map(|v| (v, Fr::one())).collect();
+    assert_eq!(all.as_ref(), &expected[..]);
+
+    let lc = all - (three, &evens) + (two, &evens);
+    assert_eq!(lc.as_ref(), odds.as_ref());
+}
@@ -0,0 +1,137 @@
+// For randomness (during paramgen and proof generation)
+use rand::{thread_rng, Rng};
+
+// Bring in some tools for using pairing-friendly curves
+use bellman_ce::pairing::{
+    Engine
+};
+
+use bellman_ce::pairing::ff::{
+    Field,
+};
+
+use bellman_ce::pairing::bls12_381::{
+    Bls12
+};
+
+use bellman_ce::pairing::bn256::{
+    Bn256
+};
+
+// We'll use these interfaces to construct our circuit.
+use bellman_ce::{
+    Circuit,
+    ConstraintSystem,
+    SynthesisError
+};
+
+// The MiMC gadget and its native counterpart.
+use bellman_ce::gadgets::expr::Expr;
+use bellman_ce::gadgets::mimc::{
+    MiMCParams,
+    circuit,
+    native
+};
+
+// We're going to use the Groth16 proving system.
+use bellman_ce::groth16::{
+    Proof,
+    generate_random_parameters,
+    prepare_verifying_key,
+    create_random_proof,
+    verify_proof,
+};
+
+/// This circuit proves knowledge of the preimage of a MiMC
+/// encryption under the zero key, with the parameters of
+/// circomlib, using the MiMC gadget.
+#[derive(Clone)]
+struct MiMCGadgetDemo<'a, E: Engine> {
+    xl: Option<E::Fr>,
+    xr: Option<E::Fr>,
+    params: &'a MiMCParams<E>
+}
+
+impl<'a, E: Engine> Circuit<E> for MiMCGadgetDemo<'a, E> {
+    fn synthesize<CS: ConstraintSystem<E>>(
+        self,
+        cs: &mut CS
+    ) -> Result<(), SynthesisError>
+    {
+        // Allocate the preimage.
+        let xl_value = self.xl;
+        let xl = Expr::alloc(cs.namespace(|| "preimage xl"), || {
+            xl_value.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let xr_value = self.xr;
+        let xr = Expr::alloc(cs.namespace(|| "preimage xr"), || {
+            xr_value.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let (_, image) = circuit::feistel(cs.namespace(|| "mimc"), self.params, &xl, &xr, &Expr::zero())?;
+
+        // The right half of the output is our image, and so
+        // we expose it as a public input.
+        let input = Expr::alloc_input(cs.namespace(|| "image"), || {
+            image.get_value().ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        image.assert_eq(cs.namespace(|| "image is input"), &input)
+    }
+}
+
+fn prove_and_verify<E: Engine>(samples: usize) {
+    // This may not be cryptographically safe, use
+    // `OsRng` (for example) in production software.
+    let rng = &mut thread_rng();
+
+    let mimc_params = MiMCParams::<E>::circomlib();
+
+    let params = {
+        let c = MiMCGadgetDemo::<E> {
+            xl: None,
+            xr: None,
+            params: &mimc_params
+        };
+
+        generate_random_parameters(c, rng).unwrap()
+    };
+
+    let pvk = prepare_verifying_key(&params.vk);
+
+    let mut proof_vec = vec![];
+    for _ in 0..samples {
+        // Generate a random preimage and compute the image
+        let xl = rng.gen();
+        let xr = rng.gen();
+        let (_, image) = native::feistel::<E>(&mimc_params, xl, xr, &Field::zero());
+
+        proof_vec.truncate(0);
+
+        let c = MiMCGadgetDemo {
+            xl: Some(xl),
+            xr: Some(xr),
+            params: &mimc_params
+        };
+        let proof = create_random_proof(c, &params, rng).unwrap();
+        proof.write(&mut proof_vec).unwrap();
+
+        let proof = Proof::read(&proof_vec[..]).unwrap();
+        assert!(verify_proof(&pvk, &proof, &[image]).unwrap());
+
+        // The image of another preimage is rejected
+        let (_, other) = native::feistel::<E>(&mimc_params, xr, xl, &Field::zero());
+        assert!(!verify_proof(&pvk, &proof, &[other]).unwrap());
+    }
+}
+
+#[test]
+fn test_mimc_gadget_bls12() {
+    prove_and_verify::<Bls12>(1);
+}
+
+#[test]
+fn test_mimc_gadget_bn256() {
+    prove_and_verify::<Bn256>(5);
+}